@@ -46,3 +46,57 @@ db.direct_message_items.createIndex(
   { "author_id": 1, "created_at": -1 },
   { name: "idx_direct_message_items_author_created" }
 );
+
+// Déduplication des envois rejoués : un nonce client par auteur
+db.channel_messages.createIndex(
+  { "author_id": 1, "nonce": 1 },
+  {
+    name: "idx_channel_messages_author_nonce",
+    unique: true,
+    partialFilterExpression: { "nonce": { $type: "string" } }
+  }
+);
+
+db.direct_message_items.createIndex(
+  { "author_id": 1, "nonce": 1 },
+  {
+    name: "idx_direct_message_items_author_nonce",
+    unique: true,
+    partialFilterExpression: { "nonce": { $type: "string" } }
+  }
+);
+
+// Messages épinglés d'un channel ou d'une conversation, les plus récents d'abord
+db.channel_messages.createIndex(
+  { "channel_id": 1, "pinned_at": -1 },
+  {
+    name: "idx_channel_messages_channel_pinned",
+    partialFilterExpression: { "pinned_at": { $type: "date" } }
+  }
+);
+
+db.direct_message_items.createIndex(
+  { "dm_id": 1, "pinned_at": -1 },
+  {
+    name: "idx_direct_message_items_dm_pinned",
+    partialFilterExpression: { "pinned_at": { $type: "date" } }
+  }
+);
+
+// Recherche plein texte (un seul index texte par collection) ; sans langue, donc sans
+// racinisation ni mots vides, le contenu mêlant français et anglais
+db.channel_messages.createIndex(
+  { "content": "text" },
+  { name: "idx_channel_messages_content_text", default_language: "none" }
+);
+
+db.direct_message_items.createIndex(
+  { "content": "text" },
+  { name: "idx_direct_message_items_content_text", default_language: "none" }
+);
+
+// Opérateur de recherche mentions:
+db.channel_messages.createIndex(
+  { "mentions": 1, "created_at": -1 },
+  { name: "idx_channel_messages_mentions_created" }
+);
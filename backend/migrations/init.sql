@@ -224,6 +224,95 @@ CREATE INDEX IF NOT EXISTS idx_server_bans_user ON server_bans(user_id);
 CREATE INDEX IF NOT EXISTS idx_server_bans_server ON server_bans(server_id);
 CREATE INDEX IF NOT EXISTS idx_server_bans_expires ON server_bans(expires_at);
 
+-- ROLES (rôles personnalisés par serveur, permissions en champ de bits)
+-- Le rôle par défaut (@everyone, position 0) s'applique à tous les membres
+CREATE TABLE IF NOT EXISTS roles (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    server_id UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL CHECK (char_length(trim(name)) > 0),
+    color INT NOT NULL DEFAULT 0 CHECK (color BETWEEN 0 AND 16777215),
+    position INT NOT NULL DEFAULT 0 CHECK (position >= 0),
+    permissions BIGINT NOT NULL DEFAULT 0,
+    is_default BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS uq_roles_server_default ON roles(server_id) WHERE is_default;
+CREATE INDEX IF NOT EXISTS idx_roles_server ON roles(server_id);
+
+-- MEMBER ROLES (attribution membre <-> rôle, supprimée avec le membre)
+CREATE TABLE IF NOT EXISTS member_roles (
+    server_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    PRIMARY KEY (server_id, user_id, role_id),
+    FOREIGN KEY (server_id, user_id) REFERENCES server_members(server_id, user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_member_roles_role ON member_roles(role_id);
+
+-- Migration : chaque serveur sans rôle par défaut reçoit @everyone, et ses admins
+-- (server_members.role = 'admin') un rôle "Admin" équivalent (Permissions::LEGACY_ADMIN)
+DO $$
+DECLARE
+    s RECORD;
+    admin_role_id UUID;
+BEGIN
+    FOR s IN
+        SELECT sv.id FROM servers sv
+        WHERE NOT EXISTS (SELECT 1 FROM roles r WHERE r.server_id = sv.id AND r.is_default)
+    LOOP
+        INSERT INTO roles (server_id, name, position, permissions, is_default)
+        VALUES (s.id, '@everyone', 0, 3840, TRUE);
+
+        IF EXISTS (SELECT 1 FROM server_members WHERE server_id = s.id AND role = 'admin') THEN
+            INSERT INTO roles (server_id, name, position, permissions)
+            VALUES (s.id, 'Admin', 1, 250)
+            RETURNING id INTO admin_role_id;
+
+            INSERT INTO member_roles (server_id, user_id, role_id)
+            SELECT server_id, user_id, admin_role_id
+            FROM server_members
+            WHERE server_id = s.id AND role = 'admin';
+        END IF;
+    END LOOP;
+END $$;
+
+-- SCHEMA MIGRATIONS (migrations de données à n'appliquer qu'une seule fois)
+CREATE TABLE IF NOT EXISTS schema_migrations (
+    name VARCHAR(100) PRIMARY KEY,
+    applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Permissions de channel : @everyone reçoit VIEW_CHANNEL, SEND_MESSAGES, ADD_REACTIONS
+-- et ATTACH_FILES (Permissions::DEFAULT_EVERYONE) pour garder l'accès existant
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM schema_migrations WHERE name = 'channel_permissions') THEN
+        UPDATE roles SET permissions = permissions | 3840 WHERE is_default;
+        INSERT INTO schema_migrations (name) VALUES ('channel_permissions');
+    END IF;
+END $$;
+
+DO $$ BEGIN
+CREATE TYPE overwrite_target AS ENUM ('role', 'member');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+-- CHANNEL OVERWRITES (permissions par rôle ou par membre dans un channel)
+CREATE TABLE IF NOT EXISTS channel_overwrites (
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    target_type overwrite_target NOT NULL,
+    target_id UUID NOT NULL,
+    allow BIGINT NOT NULL DEFAULT 0,
+    deny BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (channel_id, target_type, target_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_channel_overwrites_target ON channel_overwrites(target_id);
+
 -- DIRECT MESSAGES (private conversations)
 CREATE TABLE IF NOT EXISTS direct_messages (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
@@ -269,3 +358,86 @@ CREATE TABLE IF NOT EXISTS attachments (
 );
 
 CREATE INDEX IF NOT EXISTS idx_attachments_sender ON attachments(sender_id);
+
+-- GATEWAY EVENTS (événements WebSocket trop gros pour un NOTIFY, référencés par id)
+CREATE TABLE IF NOT EXISTS gateway_events (
+    id BIGSERIAL PRIMARY KEY,
+    payload TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_gateway_events_created_at ON gateway_events(created_at);
+
+-- AUDIT LOG (actions de modération et d'administration d'un serveur)
+CREATE TABLE IF NOT EXISTS audit_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    server_id UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    action TEXT NOT NULL,
+    target_id UUID,
+    before JSONB,
+    after JSONB,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_server_created ON audit_log(server_id, created_at DESC, id DESC);
+
+-- CATEGORIES : un channel de type 'category' regroupe les channels dont il est le parent
+ALTER TABLE channels
+ADD COLUMN IF NOT EXISTS kind TEXT NOT NULL DEFAULT 'text';
+
+ALTER TABLE channels
+ADD COLUMN IF NOT EXISTS parent_id UUID REFERENCES channels(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_channels_parent ON channels(parent_id);
+
+-- SLOWMODE : délai minimum (secondes) entre deux messages d'un membre, 0 = désactivé
+ALTER TABLE channels
+ADD COLUMN IF NOT EXISTS rate_limit_per_user INTEGER NOT NULL DEFAULT 0
+    CHECK (rate_limit_per_user BETWEEN 0 AND 21600);
+
+-- ANNONCES SUIVIES : les messages d'un channel d'annonces sont recopiés dans les channels abonnés
+CREATE TABLE IF NOT EXISTS channel_followers (
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    target_channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (channel_id, target_channel_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_channel_followers_target ON channel_followers(target_channel_id);
+
+-- TIMEOUTS : le membre ne peut plus écrire ni réagir jusqu'à cette date
+ALTER TABLE server_members
+ADD COLUMN IF NOT EXISTS communication_disabled_until TIMESTAMPTZ;
+
+-- PROFIL PAR SERVEUR : pseudo et avatar affichés à la place du profil global
+ALTER TABLE server_members
+ADD COLUMN IF NOT EXISTS nickname TEXT,
+ADD COLUMN IF NOT EXISTS avatar_url TEXT;
+
+-- THREADS : channel de type 'thread' ouvert depuis un message de son channel parent (parent_id)
+-- Il reprend les permissions du parent et s'archive après auto_archive_minutes sans message
+CREATE TABLE IF NOT EXISTS threads (
+    channel_id UUID PRIMARY KEY REFERENCES channels(id) ON DELETE CASCADE,
+    parent_message_id UUID NOT NULL UNIQUE,
+    owner_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    auto_archive_minutes INTEGER NOT NULL DEFAULT 1440
+        CHECK (auto_archive_minutes IN (60, 1440, 4320, 10080)),
+    archived BOOLEAN NOT NULL DEFAULT FALSE,
+    archived_at TIMESTAMPTZ,
+    last_activity_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_threads_active ON threads(last_activity_at) WHERE NOT archived;
+
+-- Membres d'un thread : ceux qui le suivent (créateur, participants, abonnés explicites)
+CREATE TABLE IF NOT EXISTS thread_members (
+    thread_id UUID NOT NULL REFERENCES threads(channel_id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (thread_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_thread_members_user ON thread_members(user_id);
@@ -31,18 +31,28 @@ pub enum Error {
     ServerForbidden,
     #[error("You are banned from this server")]
     ServerBanned,
+    #[error("You are timed out in this server")]
+    MemberTimedOut,
     #[error("Owner cannot leave server")]
     ServerOwnerCannotLeave,
     #[error("Already a member")]
     ServerAlreadyMember,
+    #[error("Role not found")]
+    RoleNotFound,
     #[error("Channel not found")]
     ChannelNotFound,
     #[error("Channel access forbidden")]
     ChannelForbidden,
+    #[error("Permission overwrite not found")]
+    OverwriteNotFound,
     #[error("Message not found")]
     MessageNotFound,
     #[error("Message access forbidden")]
     MessageForbidden,
+    #[error("A thread already exists for this message")]
+    ThreadAlreadyExists,
+    #[error("Slowmode is active, retry in {retry_after} seconds")]
+    SlowmodeActive { retry_after: u64 },
     #[error("Bad request: {message}")]
     BadRequest { message: String },
     #[error("Database error: {message}")]
@@ -89,6 +99,9 @@ impl IntoResponse for Error {
             Self::BadRequest { message } => {
                 body["details"] = serde_json::json!(message);
             }
+            Self::SlowmodeActive { retry_after } => {
+                body["retry_after"] = serde_json::json!(retry_after);
+            }
             Self::DatabaseError { message } => {
                 body["details"] = serde_json::json!(message);
             }
@@ -121,12 +134,20 @@ impl Error {
             ),
             Self::ServerForbidden => (StatusCode::FORBIDDEN, "Server access forbidden"),
             Self::ServerBanned => (StatusCode::FORBIDDEN, "You are banned from this server"),
+            Self::MemberTimedOut => (StatusCode::FORBIDDEN, "You are timed out in this server"),
             Self::ServerOwnerCannotLeave => (StatusCode::BAD_REQUEST, "Owner cannot leave server"),
             Self::ServerAlreadyMember => (StatusCode::CONFLICT, "Already a member"),
+            Self::RoleNotFound => (StatusCode::NOT_FOUND, "Role not found"),
             Self::ChannelNotFound => (StatusCode::NOT_FOUND, "Channel not found"),
             Self::ChannelForbidden => (StatusCode::FORBIDDEN, "Channel access forbidden"),
+            Self::OverwriteNotFound => (StatusCode::NOT_FOUND, "Permission overwrite not found"),
             Self::MessageNotFound => (StatusCode::NOT_FOUND, "Message not found"),
             Self::MessageForbidden => (StatusCode::FORBIDDEN, "Message access forbidden"),
+            Self::ThreadAlreadyExists => (
+                StatusCode::CONFLICT,
+                "A thread already exists for this message",
+            ),
+            Self::SlowmodeActive { .. } => (StatusCode::TOO_MANY_REQUESTS, "Slowmode is active"),
             Self::BadRequest { .. } => (StatusCode::BAD_REQUEST, "Bad request"),
             Self::DatabaseError { .. } => (StatusCode::INTERNAL_SERVER_ERROR, "Database error"),
             Self::InternalError { .. } => (StatusCode::INTERNAL_SERVER_ERROR, "Internal error"),
@@ -7,7 +7,10 @@ use uuid::Uuid;
 
 use crate::ctx::Ctx;
 use crate::error::Result;
-use crate::models::{Channel, CreateChannelPayload, UpdateChannelPayload};
+use crate::models::{
+    Channel, ChannelFollower, ChannelOverwrite, ChannelOverwritePayload, ChannelPositionPayload,
+    CreateChannelPayload, FollowChannelPayload, OverwriteTarget, UpdateChannelPayload,
+};
 use crate::services;
 use crate::AppState;
 
@@ -17,22 +20,37 @@ pub async fn create_channel(
     Path(server_id): Path<Uuid>,
     Json(payload): Json<CreateChannelPayload>,
 ) -> Result<Json<Channel>> {
-    let channel = services::create_channel(
+    let channel =
+        services::realtime::handle_create_channel(&state, ctx.user_id(), server_id, payload)
+            .await?;
+    Ok(Json(channel))
+}
+
+pub async fn list_channels(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(server_id): Path<Uuid>,
+) -> Result<Json<Vec<Channel>>> {
+    let channels = services::list_channels(
         &state.server_repo,
         &state.channel_repo,
         server_id,
         ctx.user_id(),
-        payload,
     )
     .await?;
-    Ok(Json(channel))
+    Ok(Json(channels))
 }
 
-pub async fn list_channels(
+/// Applique les déplacements puis renvoie les channels visibles dans leur nouvel ordre
+pub async fn update_channel_positions(
     State(state): State<AppState>,
     ctx: Ctx,
     Path(server_id): Path<Uuid>,
+    Json(changes): Json<Vec<ChannelPositionPayload>>,
 ) -> Result<Json<Vec<Channel>>> {
+    services::realtime::handle_update_channel_positions(&state, ctx.user_id(), server_id, changes)
+        .await?;
+
     let channels = services::list_channels(
         &state.server_repo,
         &state.channel_repo,
@@ -59,22 +77,117 @@ pub async fn update_channel(
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateChannelPayload>,
 ) -> Result<Json<Channel>> {
-    let channel = services::update_channel(
+    let channel =
+        services::realtime::handle_update_channel(&state, ctx.user_id(), id, payload).await?;
+    Ok(Json(channel))
+}
+
+pub async fn delete_channel(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    services::realtime::handle_delete_channel(&state, ctx.user_id(), id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_overwrites(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ChannelOverwrite>>> {
+    let overwrites = services::channels::list_overwrites(
         &state.server_repo,
         &state.channel_repo,
         id,
         ctx.user_id(),
+    )
+    .await?;
+    Ok(Json(overwrites))
+}
+
+pub async fn set_overwrite(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((id, target_type, target_id)): Path<(Uuid, OverwriteTarget, Uuid)>,
+    Json(payload): Json<ChannelOverwritePayload>,
+) -> Result<Json<ChannelOverwrite>> {
+    let overwrite = services::realtime::handle_set_overwrite(
+        &state,
+        ctx.user_id(),
+        id,
+        target_type,
+        target_id,
         payload,
     )
     .await?;
-    Ok(Json(channel))
+    Ok(Json(overwrite))
 }
 
-pub async fn delete_channel(
+pub async fn delete_overwrite(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((id, target_type, target_id)): Path<(Uuid, OverwriteTarget, Uuid)>,
+) -> Result<StatusCode> {
+    services::realtime::handle_delete_overwrite(&state, ctx.user_id(), id, target_type, target_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_followers(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ChannelFollower>>> {
+    let followers = services::channels::list_followers(
+        &state.server_repo,
+        &state.channel_repo,
+        id,
+        ctx.user_id(),
+    )
+    .await?;
+    Ok(Json(followers))
+}
+
+pub async fn follow_channel(
     State(state): State<AppState>,
     ctx: Ctx,
     Path(id): Path<Uuid>,
+    Json(payload): Json<FollowChannelPayload>,
+) -> Result<Json<ChannelFollower>> {
+    let follower = services::channels::follow_channel(
+        &state.server_repo,
+        &state.channel_repo,
+        id,
+        ctx.user_id(),
+        payload,
+    )
+    .await?;
+    Ok(Json(follower))
+}
+
+pub async fn unfollow_channel(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((id, target_channel_id)): Path<(Uuid, Uuid)>,
 ) -> Result<StatusCode> {
-    services::delete_channel(&state.server_repo, &state.channel_repo, id, ctx.user_id()).await?;
+    services::channels::unfollow_channel(
+        &state.server_repo,
+        &state.channel_repo,
+        id,
+        target_channel_id,
+        ctx.user_id(),
+    )
+    .await?;
     Ok(StatusCode::NO_CONTENT)
 }
+
+pub async fn list_voice_states(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<Uuid>>> {
+    let participants =
+        services::realtime::voice::list_voice_participants(&state, ctx.user_id(), id).await?;
+    Ok(Json(participants))
+}
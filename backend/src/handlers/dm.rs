@@ -1,8 +1,10 @@
 use crate::ctx::Ctx;
 use crate::models::{
-    CreateDMMessagePayload, CreateDMPayload, DMWithRecipient, DirectMessageItem,
-    DirectMessageItemResponse, MessageReactionPayload, MessageReactionPublic, UpdateMessagePayload,
+    CreateDMMessagePayload, CreateDMPayload, DMWithRecipient, DirectMessageItemResponse,
+    MessageReactionPayload, UpdateMessagePayload,
 };
+use crate::services::dm::{to_public_reactions, to_response, with_authors};
+use crate::services::realtime::broadcast_to_dm_participants;
 use crate::{AppState, Error, Result};
 use axum::{
     extract::{Path, Query, State},
@@ -31,45 +33,6 @@ fn validate_reaction_emoji(emoji: &str) -> Result<()> {
     Ok(())
 }
 
-fn to_public_reactions(
-    reactions: Vec<crate::models::MessageReaction>,
-) -> Vec<MessageReactionPublic> {
-    reactions
-        .into_iter()
-        .map(MessageReactionPublic::from)
-        .collect()
-}
-
-fn to_response(message: DirectMessageItem, username: String) -> DirectMessageItemResponse {
-    DirectMessageItemResponse {
-        id: message.message_id,
-        dm_id: message.dm_id,
-        author_id: message.author_id,
-        username,
-        content: message.content,
-        created_at: message.created_at,
-        edited_at: message.edited_at,
-        reactions: to_public_reactions(message.reactions),
-    }
-}
-
-async fn broadcast_to_dm_participants(
-    state: &AppState,
-    dm_id: Uuid,
-    event: &ServerEvent,
-) -> Result<()> {
-    let Some((user1_id, user2_id)) = state.dm_repo.get_participants(dm_id).await? else {
-        return Ok(());
-    };
-
-    state.ws_hub.send_to_user(user1_id, event).await;
-    if user2_id != user1_id {
-        state.ws_hub.send_to_user(user2_id, event).await;
-    }
-
-    Ok(())
-}
-
 #[derive(Deserialize)]
 pub struct ListDMMessagesQuery {
     pub limit: Option<i64>,
@@ -140,25 +103,56 @@ pub async fn list_messages(
             message: format!("MongoDB query failed: {}", e),
         })?;
 
-    if messages.is_empty() {
-        return Ok(Json(vec![]));
-    }
+    let response = with_authors(&state.dm_message_repo, &state.user_repo, messages).await?;
+    Ok(Json(response))
+}
 
-    let author_ids: Vec<Uuid> = messages.iter().map(|message| message.author_id).collect();
-    let usernames = state.user_repo.get_usernames_batch(&author_ids).await?;
+pub async fn list_pins(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(dm_id): Path<Uuid>,
+) -> Result<Json<Vec<DirectMessageItemResponse>>> {
+    let pins = crate::services::dm::list_pins(
+        &state.dm_repo,
+        &state.dm_message_repo,
+        &state.user_repo,
+        dm_id,
+        ctx.user_id(),
+    )
+    .await?;
+    Ok(Json(pins))
+}
 
-    let response = messages
-        .into_iter()
-        .map(|message| {
-            let username = usernames
-                .get(&message.author_id)
-                .cloned()
-                .unwrap_or_else(|| "Unknown".to_string());
-            to_response(message, username)
-        })
-        .collect();
+pub async fn pin_message(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((dm_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    crate::services::realtime::handle_set_direct_message_pinned(
+        &state,
+        ctx.user_id(),
+        dm_id,
+        message_id,
+        true,
+    )
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
 
-    Ok(Json(response))
+pub async fn unpin_message(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((dm_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    crate::services::realtime::handle_set_direct_message_pinned(
+        &state,
+        ctx.user_id(),
+        dm_id,
+        message_id,
+        false,
+    )
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn create_message(
@@ -167,57 +161,13 @@ pub async fn create_message(
     Path(dm_id): Path<Uuid>,
     Json(payload): Json<CreateDMMessagePayload>,
 ) -> Result<Json<DirectMessageItemResponse>> {
-    let content = payload.content.trim();
-    if content.is_empty() {
-        return Err(Error::BadRequest {
-            message: "Message content cannot be empty".to_string(),
-        });
-    }
-
-    if !state.dm_repo.user_has_access(dm_id, ctx.user_id()).await? {
-        return Err(Error::MessageForbidden);
-    }
-
-    let message = DirectMessageItem {
-        id: None,
-        message_id: Uuid::new_v4(),
+    let response = crate::services::realtime::handle_send_direct_message(
+        &state,
+        ctx.user_id(),
         dm_id,
-        author_id: ctx.user_id(),
-        content: content.to_string(),
-        created_at: chrono::Utc::now(),
-        edited_at: None,
-        deleted_at: None,
-        reactions: vec![],
-    };
-
-    state
-        .dm_message_repo
-        .create(&message)
-        .await
-        .map_err(|e| Error::DatabaseError {
-            message: format!("MongoDB insert failed: {}", e),
-        })?;
-
-    let username = state
-        .user_repo
-        .get_username(ctx.user_id())
-        .await?
-        .ok_or(Error::UserNotFound)?;
-
-    let response = to_response(message, username.clone());
-
-    let event = ServerEvent::DirectMessageCreate {
-        id: response.id,
-        dm_id: response.dm_id,
-        author_id: response.author_id,
-        username,
-        content: response.content.clone(),
-        created_at: response.created_at,
-        edited_at: response.edited_at,
-        reactions: response.reactions.clone(),
-    };
-
-    broadcast_to_dm_participants(&state, dm_id, &event).await?;
+        payload,
+    )
+    .await?;
 
     Ok(Json(response))
 }
@@ -284,6 +234,10 @@ pub async fn update_message(
         created_at: message.created_at,
         edited_at,
         reactions: to_public_reactions(message.reactions),
+        nonce: message.nonce,
+        reply_to: None,
+        pinned_at: message.pinned_at,
+        pinned_by: message.pinned_by,
     };
 
     if let Some(edited_at) = response.edited_at {
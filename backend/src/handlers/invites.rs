@@ -1,5 +1,6 @@
 use axum::{
     extract::{Path, State},
+    http::StatusCode,
     Json,
 };
 use uuid::Uuid;
@@ -50,19 +51,24 @@ pub async fn get_invite(
     Ok(Json(invite))
 }
 
+pub async fn revoke_invite(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(code): Path<String>,
+) -> Result<StatusCode> {
+    services::invites::revoke_invite(&state.invite_repo, &state.server_repo, &code, ctx.user_id())
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn accept_invite(
     State(state): State<AppState>,
     ctx: Ctx,
     Path(code): Path<String>,
 ) -> Result<Json<serde_json::Value>> {
     let payload = JoinServerWithCodePayload { code };
-    let server_id = services::join_server_with_code(
-        &state.invite_repo,
-        &state.server_repo,
-        payload,
-        ctx.user_id(),
-    )
-    .await?;
+    let server_id =
+        services::realtime::handle_join_server_with_code(&state, ctx.user_id(), payload).await?;
     Ok(Json(serde_json::json!({ "server_id": server_id })))
 }
 
@@ -71,12 +77,7 @@ pub async fn join_server_with_code(
     ctx: Ctx,
     Json(payload): Json<JoinServerWithCodePayload>,
 ) -> Result<Json<serde_json::Value>> {
-    let server_id = services::join_server_with_code(
-        &state.invite_repo,
-        &state.server_repo,
-        payload,
-        ctx.user_id(),
-    )
-    .await?;
+    let server_id =
+        services::realtime::handle_join_server_with_code(&state, ctx.user_id(), payload).await?;
     Ok(Json(serde_json::json!({ "server_id": server_id })))
 }
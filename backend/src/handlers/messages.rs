@@ -9,10 +9,10 @@ use uuid::Uuid;
 use crate::ctx::Ctx;
 use crate::error::Result;
 use crate::models::{
-    CreateMessagePayload, MessageReactionPayload, MessageWithUser, UpdateMessagePayload,
+    BulkDeleteMessagesPayload, BulkDeleteMessagesResponse, CreateMessagePayload,
+    MessageReactionPayload, MessageWithUser, UpdateMessagePayload,
 };
 use crate::services;
-use crate::web::ws::protocol::ServerEvent;
 use crate::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -27,16 +27,20 @@ pub async fn create_message(
     Path(channel_id): Path<Uuid>,
     Json(payload): Json<CreateMessagePayload>,
 ) -> Result<Json<MessageWithUser>> {
-    let message = services::create_message(
+    let (message, channel_kind, created) = services::create_message(
         &state.server_repo,
         &state.channel_repo,
-        &state.user_repo,
         &state.message_repo,
         channel_id,
         ctx.user_id(),
         payload,
     )
     .await?;
+
+    if created {
+        services::realtime::handle_message_created(&state, channel_kind, &message).await;
+    }
+
     Ok(Json(message))
 }
 
@@ -50,7 +54,6 @@ pub async fn list_messages(
     let messages = services::list_messages(
         &state.server_repo,
         &state.channel_repo,
-        &state.user_repo,
         &state.message_repo,
         channel_id,
         ctx.user_id(),
@@ -61,35 +64,62 @@ pub async fn list_messages(
     Ok(Json(messages))
 }
 
-pub async fn update_message(
+pub async fn list_pins(
     State(state): State<AppState>,
     ctx: Ctx,
-    Path(id): Path<Uuid>,
-    Json(payload): Json<UpdateMessagePayload>,
-) -> Result<Json<MessageWithUser>> {
-    let message = services::update_message(
+    Path(channel_id): Path<Uuid>,
+) -> Result<Json<Vec<MessageWithUser>>> {
+    let pins = services::messages::list_pins(
         &state.server_repo,
+        &state.channel_repo,
         &state.message_repo,
-        id,
+        channel_id,
         ctx.user_id(),
-        payload,
     )
     .await?;
+    Ok(Json(pins))
+}
 
-    if let Some(edited_at) = message.edited_at {
-        let event = ServerEvent::MessageUpdate {
-            id: message.id,
-            channel_id: message.channel_id,
-            content: message.content.clone(),
-            edited_at,
-        };
+pub async fn pin_message(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    services::realtime::handle_set_message_pinned(
+        &state,
+        ctx.user_id(),
+        channel_id,
+        message_id,
+        true,
+    )
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
 
-        state
-            .ws_hub
-            .broadcast_to_channel_with_metrics(message.channel_id, &event, Some(&state.ws_metrics))
-            .await;
-    }
+pub async fn unpin_message(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    services::realtime::handle_set_message_pinned(
+        &state,
+        ctx.user_id(),
+        channel_id,
+        message_id,
+        false,
+    )
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
 
+pub async fn update_message(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateMessagePayload>,
+) -> Result<Json<MessageWithUser>> {
+    let message =
+        services::realtime::handle_edit_message(&state, ctx.user_id(), id, payload.content).await?;
     Ok(Json(message))
 }
 
@@ -98,44 +128,30 @@ pub async fn delete_message(
     ctx: Ctx,
     Path(id): Path<Uuid>,
 ) -> Result<StatusCode> {
-    let channel_id =
-        services::delete_message(&state.server_repo, &state.message_repo, id, ctx.user_id())
-            .await?;
-
-    let event = ServerEvent::MessageDelete { id, channel_id };
-    state
-        .ws_hub
-        .broadcast_to_channel_with_metrics(channel_id, &event, Some(&state.ws_metrics))
-        .await;
-
+    services::realtime::handle_delete_message(&state, ctx.user_id(), id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
+pub async fn bulk_delete_messages(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(channel_id): Path<Uuid>,
+    Json(payload): Json<BulkDeleteMessagesPayload>,
+) -> Result<Json<BulkDeleteMessagesResponse>> {
+    let deleted =
+        services::realtime::handle_bulk_delete_messages(&state, ctx.user_id(), channel_id, payload)
+            .await?;
+    Ok(Json(BulkDeleteMessagesResponse { deleted }))
+}
+
 pub async fn add_reaction(
     State(state): State<AppState>,
     ctx: Ctx,
     Path(id): Path<Uuid>,
     Json(payload): Json<MessageReactionPayload>,
 ) -> Result<Json<MessageWithUser>> {
-    let message = services::messages::add_reaction(
-        &state.server_repo,
-        &state.message_repo,
-        id,
-        ctx.user_id(),
-        payload,
-    )
-    .await?;
-
-    let event = ServerEvent::MessageReactionUpdate {
-        id: message.id,
-        channel_id: message.channel_id,
-        reactions: message.reactions.clone(),
-    };
-    state
-        .ws_hub
-        .broadcast_to_channel_with_metrics(message.channel_id, &event, Some(&state.ws_metrics))
-        .await;
-
+    let message =
+        services::realtime::handle_add_reaction(&state, ctx.user_id(), id, payload.emoji).await?;
     Ok(Json(message))
 }
 
@@ -145,24 +161,8 @@ pub async fn remove_reaction(
     Path(id): Path<Uuid>,
     Json(payload): Json<MessageReactionPayload>,
 ) -> Result<Json<MessageWithUser>> {
-    let message = services::messages::remove_reaction(
-        &state.server_repo,
-        &state.message_repo,
-        id,
-        ctx.user_id(),
-        payload,
-    )
-    .await?;
-
-    let event = ServerEvent::MessageReactionUpdate {
-        id: message.id,
-        channel_id: message.channel_id,
-        reactions: message.reactions.clone(),
-    };
-    state
-        .ws_hub
-        .broadcast_to_channel_with_metrics(message.channel_id, &event, Some(&state.ws_metrics))
-        .await;
-
+    let message =
+        services::realtime::handle_remove_reaction(&state, ctx.user_id(), id, payload.emoji)
+            .await?;
     Ok(Json(message))
 }
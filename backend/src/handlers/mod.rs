@@ -4,7 +4,10 @@ pub mod dm;
 pub mod friends;
 pub mod invites;
 pub mod messages;
+pub mod roles;
+pub mod search;
 pub mod servers;
+pub mod threads;
 pub mod upload;
 pub mod user;
 pub mod user_public;
@@ -0,0 +1,89 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::ctx::Ctx;
+use crate::error::Result;
+use crate::models::{CreateRolePayload, Role, UpdateRolePayload};
+use crate::services;
+use crate::AppState;
+
+pub async fn list_roles(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(server_id): Path<Uuid>,
+) -> Result<Json<Vec<Role>>> {
+    let roles = services::list_roles(&state.server_repo, server_id, ctx.user_id()).await?;
+    Ok(Json(roles))
+}
+
+pub async fn create_role(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<CreateRolePayload>,
+) -> Result<Json<Role>> {
+    let role =
+        services::realtime::handle_create_role(&state, ctx.user_id(), server_id, payload).await?;
+    Ok(Json(role))
+}
+
+pub async fn update_role(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((server_id, role_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateRolePayload>,
+) -> Result<Json<Role>> {
+    let role =
+        services::realtime::handle_update_role(&state, ctx.user_id(), server_id, role_id, payload)
+            .await?;
+    Ok(Json(role))
+}
+
+pub async fn delete_role(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((server_id, role_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    services::realtime::handle_delete_role(&state, ctx.user_id(), server_id, role_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Attribue un rôle à un membre, retourne ses rôles attribués
+pub async fn add_member_role(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((server_id, user_id, role_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<Json<Vec<Uuid>>> {
+    let roles = services::realtime::handle_set_member_role(
+        &state,
+        ctx.user_id(),
+        server_id,
+        user_id,
+        role_id,
+        true,
+    )
+    .await?;
+    Ok(Json(roles))
+}
+
+/// Retire un rôle à un membre, retourne ses rôles attribués
+pub async fn remove_member_role(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((server_id, user_id, role_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<Json<Vec<Uuid>>> {
+    let roles = services::realtime::handle_set_member_role(
+        &state,
+        ctx.user_id(),
+        server_id,
+        user_id,
+        role_id,
+        false,
+    )
+    .await?;
+    Ok(Json(roles))
+}
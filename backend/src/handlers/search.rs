@@ -0,0 +1,48 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::ctx::Ctx;
+use crate::error::Result;
+use crate::models::{
+    DirectMessageItemResponse, MessageSearchResponse, MessageWithUser, SearchMessagesQuery,
+};
+use crate::services::search;
+use crate::AppState;
+
+pub async fn search_server_messages(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(server_id): Path<Uuid>,
+    Query(query): Query<SearchMessagesQuery>,
+) -> Result<Json<MessageSearchResponse<MessageWithUser>>> {
+    let response = search::search_server_messages(
+        &state.server_repo,
+        &state.channel_repo,
+        &state.message_repo,
+        &state.user_repo,
+        server_id,
+        ctx.user_id(),
+        query,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+pub async fn search_direct_messages(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Query(query): Query<SearchMessagesQuery>,
+) -> Result<Json<MessageSearchResponse<DirectMessageItemResponse>>> {
+    let response = search::search_direct_messages(
+        &state.dm_repo,
+        &state.dm_message_repo,
+        &state.user_repo,
+        ctx.user_id(),
+        query,
+    )
+    .await?;
+    Ok(Json(response))
+}
@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
@@ -8,8 +8,9 @@ use uuid::Uuid;
 use crate::ctx::Ctx;
 use crate::error::Result;
 use crate::models::{
-    BanMemberPayload, CreateServerPayload, Server, ServerBan, ServerMember,
-    TransferOwnershipPayload, UpdateMemberRolePayload, UpdateServerPayload,
+    AuditLogEntry, AuditLogQuery, BanMemberPayload, CreateServerPayload, Server, ServerBan,
+    ServerMember, TimeoutMemberPayload, TransferOwnershipPayload, UpdateMemberProfilePayload,
+    UpdateMemberRolePayload, UpdateServerPayload,
 };
 use crate::services;
 use crate::AppState;
@@ -19,9 +20,7 @@ pub async fn create_server(
     ctx: Ctx,
     Json(payload): Json<CreateServerPayload>,
 ) -> Result<Json<Server>> {
-    let server =
-        services::create_server(&state.server_repo, &state.user_repo, ctx.user_id(), payload)
-            .await?;
+    let server = services::realtime::handle_create_server(&state, ctx.user_id(), payload).await?;
     Ok(Json(server))
 }
 
@@ -50,7 +49,8 @@ pub async fn update_server(
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateServerPayload>,
 ) -> Result<Json<Server>> {
-    let server = services::update_server(&state.server_repo, id, ctx.user_id(), payload).await?;
+    let server =
+        services::realtime::handle_update_server(&state, ctx.user_id(), id, payload).await?;
     Ok(Json(server))
 }
 
@@ -59,7 +59,7 @@ pub async fn delete_server(
     ctx: Ctx,
     Path(id): Path<Uuid>,
 ) -> Result<StatusCode> {
-    services::delete_server(&state.server_repo, id, ctx.user_id()).await?;
+    services::realtime::handle_delete_server(&state, ctx.user_id(), id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -68,7 +68,7 @@ pub async fn join_server(
     ctx: Ctx,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ServerMember>> {
-    let member = services::join_server(&state.server_repo, id, ctx.user_id()).await?;
+    let member = services::realtime::handle_join_server(&state, ctx.user_id(), id).await?;
     Ok(Json(member))
 }
 
@@ -77,7 +77,7 @@ pub async fn leave_server(
     ctx: Ctx,
     Path(id): Path<Uuid>,
 ) -> Result<StatusCode> {
-    services::leave_server(&state.server_repo, id, ctx.user_id()).await?;
+    services::realtime::handle_leave_server(&state, ctx.user_id(), id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -103,6 +103,12 @@ pub async fn list_members(
     let user_map: std::collections::HashMap<uuid::Uuid, _> =
         users.into_iter().map(|u| (u.id, u)).collect();
 
+    let mut roles_by_user: std::collections::HashMap<Uuid, Vec<Uuid>> =
+        std::collections::HashMap::new();
+    for (user_id, role_id) in state.server_repo.list_role_assignments(id).await? {
+        roles_by_user.entry(user_id).or_default().push(role_id);
+    }
+
     let members_with_user: Vec<_> = members
         .into_iter()
         .filter_map(|member| {
@@ -113,8 +119,12 @@ pub async fn list_members(
                     user_id: member.user_id,
                     role: member.role,
                     joined_at: member.joined_at,
+                    communication_disabled_until: member.communication_disabled_until,
                     username: user.username.clone(),
                     avatar_url: user.avatar_url.clone(),
+                    nickname: member.nickname.clone(),
+                    server_avatar_url: member.avatar_url.clone(),
+                    roles: roles_by_user.remove(&member.user_id).unwrap_or_default(),
                 })
         })
         .collect();
@@ -127,24 +137,53 @@ pub async fn kick_member(
     ctx: Ctx,
     Path((server_id, user_id)): Path<(Uuid, Uuid)>,
 ) -> Result<StatusCode> {
-    services::kick_member(&state.server_repo, server_id, user_id, ctx.user_id()).await?;
+    services::realtime::handle_kick_member(&state, ctx.user_id(), server_id, user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
-pub async fn ban_member(
+pub async fn timeout_member(
     State(state): State<AppState>,
     ctx: Ctx,
     Path((server_id, user_id)): Path<(Uuid, Uuid)>,
-    Json(payload): Json<BanMemberPayload>,
-) -> Result<Json<ServerBan>> {
-    let ban = services::ban_member(
-        &state.server_repo,
+    Json(payload): Json<TimeoutMemberPayload>,
+) -> Result<Json<ServerMember>> {
+    let member = services::realtime::handle_timeout_member(
+        &state,
+        ctx.user_id(),
         server_id,
         user_id,
         payload,
+    )
+    .await?;
+    Ok(Json(member))
+}
+
+pub async fn update_member_profile(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((server_id, user_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateMemberProfilePayload>,
+) -> Result<Json<ServerMember>> {
+    let member = services::realtime::handle_update_member_profile(
+        &state,
         ctx.user_id(),
+        server_id,
+        user_id,
+        payload,
     )
     .await?;
+    Ok(Json(member))
+}
+
+pub async fn ban_member(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((server_id, user_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<BanMemberPayload>,
+) -> Result<Json<ServerBan>> {
+    let ban =
+        services::realtime::handle_ban_member(&state, ctx.user_id(), server_id, user_id, payload)
+            .await?;
     Ok(Json(ban))
 }
 
@@ -153,7 +192,7 @@ pub async fn unban_member(
     ctx: Ctx,
     Path((server_id, user_id)): Path<(Uuid, Uuid)>,
 ) -> Result<StatusCode> {
-    services::unban_member(&state.server_repo, server_id, user_id, ctx.user_id()).await?;
+    services::realtime::handle_unban_member(&state, ctx.user_id(), server_id, user_id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -166,18 +205,29 @@ pub async fn list_bans(
     Ok(Json(bans))
 }
 
+pub async fn list_audit_log(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(id): Path<Uuid>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<Vec<AuditLogEntry>>> {
+    let entries =
+        services::audit::list_audit_log(&state.server_repo, id, ctx.user_id(), query).await?;
+    Ok(Json(entries))
+}
+
 pub async fn update_member_role(
     State(state): State<AppState>,
     ctx: Ctx,
     Path((server_id, user_id)): Path<(Uuid, Uuid)>,
     Json(payload): Json<UpdateMemberRolePayload>,
 ) -> Result<Json<ServerMember>> {
-    let member = services::update_member_role(
-        &state.server_repo,
+    let member = services::realtime::handle_update_member_role(
+        &state,
+        ctx.user_id(),
         server_id,
         user_id,
         payload.role,
-        ctx.user_id(),
     )
     .await?;
     Ok(Json(member))
@@ -190,6 +240,6 @@ pub async fn transfer_ownership(
     Json(payload): Json<TransferOwnershipPayload>,
 ) -> Result<Json<Server>> {
     let server =
-        services::transfer_ownership(&state.server_repo, id, payload, ctx.user_id()).await?;
+        services::realtime::handle_transfer_ownership(&state, ctx.user_id(), id, payload).await?;
     Ok(Json(server))
 }
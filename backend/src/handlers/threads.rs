@@ -0,0 +1,106 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::ctx::Ctx;
+use crate::error::Result;
+use crate::models::{
+    CreateThreadPayload, ListThreadsQuery, Thread, ThreadMember, UpdateThreadPayload,
+};
+use crate::services;
+use crate::AppState;
+
+pub async fn create_thread(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<CreateThreadPayload>,
+) -> Result<Json<Thread>> {
+    let thread = services::realtime::handle_create_thread(
+        &state,
+        ctx.user_id(),
+        channel_id,
+        message_id,
+        payload,
+    )
+    .await?;
+    Ok(Json(thread))
+}
+
+pub async fn list_threads(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(channel_id): Path<Uuid>,
+    Query(query): Query<ListThreadsQuery>,
+) -> Result<Json<Vec<Thread>>> {
+    let threads = services::threads::list_threads(
+        &state.server_repo,
+        &state.channel_repo,
+        channel_id,
+        ctx.user_id(),
+        query.archived,
+    )
+    .await?;
+    Ok(Json(threads))
+}
+
+pub async fn get_thread(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Thread>> {
+    let thread =
+        services::threads::get_thread(&state.server_repo, &state.channel_repo, id, ctx.user_id())
+            .await?;
+    Ok(Json(thread))
+}
+
+pub async fn update_thread(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateThreadPayload>,
+) -> Result<Json<Thread>> {
+    let thread =
+        services::realtime::handle_update_thread(&state, ctx.user_id(), id, payload).await?;
+    Ok(Json(thread))
+}
+
+pub async fn list_members(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ThreadMember>>> {
+    let members = services::threads::list_thread_members(
+        &state.server_repo,
+        &state.channel_repo,
+        id,
+        ctx.user_id(),
+    )
+    .await?;
+    Ok(Json(members))
+}
+
+pub async fn join_thread(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ThreadMember>> {
+    let member =
+        services::threads::join_thread(&state.server_repo, &state.channel_repo, id, ctx.user_id())
+            .await?;
+    Ok(Json(member))
+}
+
+pub async fn leave_thread(
+    State(state): State<AppState>,
+    ctx: Ctx,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    services::threads::leave_thread(&state.server_repo, &state.channel_repo, id, ctx.user_id())
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
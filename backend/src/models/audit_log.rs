@@ -0,0 +1,127 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Action journalisée (colonne TEXT `audit_log.action`, pour ajouter des actions sans migration)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    ServerUpdate,
+    OwnershipTransfer,
+    MemberKick,
+    MemberBanAdd,
+    MemberBanRemove,
+    /// Ban temporaire levé automatiquement à son expiration (sans auteur)
+    MemberBanExpire,
+    /// Rang historique (Owner/Admin/Member) ou rôle attribué / retiré
+    MemberRoleUpdate,
+    /// Timeout appliqué ou levé (`after.communication_disabled_until`)
+    MemberTimeout,
+    /// Pseudo ou avatar de serveur modifié (par le membre ou un modérateur)
+    MemberProfileUpdate,
+    RoleCreate,
+    RoleUpdate,
+    RoleDelete,
+    ChannelCreate,
+    ChannelUpdate,
+    ChannelDelete,
+    ChannelOverwriteUpdate,
+    ChannelOverwriteDelete,
+    /// Channel du serveur abonné à un channel d'annonces (cible : le channel abonné)
+    ChannelFollow,
+    ChannelUnfollow,
+    InviteCreate,
+    InviteRevoke,
+    /// Message d'un autre membre supprimé par un modérateur
+    MessageDelete,
+    MessageBulkDelete,
+    /// Message épinglé ou désépinglé par un modérateur
+    MessagePin,
+    MessageUnpin,
+    /// Thread d'un autre membre renommé ou archivé par un modérateur
+    ThreadUpdate,
+}
+
+/// Entrée du journal d'audit d'un serveur
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AuditLogEntry {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    /// None si le compte de l'auteur a été supprimé
+    pub actor_id: Option<Uuid>,
+    pub action: AuditAction,
+    /// Membre, rôle, channel, invitation ou message visé
+    pub target_id: Option<Uuid>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Entrée à écrire (l'id et la date sont attribués par la base)
+#[derive(Debug, Clone)]
+pub struct NewAuditLogEntry {
+    pub server_id: Uuid,
+    /// None pour les actions du système (expiration d'un ban, ...)
+    pub actor_id: Option<Uuid>,
+    pub action: AuditAction,
+    pub target_id: Option<Uuid>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub reason: Option<String>,
+}
+
+impl NewAuditLogEntry {
+    pub fn new(server_id: Uuid, actor_id: Uuid, action: AuditAction) -> Self {
+        Self {
+            actor_id: Some(actor_id),
+            ..Self::system(server_id, action)
+        }
+    }
+
+    /// Action effectuée par le serveur lui-même, sans membre à l'origine
+    pub fn system(server_id: Uuid, action: AuditAction) -> Self {
+        Self {
+            server_id,
+            actor_id: None,
+            action,
+            target_id: None,
+            before: None,
+            after: None,
+            reason: None,
+        }
+    }
+
+    pub fn target(mut self, target_id: Uuid) -> Self {
+        self.target_id = Some(target_id);
+        self
+    }
+
+    /// État avant l'action (sérialisé en JSON)
+    pub fn before<T: Serialize>(mut self, value: &T) -> Self {
+        self.before = serde_json::to_value(value).ok();
+        self
+    }
+
+    /// État après l'action (sérialisé en JSON)
+    pub fn after<T: Serialize>(mut self, value: &T) -> Self {
+        self.after = serde_json::to_value(value).ok();
+        self
+    }
+
+    pub fn reason(mut self, reason: Option<String>) -> Self {
+        self.reason = reason;
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    pub actor_id: Option<Uuid>,
+    pub action: Option<AuditAction>,
+    /// Curseur : id de la dernière entrée de la page précédente
+    pub before: Option<Uuid>,
+    pub limit: Option<i64>,
+}
@@ -1,14 +1,45 @@
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::models::Permissions;
+
+/// Type de channel (colonne TEXT `channels.kind`, pour ajouter des types sans migration)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelKind {
+    #[default]
+    Text,
+    /// Groupe repliable de channels, sans messages
+    Category,
+    /// Seuls les membres avec MANAGE_MESSAGES y publient, d'autres channels peuvent le suivre
+    Announcement,
+    /// Sans messages : participants connectés et signalisation WebRTC relayée par le gateway
+    Voice,
+    /// Discussion ouverte depuis un message du channel parent, absente de la liste des channels
+    Thread,
+}
+
+impl ChannelKind {
+    pub fn accepts_messages(self) -> bool {
+        matches!(self, Self::Text | Self::Announcement | Self::Thread)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, FromRow)]
 pub struct Channel {
     pub id: Uuid,
     pub server_id: Uuid,
     pub name: String,
+    pub kind: ChannelKind,
+    /// Catégorie parente, None au premier niveau (channel d'origine pour un thread)
+    pub parent_id: Option<Uuid>,
+    /// Ordre parmi les channels de même parent
     pub position: i32,
+    /// Slowmode : secondes minimum entre deux messages d'un même membre (0 = désactivé)
+    pub rate_limit_per_user: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -17,10 +48,83 @@ pub struct Channel {
 pub struct CreateChannelPayload {
     pub name: String,
     pub position: Option<i32>,
+    #[serde(default)]
+    pub kind: ChannelKind,
+    /// Catégorie parente : le channel reprend ses overwrites
+    pub parent_id: Option<Uuid>,
+    #[serde(default)]
+    pub rate_limit_per_user: i32,
+    /// Channel privé : @everyone perd VIEW_CHANNEL, le créateur le garde
+    #[serde(default)]
+    pub private: bool,
+}
+
+/// Channel texte abonné à un channel d'annonces : les annonces y sont recopiées
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ChannelFollower {
+    pub channel_id: Uuid,
+    pub target_channel_id: Uuid,
+    /// None si le compte de l'auteur a été supprimé
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FollowChannelPayload {
+    pub target_channel_id: Uuid,
+}
+
+/// Déplacement d'un channel (PATCH /servers/{id}/channels/positions)
+#[derive(Debug, Deserialize)]
+pub struct ChannelPositionPayload {
+    pub id: Uuid,
+    pub position: i32,
+    /// Absent : parent inchangé, null : sortir de la catégorie
+    #[serde(default, deserialize_with = "double_option")]
+    pub parent_id: Option<Option<Uuid>>,
+    /// Remplace les overwrites du channel par ceux de sa catégorie
+    #[serde(default)]
+    pub lock_permissions: bool,
+}
+
+/// Distingue un champ absent (None) d'un champ à null (Some(None))
+pub fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateChannelPayload {
     pub name: Option<String>,
     pub position: Option<i32>,
+    pub rate_limit_per_user: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "overwrite_target", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum OverwriteTarget {
+    Role,
+    Member,
+}
+
+/// Permissions accordées (`allow`) ou retirées (`deny`) à un rôle ou un membre dans un channel
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ChannelOverwrite {
+    pub channel_id: Uuid,
+    pub target_type: OverwriteTarget,
+    pub target_id: Uuid,
+    pub allow: Permissions,
+    pub deny: Permissions,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChannelOverwritePayload {
+    #[serde(default)]
+    pub allow: i64,
+    #[serde(default)]
+    pub deny: i64,
 }
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
-use crate::models::message::{MessageReaction, MessageReactionPublic};
+use crate::models::message::{MessagePreview, MessageReaction, MessageReactionPublic};
 
 mod uuid_compat_binary_generic {
     use super::*;
@@ -42,6 +42,33 @@ mod uuid_compat_binary_generic {
             UuidCompat::Binary(b) => Uuid::from_slice(&b.bytes).map_err(D::Error::custom),
         }
     }
+
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S>(value: &Option<Uuid>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match value {
+                Some(v) => {
+                    let b = Binary {
+                        subtype: bson::spec::BinarySubtype::Generic,
+                        bytes: v.as_bytes().to_vec(),
+                    };
+                    serializer.serialize_some(&b)
+                }
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Uuid>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Option::<Uuid>::deserialize(deserializer)
+        }
+    }
 }
 
 mod datetime_compat {
@@ -129,6 +156,12 @@ pub struct CreateDMPayload {
 #[derive(Debug, Deserialize)]
 pub struct CreateDMMessagePayload {
     pub content: String,
+    /// Identifiant client optionnel : un envoi rejoué avec le même nonce n'est pas dupliqué
+    #[serde(default)]
+    pub nonce: Option<String>,
+    /// Message de la même conversation auquel on répond
+    #[serde(default)]
+    pub reply_to: Option<Uuid>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -155,6 +188,23 @@ pub struct DirectMessageItem {
     #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub reactions: Vec<MessageReaction>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// Message de la même conversation auquel celui-ci répond
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(with = "uuid_compat_binary_generic::option")]
+    pub reply_to: Option<Uuid>,
+    /// Épinglé par l'un des participants
+    #[serde(default)]
+    #[serde(with = "datetime_compat::option")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(with = "uuid_compat_binary_generic::option")]
+    pub pinned_by: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize)]
@@ -168,4 +218,12 @@ pub struct DirectMessageItemResponse {
     pub edited_at: Option<DateTime<Utc>>,
     #[serde(default)]
     pub reactions: Vec<MessageReactionPublic>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<MessagePreview>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_by: Option<Uuid>,
 }
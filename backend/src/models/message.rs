@@ -66,6 +66,27 @@ mod uuid_compat_binary_generic {
             Option::<Uuid>::deserialize(deserializer)
         }
     }
+
+    pub mod vec {
+        use super::*;
+
+        pub fn serialize<S>(value: &[Uuid], serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.collect_seq(value.iter().map(|v| Binary {
+                subtype: bson::spec::BinarySubtype::Generic,
+                bytes: v.as_bytes().to_vec(),
+            }))
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Uuid>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Vec::<Uuid>::deserialize(deserializer)
+        }
+    }
 }
 
 mod datetime_compat {
@@ -156,6 +177,64 @@ pub struct ChannelMessage {
     #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub reactions: Vec<MessageReaction>,
+    /// Nonce client (déduplication des envois rejoués, index unique par auteur)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// Channel d'annonces d'origine d'un message recopié chez un abonné
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(with = "uuid_compat_binary_generic::option")]
+    pub crossposted_from: Option<Uuid>,
+    /// Message du même channel auquel celui-ci répond
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(with = "uuid_compat_binary_generic::option")]
+    pub reply_to: Option<Uuid>,
+    /// Thread ouvert depuis ce message
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(with = "uuid_compat_binary_generic::option")]
+    pub thread_id: Option<Uuid>,
+    /// Nombre de messages publiés dans le thread
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub thread_message_count: i32,
+    #[serde(default)]
+    #[serde(with = "datetime_compat::option")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_last_activity_at: Option<DateTime<Utc>>,
+    /// Épinglé par un modérateur
+    #[serde(default)]
+    #[serde(with = "datetime_compat::option")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(with = "uuid_compat_binary_generic::option")]
+    pub pinned_by: Option<Uuid>,
+    /// Membres du serveur mentionnés (`<@user_id>`)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(with = "uuid_compat_binary_generic::vec")]
+    pub mentions: Vec<Uuid>,
+    /// Rôles du serveur mentionnés (`<@&role_id>`)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(with = "uuid_compat_binary_generic::vec")]
+    pub mention_roles: Vec<Uuid>,
+    /// `@everyone` ou `@here` par un membre qui en a la permission
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub mention_everyone: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -191,17 +270,68 @@ pub struct MessageWithUser {
     pub channel_id: Uuid,
     pub author_id: Uuid,
     pub username: String,
+    /// Pseudo de l'auteur dans ce serveur
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    /// Avatar de serveur de l'auteur, sinon son avatar global
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
     pub content: String,
     pub created_at: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub edited_at: Option<DateTime<Utc>>,
     #[serde(default)]
     pub reactions: Vec<MessageReactionPublic>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crossposted_from: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<MessagePreview>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub thread_message_count: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_last_activity_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_by: Option<Uuid>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mentions: Vec<Uuid>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mention_roles: Vec<Uuid>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub mention_everyone: bool,
+}
+
+/// Aperçu compact du message auquel on répond (channel ou conversation privée)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessagePreview {
+    pub id: Uuid,
+    /// Vrai si le message a été supprimé depuis : auteur et contenu sont alors omis
+    pub deleted: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    /// Contenu tronqué
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateMessagePayload {
     pub content: String,
+    /// Identifiant client optionnel : un envoi rejoué avec le même nonce n'est pas dupliqué
+    #[serde(default)]
+    pub nonce: Option<String>,
+    /// Message du même channel auquel on répond
+    #[serde(default)]
+    pub reply_to: Option<Uuid>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -213,3 +343,17 @@ pub struct UpdateMessagePayload {
 pub struct MessageReactionPayload {
     pub emoji: String,
 }
+
+/// Suppression groupée : une liste d'ids, ou les `limit` derniers messages d'un auteur
+#[derive(Debug, Deserialize)]
+pub struct BulkDeleteMessagesPayload {
+    #[serde(default)]
+    pub message_ids: Vec<Uuid>,
+    pub author_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkDeleteMessagesResponse {
+    pub deleted: Vec<Uuid>,
+}
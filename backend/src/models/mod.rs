@@ -1,16 +1,24 @@
 pub mod attachment;
+pub mod audit_log;
 pub mod channel;
 pub mod dm;
 pub mod invite;
 pub mod message;
+pub mod role;
+pub mod search;
 pub mod server;
+pub mod thread;
 pub mod user;
 
 pub use attachment::*;
+pub use audit_log::*;
 pub use channel::*;
 #[allow(unused_imports)]
 pub use dm::*;
 pub use invite::*;
 pub use message::*;
+pub use role::*;
+pub use search::*;
 pub use server::*;
+pub use thread::*;
 pub use user::*;
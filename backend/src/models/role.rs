@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::ops::{BitAnd, BitOr, BitOrAssign, Not};
+use uuid::Uuid;
+
+/// Champ de bits des permissions d'un rôle (colonne BIGINT `roles.permissions`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, sqlx::Type)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct Permissions(i64);
+
+impl Permissions {
+    /// Toutes les permissions, ignore la hiérarchie des permissions (pas celle des rôles)
+    pub const ADMINISTRATOR: Self = Self(1 << 0);
+    /// Renommer le serveur, voir les invitations
+    pub const MANAGE_SERVER: Self = Self(1 << 1);
+    /// Créer, modifier, supprimer et attribuer les rôles inférieurs au sien
+    pub const MANAGE_ROLES: Self = Self(1 << 2);
+    pub const MANAGE_CHANNELS: Self = Self(1 << 3);
+    pub const KICK_MEMBERS: Self = Self(1 << 4);
+    pub const BAN_MEMBERS: Self = Self(1 << 5);
+    pub const CREATE_INVITE: Self = Self(1 << 6);
+    /// Supprimer les messages des autres membres
+    pub const MANAGE_MESSAGES: Self = Self(1 << 7);
+    pub const VIEW_CHANNEL: Self = Self(1 << 8);
+    pub const SEND_MESSAGES: Self = Self(1 << 9);
+    pub const ADD_REACTIONS: Self = Self(1 << 10);
+    /// Envoyer des messages contenant un fichier uploadé (`/files/...`)
+    pub const ATTACH_FILES: Self = Self(1 << 11);
+    /// Notifier tout le channel avec `@everyone` / `@here`
+    pub const MENTION_EVERYONE: Self = Self(1 << 12);
+
+    pub const NONE: Self = Self(0);
+    pub const ALL: Self = Self((1 << 13) - 1);
+
+    /// Permissions du rôle @everyone d'un nouveau serveur
+    pub const DEFAULT_EVERYONE: Self = Self(
+        Self::VIEW_CHANNEL.0 | Self::SEND_MESSAGES.0 | Self::ADD_REACTIONS.0 | Self::ATTACH_FILES.0,
+    );
+
+    /// Permissions retirées à un membre en timeout (ATTACH_FILES suit SEND_MESSAGES)
+    pub const COMMUNICATION: Self = Self(Self::SEND_MESSAGES.0 | Self::ADD_REACTIONS.0);
+
+    /// Permissions qu'un overwrite de channel peut accorder ou retirer
+    pub const CHANNEL_OVERWRITABLE: Self =
+        Self(Self::DEFAULT_EVERYONE.0 | Self::MANAGE_MESSAGES.0 | Self::MENTION_EVERYONE.0);
+
+    /// Permissions du rôle "Admin" créé pour les anciens administrateurs
+    pub const LEGACY_ADMIN: Self = Self(
+        Self::MANAGE_SERVER.0
+            | Self::MANAGE_CHANNELS.0
+            | Self::KICK_MEMBERS.0
+            | Self::BAN_MEMBERS.0
+            | Self::CREATE_INVITE.0
+            | Self::MANAGE_MESSAGES.0,
+    );
+
+    /// Refuse les bits inconnus
+    pub fn from_bits(bits: i64) -> Option<Self> {
+        (bits & !Self::ALL.0 == 0).then_some(Self(bits))
+    }
+
+    pub fn bits(self) -> i64 {
+        self.0
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Applique un overwrite : retire `deny` puis ajoute `allow`
+    pub fn apply_overwrite(self, allow: Self, deny: Self) -> Self {
+        (self & !deny) | allow
+    }
+}
+
+impl BitAnd for Permissions {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl Not for Permissions {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0 & Self::ALL.0)
+    }
+}
+
+impl BitOr for Permissions {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Permissions {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Rôle d'un serveur. Le rôle par défaut (`@everyone`, position 0) s'applique à tous les membres
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Role {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub name: String,
+    pub color: i32,
+    pub position: i32,
+    pub permissions: Permissions,
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRolePayload {
+    pub name: String,
+    pub color: Option<i32>,
+    pub permissions: Option<i64>,
+    pub position: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRolePayload {
+    pub name: Option<String>,
+    pub color: Option<i32>,
+    pub permissions: Option<i64>,
+    pub position: Option<i32>,
+}
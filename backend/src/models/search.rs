@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct SearchMessagesQuery {
+    /// Texte libre et opérateurs (`from:`, `in:`, `has:`, `before:`, `after:`, `mentions:`)
+    pub q: String,
+    /// Curseur : id du dernier résultat reçu
+    pub before: Option<Uuid>,
+    pub limit: Option<i64>,
+    /// Nombre de messages de contexte de part et d'autre de chaque résultat
+    pub context: Option<i64>,
+}
+
+/// Critères d'une recherche de messages, noms déjà résolus en ids
+#[derive(Debug, Default)]
+pub struct MessageSearchFilter {
+    pub text: Option<String>,
+    /// Auteurs acceptés (vide = tous)
+    pub author_ids: Vec<Uuid>,
+    /// Utilisateurs dont au moins un doit être mentionné (vide = aucun filtre)
+    pub mention_ids: Vec<Uuid>,
+    pub has_attachment: bool,
+    pub has_link: bool,
+    /// Borne haute exclue
+    pub before: Option<DateTime<Utc>>,
+    /// Borne basse incluse
+    pub after: Option<DateTime<Utc>>,
+}
+
+/// Message trouvé, avec les messages qui l'entourent dans son channel ou sa conversation
+#[derive(Debug, Serialize)]
+pub struct MessageSearchResult<T> {
+    pub message: T,
+    pub before: Vec<T>,
+    pub after: Vec<T>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageSearchResponse<T> {
+    /// Du plus récent au plus ancien
+    pub results: Vec<MessageSearchResult<T>>,
+    /// Curseur de la page suivante, absent sur la dernière page
+    pub next_before: Option<Uuid>,
+}
+
+impl<T> MessageSearchResponse<T> {
+    pub fn empty() -> Self {
+        Self {
+            results: vec![],
+            next_before: None,
+        }
+    }
+}
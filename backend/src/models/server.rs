@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::models::double_option;
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq, Default)]
 #[sqlx(type_name = "member_role", rename_all = "lowercase")]
 pub enum MemberRole {
@@ -27,6 +29,19 @@ pub struct ServerMember {
     pub user_id: Uuid,
     pub role: MemberRole,
     pub joined_at: DateTime<Utc>,
+    /// Timeout : plus de messages, réactions ni frappe jusqu'à cette date
+    pub communication_disabled_until: Option<DateTime<Utc>>,
+    /// Pseudo propre à ce serveur
+    pub nickname: Option<String>,
+    /// Avatar propre à ce serveur
+    pub avatar_url: Option<String>,
+}
+
+impl ServerMember {
+    pub fn is_timed_out(&self) -> bool {
+        self.communication_disabled_until
+            .is_some_and(|until| until > Utc::now())
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -35,10 +50,35 @@ pub struct ServerMemberWithUser {
     pub user_id: Uuid,
     pub role: MemberRole,
     pub joined_at: DateTime<Utc>,
+    pub communication_disabled_until: Option<DateTime<Utc>>,
+    pub username: String,
+    pub avatar_url: Option<String>,
+    pub nickname: Option<String>,
+    pub server_avatar_url: Option<String>,
+    /// Rôles attribués (hors @everyone)
+    pub roles: Vec<Uuid>,
+}
+
+/// Identité d'un utilisateur telle qu'affichée dans un serveur
+#[derive(Debug, Clone, Default, FromRow)]
+pub struct MemberProfile {
+    pub user_id: Uuid,
     pub username: String,
+    /// None hors du serveur ou sans pseudo
+    pub nickname: Option<String>,
+    /// Avatar du serveur, sinon l'avatar global
     pub avatar_url: Option<String>,
 }
 
+/// Absent : inchangé, null ou vide : retour au profil global
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberProfilePayload {
+    #[serde(default, deserialize_with = "double_option")]
+    pub nickname: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub avatar_url: Option<Option<String>>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateServerPayload {
     pub name: String,
@@ -66,6 +106,13 @@ pub struct BanMemberPayload {
     pub expires_at: Option<DateTime<Utc>>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct TimeoutMemberPayload {
+    /// Fin du timeout (28 jours max), null pour le lever
+    pub until: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, FromRow)]
 pub struct ServerBan {
     pub server_id: Uuid,
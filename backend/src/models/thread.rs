@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Thread : channel de type `thread` ouvert depuis un message, avec ses métadonnées
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Thread {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    /// Channel dans lequel le thread a été ouvert (permissions héritées)
+    pub parent_id: Uuid,
+    pub parent_message_id: Uuid,
+    /// None si le compte du créateur a été supprimé
+    pub owner_id: Option<Uuid>,
+    pub name: String,
+    pub archived: bool,
+    pub archived_at: Option<DateTime<Utc>>,
+    /// Inactivité (minutes) au-delà de laquelle le thread est archivé
+    pub auto_archive_minutes: i32,
+    pub last_activity_at: DateTime<Utc>,
+    pub rate_limit_per_user: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ThreadMember {
+    pub thread_id: Uuid,
+    pub user_id: Uuid,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateThreadPayload {
+    /// Absent : début du contenu du message d'origine
+    pub name: Option<String>,
+    pub auto_archive_minutes: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateThreadPayload {
+    pub name: Option<String>,
+    pub archived: Option<bool>,
+    pub auto_archive_minutes: Option<i32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListThreadsQuery {
+    /// Threads archivés plutôt qu'actifs
+    #[serde(default)]
+    pub archived: bool,
+}
@@ -1,7 +1,46 @@
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
-use crate::models::Channel;
+use crate::models::{
+    Channel, ChannelFollower, ChannelKind, ChannelOverwrite, OverwriteTarget, Permissions, Thread,
+    ThreadMember,
+};
+
+/// Nouvelle place d'un channel, validée par le service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelPosition {
+    pub channel_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub position: i32,
+    /// Catégorie dont copier les overwrites
+    pub sync_with: Option<Uuid>,
+}
+
+/// Remplace les overwrites d'un channel par ceux d'un autre
+async fn copy_overwrites(
+    conn: &mut PgConnection,
+    channel_id: Uuid,
+    source_id: Uuid,
+) -> sqlx::Result<()> {
+    sqlx::query("DELETE FROM channel_overwrites WHERE channel_id = $1")
+        .bind(channel_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO channel_overwrites (channel_id, target_type, target_id, allow, deny)
+        SELECT $1, target_type, target_id, allow, deny
+        FROM channel_overwrites
+        WHERE channel_id = $2
+        "#,
+    )
+    .bind(channel_id)
+    .bind(source_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
 
 #[derive(Clone)]
 pub struct ChannelRepository {
@@ -13,31 +52,38 @@ impl ChannelRepository {
         Self { pool }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn create(
         &self,
         channel_id: Uuid,
         server_id: Uuid,
         name: &str,
+        kind: ChannelKind,
+        parent_id: Option<Uuid>,
         position: i32,
+        rate_limit_per_user: i32,
     ) -> sqlx::Result<Channel> {
         sqlx::query_as::<_, Channel>(
             r#"
-            INSERT INTO channels (id, server_id, name, position, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, NOW(), NOW())
-            RETURNING id, server_id, name, position, created_at, updated_at
+            INSERT INTO channels (id, server_id, name, kind, parent_id, position, rate_limit_per_user, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), NOW())
+            RETURNING id, server_id, name, kind, parent_id, position, rate_limit_per_user, created_at, updated_at
             "#,
         )
         .bind(channel_id)
         .bind(server_id)
         .bind(name)
+        .bind(kind)
+        .bind(parent_id)
         .bind(position)
+        .bind(rate_limit_per_user)
         .fetch_one(&self.pool)
         .await
     }
 
     pub async fn find_by_id(&self, channel_id: Uuid) -> sqlx::Result<Option<Channel>> {
         sqlx::query_as::<_, Channel>(
-            "SELECT id, server_id, name, position, created_at, updated_at FROM channels WHERE id = $1",
+            "SELECT id, server_id, name, kind, parent_id, position, rate_limit_per_user, created_at, updated_at FROM channels WHERE id = $1",
         )
         .bind(channel_id)
         .fetch_optional(&self.pool)
@@ -46,18 +92,44 @@ impl ChannelRepository {
 
     pub async fn list_by_server(&self, server_id: Uuid) -> sqlx::Result<Vec<Channel>> {
         sqlx::query_as::<_, Channel>(
-            "SELECT id, server_id, name, position, created_at, updated_at FROM channels WHERE server_id = $1 ORDER BY position",
+            "SELECT id, server_id, name, kind, parent_id, position, rate_limit_per_user, created_at, updated_at FROM channels WHERE server_id = $1 AND kind <> 'thread' ORDER BY position",
         )
         .bind(server_id)
         .fetch_all(&self.pool)
         .await
     }
 
-    pub async fn get_max_position(&self, server_id: Uuid) -> sqlx::Result<Option<i32>> {
+    /// Threads d'un serveur (exclus de `list_by_server`)
+    pub async fn list_threads_by_server(&self, server_id: Uuid) -> sqlx::Result<Vec<Channel>> {
+        sqlx::query_as::<_, Channel>(
+            "SELECT id, server_id, name, kind, parent_id, position, rate_limit_per_user, created_at, updated_at FROM channels WHERE server_id = $1 AND kind = 'thread'",
+        )
+        .bind(server_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Channels d'une catégorie
+    pub async fn list_children(&self, parent_id: Uuid) -> sqlx::Result<Vec<Channel>> {
+        sqlx::query_as::<_, Channel>(
+            "SELECT id, server_id, name, kind, parent_id, position, rate_limit_per_user, created_at, updated_at FROM channels WHERE parent_id = $1 ORDER BY position",
+        )
+        .bind(parent_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Dernière position parmi les channels de même parent
+    pub async fn get_max_position(
+        &self,
+        server_id: Uuid,
+        parent_id: Option<Uuid>,
+    ) -> sqlx::Result<Option<i32>> {
         let result: Option<Option<i32>> = sqlx::query_scalar::<_, Option<i32>>(
-            "SELECT MAX(position) FROM channels WHERE server_id = $1",
+            "SELECT MAX(position) FROM channels WHERE server_id = $1 AND parent_id IS NOT DISTINCT FROM $2",
         )
         .bind(server_id)
+        .bind(parent_id)
         .fetch_optional(&self.pool)
         .await?;
         Ok(result.flatten())
@@ -68,27 +140,498 @@ impl ChannelRepository {
         channel_id: Uuid,
         name: Option<String>,
         position: Option<i32>,
+        rate_limit_per_user: Option<i32>,
     ) -> sqlx::Result<Option<Channel>> {
         sqlx::query_as::<_, Channel>(
             r#"
             UPDATE channels
-            SET name = COALESCE($1, name), position = COALESCE($2, position), updated_at = NOW()
-            WHERE id = $3
-            RETURNING id, server_id, name, position, created_at, updated_at
+            SET name = COALESCE($1, name),
+                position = COALESCE($2, position),
+                rate_limit_per_user = COALESCE($3, rate_limit_per_user),
+                updated_at = NOW()
+            WHERE id = $4
+            RETURNING id, server_id, name, kind, parent_id, position, rate_limit_per_user, created_at, updated_at
             "#,
         )
         .bind(name)
         .bind(position)
+        .bind(rate_limit_per_user)
         .bind(channel_id)
         .fetch_optional(&self.pool)
         .await
     }
 
+    /// Applique un lot de déplacements en une transaction, puis renumérote les positions
+    /// de chaque parent (0, 1, 2...) : les channels déplacés passent avant ceux qu'ils rejoignent
+    /// Retourne tous les channels du serveur
+    pub async fn update_positions(
+        &self,
+        server_id: Uuid,
+        positions: &[ChannelPosition],
+    ) -> sqlx::Result<Vec<Channel>> {
+        let mut tx = self.pool.begin().await?;
+
+        for change in positions {
+            sqlx::query(
+                r#"
+                UPDATE channels
+                SET parent_id = $1, position = $2, updated_at = NOW()
+                WHERE id = $3 AND server_id = $4
+                "#,
+            )
+            .bind(change.parent_id)
+            .bind(change.position)
+            .bind(change.channel_id)
+            .bind(server_id)
+            .execute(&mut *tx)
+            .await?;
+
+            if let Some(source_id) = change.sync_with {
+                copy_overwrites(&mut tx, change.channel_id, source_id).await?;
+            }
+        }
+
+        let moved: Vec<Uuid> = positions.iter().map(|change| change.channel_id).collect();
+        sqlx::query(
+            r#"
+            UPDATE channels c
+            SET position = ordered.position, updated_at = NOW()
+            FROM (
+                SELECT id, (ROW_NUMBER() OVER (
+                    PARTITION BY parent_id
+                    ORDER BY position, id = ANY($2) DESC, created_at, id
+                ) - 1)::INT AS position
+                FROM channels
+                WHERE server_id = $1 AND kind <> 'thread'
+            ) ordered
+            WHERE c.id = ordered.id AND c.position <> ordered.position
+            "#,
+        )
+        .bind(server_id)
+        .bind(&moved)
+        .execute(&mut *tx)
+        .await?;
+
+        let channels = sqlx::query_as::<_, Channel>(
+            "SELECT id, server_id, name, kind, parent_id, position, rate_limit_per_user, created_at, updated_at FROM channels WHERE server_id = $1 AND kind <> 'thread' ORDER BY position",
+        )
+        .bind(server_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(channels)
+    }
+
+    /// Aligne les overwrites d'un channel sur ceux de sa catégorie
+    pub async fn sync_overwrites(&self, channel_id: Uuid, source_id: Uuid) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        copy_overwrites(&mut tx, channel_id, source_id).await?;
+        tx.commit().await
+    }
+
+    pub async fn add_follower(
+        &self,
+        channel_id: Uuid,
+        target_channel_id: Uuid,
+        created_by: Uuid,
+    ) -> sqlx::Result<ChannelFollower> {
+        sqlx::query_as::<_, ChannelFollower>(
+            r#"
+            INSERT INTO channel_followers (channel_id, target_channel_id, created_by, created_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (channel_id, target_channel_id)
+            DO UPDATE SET created_by = EXCLUDED.created_by
+            RETURNING channel_id, target_channel_id, created_by, created_at
+            "#,
+        )
+        .bind(channel_id)
+        .bind(target_channel_id)
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn remove_follower(
+        &self,
+        channel_id: Uuid,
+        target_channel_id: Uuid,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM channel_followers WHERE channel_id = $1 AND target_channel_id = $2",
+        )
+        .bind(channel_id)
+        .bind(target_channel_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Channels abonnés à un channel d'annonces
+    pub async fn list_followers(&self, channel_id: Uuid) -> sqlx::Result<Vec<ChannelFollower>> {
+        sqlx::query_as::<_, ChannelFollower>(
+            r#"
+            SELECT channel_id, target_channel_id, created_by, created_at
+            FROM channel_followers
+            WHERE channel_id = $1
+            ORDER BY created_at
+            "#,
+        )
+        .bind(channel_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Crée le channel d'un thread ouvert depuis `parent_message_id`, son créateur en est membre
+    /// Le thread reprend le slowmode de son channel d'origine
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_thread(
+        &self,
+        thread_id: Uuid,
+        parent: &Channel,
+        parent_message_id: Uuid,
+        name: &str,
+        owner_id: Uuid,
+        auto_archive_minutes: i32,
+    ) -> sqlx::Result<Thread> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO channels (id, server_id, name, kind, parent_id, position, rate_limit_per_user, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, 0, $6, NOW(), NOW())
+            "#,
+        )
+        .bind(thread_id)
+        .bind(parent.server_id)
+        .bind(name)
+        .bind(ChannelKind::Thread)
+        .bind(parent.id)
+        .bind(parent.rate_limit_per_user)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO threads (channel_id, parent_message_id, owner_id, auto_archive_minutes, last_activity_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            "#,
+        )
+        .bind(thread_id)
+        .bind(parent_message_id)
+        .bind(owner_id)
+        .bind(auto_archive_minutes)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO thread_members (thread_id, user_id, joined_at) VALUES ($1, $2, NOW())",
+        )
+        .bind(thread_id)
+        .bind(owner_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let thread = sqlx::query_as::<_, Thread>(
+            r#"
+            SELECT c.id, c.server_id, c.parent_id, t.parent_message_id, t.owner_id, c.name,
+                   t.archived, t.archived_at, t.auto_archive_minutes, t.last_activity_at,
+                   c.rate_limit_per_user, c.created_at
+            FROM threads t
+            INNER JOIN channels c ON c.id = t.channel_id
+            WHERE t.channel_id = $1
+            "#,
+        )
+        .bind(thread_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(thread)
+    }
+
+    pub async fn find_thread(&self, thread_id: Uuid) -> sqlx::Result<Option<Thread>> {
+        sqlx::query_as::<_, Thread>(
+            r#"
+            SELECT c.id, c.server_id, c.parent_id, t.parent_message_id, t.owner_id, c.name,
+                   t.archived, t.archived_at, t.auto_archive_minutes, t.last_activity_at,
+                   c.rate_limit_per_user, c.created_at
+            FROM threads t
+            INNER JOIN channels c ON c.id = t.channel_id
+            WHERE t.channel_id = $1
+            "#,
+        )
+        .bind(thread_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Threads actifs (ou archivés) d'un channel, les plus récemment actifs d'abord
+    pub async fn list_threads(&self, parent_id: Uuid, archived: bool) -> sqlx::Result<Vec<Thread>> {
+        sqlx::query_as::<_, Thread>(
+            r#"
+            SELECT c.id, c.server_id, c.parent_id, t.parent_message_id, t.owner_id, c.name,
+                   t.archived, t.archived_at, t.auto_archive_minutes, t.last_activity_at,
+                   c.rate_limit_per_user, c.created_at
+            FROM threads t
+            INNER JOIN channels c ON c.id = t.channel_id
+            WHERE c.parent_id = $1 AND t.archived = $2
+            ORDER BY t.last_activity_at DESC
+            "#,
+        )
+        .bind(parent_id)
+        .bind(archived)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn update_thread(
+        &self,
+        thread_id: Uuid,
+        name: Option<String>,
+        archived: Option<bool>,
+        auto_archive_minutes: Option<i32>,
+    ) -> sqlx::Result<Option<Thread>> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE channels SET name = COALESCE($1, name), updated_at = NOW() WHERE id = $2",
+        )
+        .bind(name)
+        .bind(thread_id)
+        .execute(&mut *tx)
+        .await?;
+
+        // Désarchiver compte comme une activité : le délai d'archivage repart de zéro
+        sqlx::query(
+            r#"
+            UPDATE threads
+            SET archived = COALESCE($1, archived),
+                archived_at = CASE
+                    WHEN $1 IS NULL OR $1 = archived THEN archived_at
+                    WHEN $1 THEN NOW()
+                    ELSE NULL
+                END,
+                last_activity_at = CASE WHEN $1 = FALSE AND archived THEN NOW() ELSE last_activity_at END,
+                auto_archive_minutes = COALESCE($2, auto_archive_minutes)
+            WHERE channel_id = $3
+            "#,
+        )
+        .bind(archived)
+        .bind(auto_archive_minutes)
+        .bind(thread_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let thread = sqlx::query_as::<_, Thread>(
+            r#"
+            SELECT c.id, c.server_id, c.parent_id, t.parent_message_id, t.owner_id, c.name,
+                   t.archived, t.archived_at, t.auto_archive_minutes, t.last_activity_at,
+                   c.rate_limit_per_user, c.created_at
+            FROM threads t
+            INNER JOIN channels c ON c.id = t.channel_id
+            WHERE t.channel_id = $1
+            "#,
+        )
+        .bind(thread_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(thread)
+    }
+
+    /// Message publié dans un thread : activité mise à jour, thread désarchivé, auteur membre
+    /// Retourne None si `channel_id` n'est pas un thread, sinon s'il était archivé
+    pub async fn record_thread_activity(
+        &self,
+        channel_id: Uuid,
+        author_id: Uuid,
+    ) -> sqlx::Result<Option<bool>> {
+        let mut tx = self.pool.begin().await?;
+
+        let was_archived: Option<bool> = sqlx::query_scalar(
+            r#"
+            UPDATE threads t
+            SET last_activity_at = NOW(), archived = FALSE, archived_at = NULL
+            FROM (SELECT channel_id, archived FROM threads WHERE channel_id = $1 FOR UPDATE) previous
+            WHERE t.channel_id = previous.channel_id
+            RETURNING previous.archived
+            "#,
+        )
+        .bind(channel_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if was_archived.is_some() {
+            sqlx::query(
+                r#"
+                INSERT INTO thread_members (thread_id, user_id, joined_at)
+                VALUES ($1, $2, NOW())
+                ON CONFLICT (thread_id, user_id) DO NOTHING
+                "#,
+            )
+            .bind(channel_id)
+            .bind(author_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(was_archived)
+    }
+
+    /// Archive les threads restés inactifs plus longtemps que leur délai, et les retourne
+    pub async fn archive_inactive_threads(&self) -> sqlx::Result<Vec<Thread>> {
+        sqlx::query_as::<_, Thread>(
+            r#"
+            WITH archived AS (
+                UPDATE threads
+                SET archived = TRUE, archived_at = NOW()
+                WHERE NOT archived
+                  AND last_activity_at + auto_archive_minutes * INTERVAL '1 minute' <= NOW()
+                RETURNING channel_id, parent_message_id, owner_id, archived, archived_at,
+                          auto_archive_minutes, last_activity_at
+            )
+            SELECT c.id, c.server_id, c.parent_id, a.parent_message_id, a.owner_id, c.name,
+                   a.archived, a.archived_at, a.auto_archive_minutes, a.last_activity_at,
+                   c.rate_limit_per_user, c.created_at
+            FROM archived a
+            INNER JOIN channels c ON c.id = a.channel_id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn add_thread_member(
+        &self,
+        thread_id: Uuid,
+        user_id: Uuid,
+    ) -> sqlx::Result<ThreadMember> {
+        sqlx::query_as::<_, ThreadMember>(
+            r#"
+            INSERT INTO thread_members (thread_id, user_id, joined_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (thread_id, user_id)
+            DO UPDATE SET joined_at = thread_members.joined_at
+            RETURNING thread_id, user_id, joined_at
+            "#,
+        )
+        .bind(thread_id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn remove_thread_member(&self, thread_id: Uuid, user_id: Uuid) -> sqlx::Result<bool> {
+        let result =
+            sqlx::query("DELETE FROM thread_members WHERE thread_id = $1 AND user_id = $2")
+                .bind(thread_id)
+                .bind(user_id)
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list_thread_members(&self, thread_id: Uuid) -> sqlx::Result<Vec<ThreadMember>> {
+        sqlx::query_as::<_, ThreadMember>(
+            r#"
+            SELECT thread_id, user_id, joined_at
+            FROM thread_members
+            WHERE thread_id = $1
+            ORDER BY joined_at
+            "#,
+        )
+        .bind(thread_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Supprime un channel avec ses threads
     pub async fn delete(&self, channel_id: Uuid) -> sqlx::Result<()> {
-        sqlx::query("DELETE FROM channels WHERE id = $1")
+        sqlx::query("DELETE FROM channels WHERE id = $1 OR (parent_id = $1 AND kind = 'thread')")
             .bind(channel_id)
             .execute(&self.pool)
             .await?;
         Ok(())
     }
+
+    /// Overwrites d'un channel (ceux de son channel d'origine pour un thread)
+    pub async fn list_overwrites(&self, channel_id: Uuid) -> sqlx::Result<Vec<ChannelOverwrite>> {
+        sqlx::query_as::<_, ChannelOverwrite>(
+            r#"
+            SELECT channel_id, target_type, target_id, allow, deny
+            FROM channel_overwrites
+            WHERE channel_id = COALESCE(
+                (SELECT parent_id FROM channels WHERE id = $1 AND kind = 'thread'),
+                $1
+            )
+            "#,
+        )
+        .bind(channel_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Overwrites de tous les channels d'un serveur
+    pub async fn list_overwrites_by_server(
+        &self,
+        server_id: Uuid,
+    ) -> sqlx::Result<Vec<ChannelOverwrite>> {
+        sqlx::query_as::<_, ChannelOverwrite>(
+            r#"
+            SELECT co.channel_id, co.target_type, co.target_id, co.allow, co.deny
+            FROM channel_overwrites co
+            INNER JOIN channels c ON c.id = co.channel_id
+            WHERE c.server_id = $1
+            "#,
+        )
+        .bind(server_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn upsert_overwrite(
+        &self,
+        channel_id: Uuid,
+        target_type: OverwriteTarget,
+        target_id: Uuid,
+        allow: Permissions,
+        deny: Permissions,
+    ) -> sqlx::Result<ChannelOverwrite> {
+        sqlx::query_as::<_, ChannelOverwrite>(
+            r#"
+            INSERT INTO channel_overwrites (channel_id, target_type, target_id, allow, deny)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (channel_id, target_type, target_id)
+            DO UPDATE SET allow = EXCLUDED.allow, deny = EXCLUDED.deny
+            RETURNING channel_id, target_type, target_id, allow, deny
+            "#,
+        )
+        .bind(channel_id)
+        .bind(target_type)
+        .bind(target_id)
+        .bind(allow)
+        .bind(deny)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn delete_overwrite(
+        &self,
+        channel_id: Uuid,
+        target_type: OverwriteTarget,
+        target_id: Uuid,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM channel_overwrites WHERE channel_id = $1 AND target_type = $2 AND target_id = $3",
+        )
+        .bind(channel_id)
+        .bind(target_type)
+        .bind(target_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
 }
@@ -1,10 +1,11 @@
-use bson::{doc, Binary};
-use chrono::Utc;
+use bson::{doc, Binary, Bson};
+use chrono::{DateTime, Utc};
 use futures::TryStreamExt;
 use mongodb::Database;
 use uuid::Uuid;
 
-use crate::models::DirectMessageItem;
+use crate::models::{DirectMessageItem, MessageSearchFilter};
+use crate::repositories::message::{search_conditions, search_cursor_condition};
 
 const COLLECTION_NAME: &str = "direct_message_items";
 
@@ -38,6 +39,15 @@ impl DirectMessageRepository {
         }
     }
 
+    fn uuid_in_filter(field: &str, uuids: &[Uuid]) -> bson::Document {
+        let values: Vec<Bson> = uuids
+            .iter()
+            .map(|uuid| Bson::Binary(Self::uuid_to_binary(*uuid)))
+            .chain(uuids.iter().map(|uuid| Bson::String(uuid.to_string())))
+            .collect();
+        doc! { field: { "$in": values } }
+    }
+
     pub async fn create(&self, message: &DirectMessageItem) -> mongodb::error::Result<()> {
         self.collection().insert_one(message).await?;
         Ok(())
@@ -52,6 +62,122 @@ impl DirectMessageRepository {
             .await
     }
 
+    pub async fn find_by_author_nonce(
+        &self,
+        author_id: Uuid,
+        nonce: &str,
+    ) -> mongodb::error::Result<Option<DirectMessageItem>> {
+        self.collection()
+            .find_one(doc! {
+                "$and": [
+                    Self::uuid_filter("author_id", author_id),
+                    { "nonce": nonce },
+                ]
+            })
+            .await
+    }
+
+    /// Libère le nonce d'un ancien message (hors fenêtre de déduplication)
+    pub async fn clear_nonce(&self, message_id: Uuid) -> mongodb::error::Result<()> {
+        self.collection()
+            .update_one(
+                Self::uuid_filter("message_id", message_id),
+                doc! { "$unset": { "nonce": "" } },
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Messages par id, supprimés compris (aperçus de réponses)
+    pub async fn find_by_ids(
+        &self,
+        message_ids: &[Uuid],
+    ) -> mongodb::error::Result<Vec<DirectMessageItem>> {
+        let cursor = self
+            .collection()
+            .find(Self::uuid_in_filter("message_id", message_ids))
+            .await?;
+        cursor.try_collect().await
+    }
+
+    /// Recherche dans les conversations `dm_ids`, les plus récents d'abord
+    /// `before` : position `(created_at, message_id)` du dernier résultat de la page précédente
+    pub async fn search(
+        &self,
+        dm_ids: &[Uuid],
+        filter: &MessageSearchFilter,
+        before: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> mongodb::error::Result<Vec<DirectMessageItem>> {
+        let mut conditions = search_conditions(filter);
+        conditions.push(Self::uuid_in_filter("dm_id", dm_ids));
+        // Les mentions ne sont pas extraites en privé : recherchées dans le contenu
+        if !filter.mention_ids.is_empty() {
+            let mentions: Vec<bson::Document> = filter
+                .mention_ids
+                .iter()
+                .map(|user_id| doc! { "content": { "$regex": format!("<@{}>", user_id) } })
+                .collect();
+            conditions.push(doc! { "$or": mentions });
+        }
+        if let Some((created_at, message_id)) = before {
+            conditions.push(search_cursor_condition(created_at, message_id));
+        }
+
+        let mut query = doc! { "$and": conditions };
+        if let Some(text) = &filter.text {
+            query.insert("$text", doc! { "$search": text });
+        }
+
+        let cursor = self
+            .collection()
+            .find(query)
+            .sort(doc! { "created_at": -1, "message_id": -1 })
+            .limit(limit)
+            .await?;
+        cursor.try_collect().await
+    }
+
+    /// Messages non supprimés qui entourent `created_at` dans une conversation : les `limit`
+    /// précédents puis les `limit` suivants, chacun dans l'ordre chronologique
+    pub async fn list_context(
+        &self,
+        dm_id: Uuid,
+        created_at: DateTime<Utc>,
+        limit: i64,
+    ) -> mongodb::error::Result<(Vec<DirectMessageItem>, Vec<DirectMessageItem>)> {
+        let around = |operator: &str| {
+            doc! {
+                "$and": [
+                    Self::uuid_filter("dm_id", dm_id),
+                    { "deleted_at": null },
+                    { "created_at": { operator: created_at } },
+                ]
+            }
+        };
+
+        let mut before: Vec<DirectMessageItem> = self
+            .collection()
+            .find(around("$lt"))
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await?
+            .try_collect()
+            .await?;
+        before.reverse();
+
+        let after = self
+            .collection()
+            .find(around("$gt"))
+            .sort(doc! { "created_at": 1 })
+            .limit(limit)
+            .await?
+            .try_collect()
+            .await?;
+
+        Ok((before, after))
+    }
+
     pub async fn list_by_dm(
         &self,
         dm_id: Uuid,
@@ -172,6 +298,71 @@ impl DirectMessageRepository {
         Ok(())
     }
 
+    /// Épingle (`pinned_by`) ou désépingle (None) un message non supprimé
+    /// Retourne false si le message était déjà dans cet état
+    pub async fn set_pinned(
+        &self,
+        message_id: Uuid,
+        pinned_by: Option<Uuid>,
+    ) -> mongodb::error::Result<bool> {
+        let (state, update) = match pinned_by {
+            Some(pinned_by) => (
+                doc! { "pinned_at": null },
+                doc! {
+                    "$set": {
+                        "pinned_at": Utc::now(),
+                        "pinned_by": Self::uuid_to_binary(pinned_by),
+                    }
+                },
+            ),
+            None => (
+                doc! { "pinned_at": { "$ne": null } },
+                doc! { "$unset": { "pinned_at": "", "pinned_by": "" } },
+            ),
+        };
+
+        let result = self
+            .collection()
+            .update_one(
+                doc! {
+                    "$and": [
+                        Self::uuid_filter("message_id", message_id),
+                        { "deleted_at": null },
+                        state,
+                    ]
+                },
+                update,
+            )
+            .await?;
+        Ok(result.modified_count > 0)
+    }
+
+    fn pins_filter(dm_id: Uuid) -> bson::Document {
+        doc! {
+            "$and": [
+                Self::uuid_filter("dm_id", dm_id),
+                { "deleted_at": null },
+                { "pinned_at": { "$ne": null } },
+            ]
+        }
+    }
+
+    pub async fn count_pins(&self, dm_id: Uuid) -> mongodb::error::Result<u64> {
+        self.collection()
+            .count_documents(Self::pins_filter(dm_id))
+            .await
+    }
+
+    /// Messages épinglés non supprimés, les derniers épinglés d'abord
+    pub async fn list_pins(&self, dm_id: Uuid) -> mongodb::error::Result<Vec<DirectMessageItem>> {
+        let cursor = self
+            .collection()
+            .find(Self::pins_filter(dm_id))
+            .sort(doc! { "pinned_at": -1 })
+            .await?;
+        cursor.try_collect().await
+    }
+
     pub async fn soft_delete(&self, message_id: Uuid) -> mongodb::error::Result<()> {
         self.collection()
             .update_one(
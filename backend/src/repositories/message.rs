@@ -1,13 +1,64 @@
-use bson::{doc, Binary};
-use chrono::Utc;
+use bson::{doc, Binary, Bson};
+use chrono::{DateTime, Utc};
 use futures::TryStreamExt;
 use mongodb::Database;
 use uuid::Uuid;
 
-use crate::models::ChannelMessage;
+use crate::models::{ChannelMessage, MessageSearchFilter};
 
 const COLLECTION_NAME: &str = "channel_messages";
 
+/// Vrai si l'erreur MongoDB est une violation d'index unique (E11000)
+pub fn is_duplicate_key_error(err: &mongodb::error::Error) -> bool {
+    matches!(
+        err.kind.as_ref(),
+        mongodb::error::ErrorKind::Write(mongodb::error::WriteFailure::WriteError(write_error))
+            if write_error.code == 11000
+    )
+}
+
+/// Conditions de recherche communes aux messages de channel et privés
+/// (le texte libre, `$text`, doit rester à la racine du filtre)
+pub fn search_conditions(filter: &MessageSearchFilter) -> Vec<bson::Document> {
+    let mut conditions = vec![doc! { "deleted_at": null }];
+    if !filter.author_ids.is_empty() {
+        conditions.push(MessageRepository::uuid_in_filter(
+            "author_id",
+            &filter.author_ids,
+        ));
+    }
+    if filter.has_attachment {
+        conditions.push(doc! { "content": { "$regex": "/files/" } });
+    }
+    if filter.has_link {
+        conditions.push(doc! { "content": { "$regex": "https?://", "$options": "i" } });
+    }
+    if let Some(before) = filter.before {
+        conditions.push(doc! { "created_at": { "$lt": before } });
+    }
+    if let Some(after) = filter.after {
+        conditions.push(doc! { "created_at": { "$gte": after } });
+    }
+    conditions
+}
+
+/// Résultats strictement après le curseur `(created_at, message_id)` dans l'ordre
+/// décroissant : le message_id départage les messages du même instant
+pub fn search_cursor_condition(created_at: DateTime<Utc>, message_id: Uuid) -> bson::Document {
+    doc! {
+        "$or": [
+            { "created_at": { "$lt": created_at } },
+            {
+                "created_at": created_at,
+                "$or": [
+                    { "message_id": { "$lt": MessageRepository::uuid_to_binary(message_id) } },
+                    { "message_id": { "$lt": message_id.to_string() } },
+                ],
+            },
+        ]
+    }
+}
+
 #[derive(Clone)]
 pub struct MessageRepository {
     db: Database,
@@ -39,6 +90,16 @@ impl MessageRepository {
         }
     }
 
+    /// Filtre qui matche une liste d'UUID stockés en Binary Generic OU en string
+    fn uuid_in_filter(field: &str, uuids: &[Uuid]) -> bson::Document {
+        let values: Vec<Bson> = uuids
+            .iter()
+            .map(|uuid| Bson::Binary(Self::uuid_to_binary(*uuid)))
+            .chain(uuids.iter().map(|uuid| Bson::String(uuid.to_string())))
+            .collect();
+        doc! { field: { "$in": values } }
+    }
+
     pub async fn create(&self, message: &ChannelMessage) -> mongodb::error::Result<()> {
         self.collection().insert_one(message).await?;
         Ok(())
@@ -53,6 +114,32 @@ impl MessageRepository {
             .await
     }
 
+    pub async fn find_by_author_nonce(
+        &self,
+        author_id: Uuid,
+        nonce: &str,
+    ) -> mongodb::error::Result<Option<ChannelMessage>> {
+        self.collection()
+            .find_one(doc! {
+                "$and": [
+                    Self::uuid_filter("author_id", author_id),
+                    { "nonce": nonce },
+                ]
+            })
+            .await
+    }
+
+    /// Libère le nonce d'un ancien message (hors fenêtre de déduplication)
+    pub async fn clear_nonce(&self, message_id: Uuid) -> mongodb::error::Result<()> {
+        self.collection()
+            .update_one(
+                Self::uuid_filter("message_id", message_id),
+                doc! { "$unset": { "nonce": "" } },
+            )
+            .await?;
+        Ok(())
+    }
+
     pub async fn list_by_channel(
         &self,
         channel_id: Uuid,
@@ -86,11 +173,166 @@ impl MessageRepository {
         cursor.try_collect().await
     }
 
+    /// Recherche dans les channels `channel_ids`, les plus récents d'abord
+    /// `before` : position `(created_at, message_id)` du dernier résultat de la page précédente
+    pub async fn search(
+        &self,
+        channel_ids: &[Uuid],
+        filter: &MessageSearchFilter,
+        before: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> mongodb::error::Result<Vec<ChannelMessage>> {
+        let mut conditions = search_conditions(filter);
+        conditions.push(Self::uuid_in_filter("channel_id", channel_ids));
+        if !filter.mention_ids.is_empty() {
+            conditions.push(Self::uuid_in_filter("mentions", &filter.mention_ids));
+        }
+        if let Some((created_at, message_id)) = before {
+            conditions.push(search_cursor_condition(created_at, message_id));
+        }
+
+        let mut query = doc! { "$and": conditions };
+        if let Some(text) = &filter.text {
+            query.insert("$text", doc! { "$search": text });
+        }
+
+        let cursor = self
+            .collection()
+            .find(query)
+            .sort(doc! { "created_at": -1, "message_id": -1 })
+            .limit(limit)
+            .await?;
+        cursor.try_collect().await
+    }
+
+    /// Messages non supprimés qui entourent `created_at` dans un channel : les `limit`
+    /// précédents puis les `limit` suivants, chacun dans l'ordre chronologique
+    pub async fn list_context(
+        &self,
+        channel_id: Uuid,
+        created_at: DateTime<Utc>,
+        limit: i64,
+    ) -> mongodb::error::Result<(Vec<ChannelMessage>, Vec<ChannelMessage>)> {
+        let around = |operator: &str| {
+            doc! {
+                "$and": [
+                    Self::uuid_filter("channel_id", channel_id),
+                    { "deleted_at": null },
+                    { "created_at": { operator: created_at } },
+                ]
+            }
+        };
+
+        let mut before: Vec<ChannelMessage> = self
+            .collection()
+            .find(around("$lt"))
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await?
+            .try_collect()
+            .await?;
+        before.reverse();
+
+        let after = self
+            .collection()
+            .find(around("$gt"))
+            .sort(doc! { "created_at": 1 })
+            .limit(limit)
+            .await?
+            .try_collect()
+            .await?;
+
+        Ok((before, after))
+    }
+
+    /// Messages non supprimés d'un channel parmi `message_ids`
+    pub async fn list_by_ids(
+        &self,
+        channel_id: Uuid,
+        message_ids: &[Uuid],
+    ) -> mongodb::error::Result<Vec<ChannelMessage>> {
+        let filter = doc! {
+            "$and": [
+                Self::uuid_filter("channel_id", channel_id),
+                Self::uuid_in_filter("message_id", message_ids),
+                { "deleted_at": null },
+            ]
+        };
+
+        let cursor = self.collection().find(filter).await?;
+        cursor.try_collect().await
+    }
+
+    /// Messages par id, supprimés compris (aperçus de réponses)
+    pub async fn find_by_ids(
+        &self,
+        message_ids: &[Uuid],
+    ) -> mongodb::error::Result<Vec<ChannelMessage>> {
+        let cursor = self
+            .collection()
+            .find(Self::uuid_in_filter("message_id", message_ids))
+            .await?;
+        cursor.try_collect().await
+    }
+
+    /// Dernier message publié par un auteur dans un channel, même supprimé depuis
+    /// (les annonces recopiées ne comptent pas)
+    pub async fn find_last_by_author(
+        &self,
+        channel_id: Uuid,
+        author_id: Uuid,
+    ) -> mongodb::error::Result<Option<ChannelMessage>> {
+        let filter = doc! {
+            "$and": [
+                Self::uuid_filter("channel_id", channel_id),
+                Self::uuid_filter("author_id", author_id),
+                { "crossposted_from": null },
+            ]
+        };
+
+        self.collection()
+            .find_one(filter)
+            .sort(doc! { "created_at": -1 })
+            .await
+    }
+
+    /// Derniers messages non supprimés d'un auteur dans un channel
+    pub async fn list_recent_by_author(
+        &self,
+        channel_id: Uuid,
+        author_id: Uuid,
+        limit: i64,
+    ) -> mongodb::error::Result<Vec<ChannelMessage>> {
+        let filter = doc! {
+            "$and": [
+                Self::uuid_filter("channel_id", channel_id),
+                Self::uuid_filter("author_id", author_id),
+                { "deleted_at": null },
+            ]
+        };
+
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .build();
+
+        let cursor = self.collection().find(filter).with_options(options).await?;
+        cursor.try_collect().await
+    }
+
+    /// Nouveau contenu et mentions qui en sont extraites
     pub async fn update_content(
         &self,
         message_id: Uuid,
         content: &str,
+        mentions: &[Uuid],
+        mention_roles: &[Uuid],
+        mention_everyone: bool,
     ) -> mongodb::error::Result<()> {
+        let to_binaries = |ids: &[Uuid]| -> Vec<Binary> {
+            ids.iter().map(|id| Self::uuid_to_binary(*id)).collect()
+        };
+
         self.collection()
             .update_one(
                 Self::uuid_filter("message_id", message_id),
@@ -98,6 +340,29 @@ impl MessageRepository {
                     "$set": {
                         "content": content,
                         "edited_at": Utc::now(),
+                        "mentions": to_binaries(mentions),
+                        "mention_roles": to_binaries(mention_roles),
+                        "mention_everyone": mention_everyone,
+                    }
+                },
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Rattache le thread ouvert depuis un message
+    pub async fn set_thread(
+        &self,
+        message_id: Uuid,
+        thread_id: Uuid,
+    ) -> mongodb::error::Result<()> {
+        self.collection()
+            .update_one(
+                Self::uuid_filter("message_id", message_id),
+                doc! {
+                    "$set": {
+                        "thread_id": Self::uuid_to_binary(thread_id),
+                        "thread_last_activity_at": Utc::now(),
                     }
                 },
             )
@@ -105,6 +370,102 @@ impl MessageRepository {
         Ok(())
     }
 
+    /// Détache le thread supprimé d'un message, qui peut en ouvrir un nouveau
+    pub async fn clear_thread(&self, message_id: Uuid) -> mongodb::error::Result<()> {
+        self.collection()
+            .update_one(
+                Self::uuid_filter("message_id", message_id),
+                doc! {
+                    "$unset": {
+                        "thread_id": "",
+                        "thread_message_count": "",
+                        "thread_last_activity_at": "",
+                    }
+                },
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Compte un nouveau message du thread ouvert depuis `message_id`
+    pub async fn record_thread_message(&self, message_id: Uuid) -> mongodb::error::Result<()> {
+        self.collection()
+            .update_one(
+                Self::uuid_filter("message_id", message_id),
+                doc! {
+                    "$inc": { "thread_message_count": 1 },
+                    "$set": { "thread_last_activity_at": Utc::now() },
+                },
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Épingle (`pinned_by`) ou désépingle (None) un message non supprimé
+    /// Retourne false si le message était déjà dans cet état
+    pub async fn set_pinned(
+        &self,
+        message_id: Uuid,
+        pinned_by: Option<Uuid>,
+    ) -> mongodb::error::Result<bool> {
+        let (state, update) = match pinned_by {
+            Some(pinned_by) => (
+                doc! { "pinned_at": null },
+                doc! {
+                    "$set": {
+                        "pinned_at": Utc::now(),
+                        "pinned_by": Self::uuid_to_binary(pinned_by),
+                    }
+                },
+            ),
+            None => (
+                doc! { "pinned_at": { "$ne": null } },
+                doc! { "$unset": { "pinned_at": "", "pinned_by": "" } },
+            ),
+        };
+
+        let result = self
+            .collection()
+            .update_one(
+                doc! {
+                    "$and": [
+                        Self::uuid_filter("message_id", message_id),
+                        { "deleted_at": null },
+                        state,
+                    ]
+                },
+                update,
+            )
+            .await?;
+        Ok(result.modified_count > 0)
+    }
+
+    fn pins_filter(channel_id: Uuid) -> bson::Document {
+        doc! {
+            "$and": [
+                Self::uuid_filter("channel_id", channel_id),
+                { "deleted_at": null },
+                { "pinned_at": { "$ne": null } },
+            ]
+        }
+    }
+
+    pub async fn count_pins(&self, channel_id: Uuid) -> mongodb::error::Result<u64> {
+        self.collection()
+            .count_documents(Self::pins_filter(channel_id))
+            .await
+    }
+
+    /// Messages épinglés non supprimés, les derniers épinglés d'abord
+    pub async fn list_pins(&self, channel_id: Uuid) -> mongodb::error::Result<Vec<ChannelMessage>> {
+        let cursor = self
+            .collection()
+            .find(Self::pins_filter(channel_id))
+            .sort(doc! { "pinned_at": -1 })
+            .await?;
+        cursor.try_collect().await
+    }
+
     pub async fn soft_delete(
         &self,
         message_id: Uuid,
@@ -124,6 +485,32 @@ impl MessageRepository {
         Ok(())
     }
 
+    /// Soft delete groupé en un seul `update_many`, retourne le nombre de messages supprimés
+    pub async fn soft_delete_many(
+        &self,
+        message_ids: &[Uuid],
+        deleted_by: Uuid,
+    ) -> mongodb::error::Result<u64> {
+        let result = self
+            .collection()
+            .update_many(
+                doc! {
+                    "$and": [
+                        Self::uuid_in_filter("message_id", message_ids),
+                        { "deleted_at": null },
+                    ]
+                },
+                doc! {
+                    "$set": {
+                        "deleted_at": Utc::now(),
+                        "deleted_by": Self::uuid_to_binary(deleted_by),
+                    }
+                },
+            )
+            .await?;
+        Ok(result.modified_count)
+    }
+
     pub async fn add_reaction(
         &self,
         message_id: Uuid,
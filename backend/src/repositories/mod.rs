@@ -9,7 +9,7 @@ pub mod server;
 pub mod user;
 
 pub use attachment::AttachmentRepository;
-pub use channel::ChannelRepository;
+pub use channel::{ChannelPosition, ChannelRepository};
 pub use dm::DmRepository;
 pub use dm_message::DirectMessageRepository;
 pub use friendship::FriendshipRepository;
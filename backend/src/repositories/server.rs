@@ -1,7 +1,11 @@
 use sqlx::PgPool;
+use std::collections::HashMap;
 use uuid::Uuid;
 
-use crate::models::{MemberRole, Server, ServerBan, ServerMember};
+use crate::models::{
+    AuditAction, AuditLogEntry, MemberProfile, MemberRole, NewAuditLogEntry, Permissions, Role,
+    Server, ServerBan, ServerMember,
+};
 
 #[derive(Clone)]
 pub struct ServerRepository {
@@ -128,7 +132,7 @@ impl ServerRepository {
         user_id: Uuid,
     ) -> sqlx::Result<Option<ServerMember>> {
         sqlx::query_as::<_, ServerMember>(
-            "SELECT server_id, user_id, role, joined_at FROM server_members WHERE server_id = $1 AND user_id = $2",
+            "SELECT server_id, user_id, role, joined_at, communication_disabled_until, nickname, avatar_url FROM server_members WHERE server_id = $1 AND user_id = $2",
         )
         .bind(server_id)
         .bind(user_id)
@@ -146,7 +150,7 @@ impl ServerRepository {
             r#"
             INSERT INTO server_members (server_id, user_id, role, joined_at)
             VALUES ($1, $2, $3::member_role, NOW())
-            RETURNING server_id, user_id, role, joined_at
+            RETURNING server_id, user_id, role, joined_at, communication_disabled_until, nickname, avatar_url
             "#,
         )
         .bind(server_id)
@@ -167,7 +171,7 @@ impl ServerRepository {
 
     pub async fn list_members(&self, server_id: Uuid) -> sqlx::Result<Vec<ServerMember>> {
         sqlx::query_as::<_, ServerMember>(
-            "SELECT server_id, user_id, role, joined_at FROM server_members WHERE server_id = $1 ORDER BY joined_at",
+            "SELECT server_id, user_id, role, joined_at, communication_disabled_until, nickname, avatar_url FROM server_members WHERE server_id = $1 ORDER BY joined_at",
         )
         .bind(server_id)
         .fetch_all(&self.pool)
@@ -185,7 +189,7 @@ impl ServerRepository {
             UPDATE server_members
             SET role = $1::member_role
             WHERE server_id = $2 AND user_id = $3
-            RETURNING server_id, user_id, role, joined_at
+            RETURNING server_id, user_id, role, joined_at, communication_disabled_until, nickname, avatar_url
             "#,
         )
         .bind(role)
@@ -195,6 +199,110 @@ impl ServerRepository {
         .await
     }
 
+    /// Fixe (ou lève avec None) le timeout d'un membre
+    pub async fn set_member_timeout(
+        &self,
+        server_id: Uuid,
+        user_id: Uuid,
+        until: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> sqlx::Result<Option<ServerMember>> {
+        sqlx::query_as::<_, ServerMember>(
+            r#"
+            UPDATE server_members
+            SET communication_disabled_until = $1
+            WHERE server_id = $2 AND user_id = $3
+            RETURNING server_id, user_id, role, joined_at, communication_disabled_until, nickname, avatar_url
+            "#,
+        )
+        .bind(until)
+        .bind(server_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Remplace le pseudo et l'avatar de serveur d'un membre
+    pub async fn set_member_profile(
+        &self,
+        server_id: Uuid,
+        user_id: Uuid,
+        nickname: Option<&str>,
+        avatar_url: Option<&str>,
+    ) -> sqlx::Result<Option<ServerMember>> {
+        sqlx::query_as::<_, ServerMember>(
+            r#"
+            UPDATE server_members
+            SET nickname = $1, avatar_url = $2
+            WHERE server_id = $3 AND user_id = $4
+            RETURNING server_id, user_id, role, joined_at, communication_disabled_until, nickname, avatar_url
+            "#,
+        )
+        .bind(nickname)
+        .bind(avatar_url)
+        .bind(server_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Profils affichés dans un serveur ; les non-membres gardent leur profil global
+    pub async fn get_member_profiles(
+        &self,
+        server_id: Uuid,
+        user_ids: &[Uuid],
+    ) -> sqlx::Result<HashMap<Uuid, MemberProfile>> {
+        if user_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let profiles = sqlx::query_as::<_, MemberProfile>(
+            r#"
+            SELECT u.id AS user_id, u.username, sm.nickname,
+                   COALESCE(sm.avatar_url, u.avatar_url) AS avatar_url
+            FROM users u
+            LEFT JOIN server_members sm ON sm.user_id = u.id AND sm.server_id = $1
+            WHERE u.id = ANY($2)
+            "#,
+        )
+        .bind(server_id)
+        .bind(user_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(profiles
+            .into_iter()
+            .map(|profile| (profile.user_id, profile))
+            .collect())
+    }
+
+    pub async fn get_member_profile(
+        &self,
+        server_id: Uuid,
+        user_id: Uuid,
+    ) -> sqlx::Result<Option<MemberProfile>> {
+        let mut profiles = self.get_member_profiles(server_id, &[user_id]).await?;
+        Ok(profiles.remove(&user_id))
+    }
+
+    /// Utilisateurs de `user_ids` membres du serveur
+    pub async fn filter_members(
+        &self,
+        server_id: Uuid,
+        user_ids: &[Uuid],
+    ) -> sqlx::Result<Vec<Uuid>> {
+        if user_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        sqlx::query_scalar::<_, Uuid>(
+            "SELECT user_id FROM server_members WHERE server_id = $1 AND user_id = ANY($2)",
+        )
+        .bind(server_id)
+        .bind(user_ids)
+        .fetch_all(&self.pool)
+        .await
+    }
+
     pub async fn upsert_ban(
         &self,
         server_id: Uuid,
@@ -230,12 +338,33 @@ impl ServerRepository {
         Ok(())
     }
 
+    pub async fn find_ban(
+        &self,
+        server_id: Uuid,
+        user_id: Uuid,
+    ) -> sqlx::Result<Option<ServerBan>> {
+        sqlx::query_as::<_, ServerBan>(
+            r#"
+            SELECT server_id, user_id, banned_by, reason, expires_at, banned_at
+            FROM server_bans
+            WHERE server_id = $1
+              AND user_id = $2
+              AND (expires_at IS NULL OR expires_at > NOW())
+            "#,
+        )
+        .bind(server_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
     pub async fn list_bans(&self, server_id: Uuid) -> sqlx::Result<Vec<ServerBan>> {
         sqlx::query_as::<_, ServerBan>(
             r#"
             SELECT server_id, user_id, banned_by, reason, expires_at, banned_at
             FROM server_bans
             WHERE server_id = $1
+              AND (expires_at IS NULL OR expires_at > NOW())
             ORDER BY banned_at DESC
             "#,
         )
@@ -244,6 +373,20 @@ impl ServerRepository {
         .await
     }
 
+    /// Supprime les bans arrivés à expiration et les retourne
+    /// (DELETE ... RETURNING : chaque ban n'est levé que par une seule instance)
+    pub async fn delete_expired_bans(&self) -> sqlx::Result<Vec<ServerBan>> {
+        sqlx::query_as::<_, ServerBan>(
+            r#"
+            DELETE FROM server_bans
+            WHERE expires_at IS NOT NULL AND expires_at <= NOW()
+            RETURNING server_id, user_id, banned_by, reason, expires_at, banned_at
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
     pub async fn is_user_banned(&self, server_id: Uuid, user_id: Uuid) -> sqlx::Result<bool> {
         let banned: Option<bool> = sqlx::query_scalar(
             r#"
@@ -260,4 +403,265 @@ impl ServerRepository {
         .await?;
         Ok(banned.unwrap_or(false))
     }
+
+    pub async fn list_roles(&self, server_id: Uuid) -> sqlx::Result<Vec<Role>> {
+        sqlx::query_as::<_, Role>(
+            r#"
+            SELECT id, server_id, name, color, position, permissions, is_default, created_at, updated_at
+            FROM roles
+            WHERE server_id = $1
+            ORDER BY position DESC, created_at
+            "#,
+        )
+        .bind(server_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Rôles de `role_ids` appartenant au serveur, hors rôle par défaut @everyone
+    pub async fn filter_roles(
+        &self,
+        server_id: Uuid,
+        role_ids: &[Uuid],
+    ) -> sqlx::Result<Vec<Uuid>> {
+        if role_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        sqlx::query_scalar::<_, Uuid>(
+            "SELECT id FROM roles WHERE server_id = $1 AND id = ANY($2) AND NOT is_default",
+        )
+        .bind(server_id)
+        .bind(role_ids)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn find_role(&self, server_id: Uuid, role_id: Uuid) -> sqlx::Result<Option<Role>> {
+        sqlx::query_as::<_, Role>(
+            r#"
+            SELECT id, server_id, name, color, position, permissions, is_default, created_at, updated_at
+            FROM roles
+            WHERE server_id = $1 AND id = $2
+            "#,
+        )
+        .bind(server_id)
+        .bind(role_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn find_role_by_name(
+        &self,
+        server_id: Uuid,
+        name: &str,
+    ) -> sqlx::Result<Option<Role>> {
+        sqlx::query_as::<_, Role>(
+            r#"
+            SELECT id, server_id, name, color, position, permissions, is_default, created_at, updated_at
+            FROM roles
+            WHERE server_id = $1 AND name = $2 AND NOT is_default
+            ORDER BY position DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(server_id)
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn create_role(
+        &self,
+        server_id: Uuid,
+        name: &str,
+        color: i32,
+        position: i32,
+        permissions: Permissions,
+        is_default: bool,
+    ) -> sqlx::Result<Role> {
+        sqlx::query_as::<_, Role>(
+            r#"
+            INSERT INTO roles (server_id, name, color, position, permissions, is_default)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, server_id, name, color, position, permissions, is_default, created_at, updated_at
+            "#,
+        )
+        .bind(server_id)
+        .bind(name)
+        .bind(color)
+        .bind(position)
+        .bind(permissions)
+        .bind(is_default)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn update_role(
+        &self,
+        role_id: Uuid,
+        name: Option<String>,
+        color: Option<i32>,
+        position: Option<i32>,
+        permissions: Option<Permissions>,
+    ) -> sqlx::Result<Option<Role>> {
+        sqlx::query_as::<_, Role>(
+            r#"
+            UPDATE roles
+            SET name = COALESCE($1, name),
+                color = COALESCE($2, color),
+                position = COALESCE($3, position),
+                permissions = COALESCE($4, permissions),
+                updated_at = NOW()
+            WHERE id = $5
+            RETURNING id, server_id, name, color, position, permissions, is_default, created_at, updated_at
+            "#,
+        )
+        .bind(name)
+        .bind(color)
+        .bind(position)
+        .bind(permissions)
+        .bind(role_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Supprime un rôle et ses overwrites de channel (pas de clé étrangère sur `target_id`)
+    pub async fn delete_role(&self, role_id: Uuid) -> sqlx::Result<()> {
+        sqlx::query(
+            r#"
+            WITH overwrites AS (
+                DELETE FROM channel_overwrites WHERE target_type = 'role' AND target_id = $1
+            )
+            DELETE FROM roles WHERE id = $1
+            "#,
+        )
+        .bind(role_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Rôles qui s'appliquent à un membre : rôle par défaut + rôles attribués
+    pub async fn list_member_roles(
+        &self,
+        server_id: Uuid,
+        user_id: Uuid,
+    ) -> sqlx::Result<Vec<Role>> {
+        sqlx::query_as::<_, Role>(
+            r#"
+            SELECT r.id, r.server_id, r.name, r.color, r.position, r.permissions, r.is_default, r.created_at, r.updated_at
+            FROM roles r
+            WHERE r.server_id = $1
+              AND (r.is_default OR EXISTS (
+                  SELECT 1 FROM member_roles mr
+                  WHERE mr.role_id = r.id AND mr.server_id = $1 AND mr.user_id = $2
+              ))
+            ORDER BY r.position DESC
+            "#,
+        )
+        .bind(server_id)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Attributions (user_id, role_id) de tous les membres d'un serveur
+    pub async fn list_role_assignments(&self, server_id: Uuid) -> sqlx::Result<Vec<(Uuid, Uuid)>> {
+        sqlx::query_as::<_, (Uuid, Uuid)>(
+            "SELECT user_id, role_id FROM member_roles WHERE server_id = $1",
+        )
+        .bind(server_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn add_member_role(
+        &self,
+        server_id: Uuid,
+        user_id: Uuid,
+        role_id: Uuid,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO member_roles (server_id, user_id, role_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(server_id)
+        .bind(user_id)
+        .bind(role_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn remove_member_role(
+        &self,
+        server_id: Uuid,
+        user_id: Uuid,
+        role_id: Uuid,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            "DELETE FROM member_roles WHERE server_id = $1 AND user_id = $2 AND role_id = $3",
+        )
+        .bind(server_id)
+        .bind(user_id)
+        .bind(role_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn create_audit_entry(&self, entry: &NewAuditLogEntry) -> sqlx::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_log (server_id, actor_id, action, target_id, before, after, reason)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(entry.server_id)
+        .bind(entry.actor_id)
+        .bind(entry.action)
+        .bind(entry.target_id)
+        .bind(&entry.before)
+        .bind(&entry.after)
+        .bind(&entry.reason)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Journal d'audit, du plus récent au plus ancien, à partir de l'entrée `before` (exclue)
+    pub async fn list_audit_log(
+        &self,
+        server_id: Uuid,
+        actor_id: Option<Uuid>,
+        action: Option<AuditAction>,
+        before: Option<Uuid>,
+        limit: i64,
+    ) -> sqlx::Result<Vec<AuditLogEntry>> {
+        sqlx::query_as::<_, AuditLogEntry>(
+            r#"
+            SELECT id, server_id, actor_id, action, target_id, before, after, reason, created_at
+            FROM audit_log
+            WHERE server_id = $1
+              AND ($2::uuid IS NULL OR actor_id = $2)
+              AND ($3::text IS NULL OR action = $3)
+              AND ($4::uuid IS NULL OR (created_at, id) < (
+                  SELECT created_at, id FROM audit_log WHERE id = $4 AND server_id = $1
+              ))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $5
+            "#,
+        )
+        .bind(server_id)
+        .bind(actor_id)
+        .bind(action)
+        .bind(before)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
 }
@@ -1,5 +1,5 @@
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 
@@ -12,10 +12,31 @@ pub fn routes() -> Router<AppState> {
             "/servers/{server_id}/channels",
             post(channels::create_channel).get(channels::list_channels),
         )
+        .route(
+            "/servers/{server_id}/channels/positions",
+            patch(channels::update_channel_positions),
+        )
         .route(
             "/channels/{id}",
             get(channels::get_channel)
                 .put(channels::update_channel)
                 .delete(channels::delete_channel),
         )
+        .route("/channels/{id}/permissions", get(channels::list_overwrites))
+        .route(
+            "/channels/{id}/permissions/{targetType}/{targetId}",
+            put(channels::set_overwrite).delete(channels::delete_overwrite),
+        )
+        .route(
+            "/channels/{id}/followers",
+            get(channels::list_followers).post(channels::follow_channel),
+        )
+        .route(
+            "/channels/{id}/followers/{target_channel_id}",
+            delete(channels::unfollow_channel),
+        )
+        .route(
+            "/channels/{id}/voice-states",
+            get(channels::list_voice_states),
+        )
 }
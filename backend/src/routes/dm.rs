@@ -15,6 +15,11 @@ pub fn routes() -> Router<AppState> {
             "/conversations/{dm_id}/messages",
             get(handlers::dm::list_messages).post(handlers::dm::create_message),
         )
+        .route("/conversations/{dm_id}/pins", get(handlers::dm::list_pins))
+        .route(
+            "/conversations/{dm_id}/pins/{message_id}",
+            put(handlers::dm::pin_message).delete(handlers::dm::unpin_message),
+        )
         .route(
             "/conversations/messages/{id}",
             put(handlers::dm::update_message).delete(handlers::dm::delete_message),
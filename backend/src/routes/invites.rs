@@ -13,6 +13,9 @@ pub fn routes() -> Router<AppState> {
             post(invites::create_invite).get(invites::list_invites),
         )
         .route("/invites/join", post(invites::join_server_with_code))
-        .route("/invites/{code}", get(invites::get_invite))
+        .route(
+            "/invites/{code}",
+            get(invites::get_invite).delete(invites::revoke_invite),
+        )
         .route("/invites/{code}/accept", post(invites::accept_invite))
 }
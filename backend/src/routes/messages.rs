@@ -1,5 +1,5 @@
 use axum::{
-    routing::{post, put},
+    routing::{get, post, put},
     Router,
 };
 
@@ -12,6 +12,15 @@ pub fn routes() -> Router<AppState> {
             "/channels/{channel_id}/messages",
             post(messages::create_message).get(messages::list_messages),
         )
+        .route(
+            "/channels/{channel_id}/messages/bulk-delete",
+            post(messages::bulk_delete_messages),
+        )
+        .route("/channels/{channel_id}/pins", get(messages::list_pins))
+        .route(
+            "/channels/{channel_id}/pins/{message_id}",
+            put(messages::pin_message).delete(messages::unpin_message),
+        )
         .route(
             "/messages/{id}",
             put(messages::update_message).delete(messages::delete_message),
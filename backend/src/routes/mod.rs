@@ -4,7 +4,10 @@ pub mod dm;
 pub mod friends;
 pub mod invites;
 pub mod messages;
+pub mod roles;
+pub mod search;
 pub mod servers;
+pub mod threads;
 pub mod upload;
 
 use crate::AppState;
@@ -15,7 +18,10 @@ pub fn create_router() -> Router<AppState> {
         .nest("/servers", servers::routes())
         .merge(channels::routes())
         .merge(messages::routes())
+        .merge(threads::routes())
+        .merge(search::routes())
         .merge(invites::routes())
+        .merge(roles::routes())
         .merge(friends::routes())
         .merge(dm::routes())
         .merge(upload::routes())
@@ -0,0 +1,23 @@
+use axum::{
+    routing::{get, put},
+    Router,
+};
+
+use crate::handlers::roles;
+use crate::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/servers/{id}/roles",
+            get(roles::list_roles).post(roles::create_role),
+        )
+        .route(
+            "/servers/{id}/roles/{roleId}",
+            put(roles::update_role).delete(roles::delete_role),
+        )
+        .route(
+            "/servers/{id}/members/{userId}/roles/{roleId}",
+            put(roles::add_member_role).delete(roles::remove_member_role),
+        )
+}
@@ -0,0 +1,13 @@
+use axum::{routing::get, Router};
+
+use crate::handlers::search;
+use crate::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/servers/{id}/messages/search",
+            get(search::search_server_messages),
+        )
+        .route("/conversations/search", get(search::search_direct_messages))
+}
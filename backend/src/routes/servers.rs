@@ -1,7 +1,7 @@
 use crate::handlers::servers;
 use crate::AppState;
 use axum::{
-    routing::{delete, get, post, put},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 
@@ -27,6 +27,15 @@ pub fn routes() -> Router<AppState> {
             "/{id}/members/{userId}/ban",
             post(servers::ban_member).delete(servers::unban_member),
         )
+        .route(
+            "/{id}/members/{userId}/timeout",
+            put(servers::timeout_member),
+        )
+        .route(
+            "/{id}/members/{userId}/profile",
+            patch(servers::update_member_profile),
+        )
         .route("/{id}/bans", get(servers::list_bans))
+        .route("/{id}/audit-log", get(servers::list_audit_log))
         .route("/{id}/transfer", put(servers::transfer_ownership))
 }
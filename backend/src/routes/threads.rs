@@ -0,0 +1,25 @@
+use axum::{
+    routing::{get, post, put},
+    Router,
+};
+
+use crate::handlers::threads;
+use crate::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/channels/{channel_id}/messages/{message_id}/threads",
+            post(threads::create_thread),
+        )
+        .route("/channels/{channel_id}/threads", get(threads::list_threads))
+        .route(
+            "/threads/{id}",
+            get(threads::get_thread).patch(threads::update_thread),
+        )
+        .route("/threads/{id}/members", get(threads::list_members))
+        .route(
+            "/threads/{id}/members/@me",
+            put(threads::join_thread).delete(threads::leave_thread),
+        )
+}
@@ -0,0 +1,52 @@
+//! Journal d'audit d'un serveur
+//! Les services écrivent une entrée après chaque action de modération ou d'administration
+
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::{AuditLogEntry, AuditLogQuery, NewAuditLogEntry, Permissions};
+use crate::repositories::ServerRepository;
+use crate::services::roles::require_permission;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 100;
+
+/// Écrit une entrée. L'action est déjà effectuée : un échec est journalisé, pas remonté
+pub async fn record(server_repo: &ServerRepository, entry: NewAuditLogEntry) {
+    if let Err(err) = server_repo.create_audit_entry(&entry).await {
+        tracing::warn!(
+            "[Audit] Failed to record {:?} on server {}: {}",
+            entry.action,
+            entry.server_id,
+            err
+        );
+    }
+}
+
+/// Journal filtré par auteur et/ou action, paginé par curseur (`before`)
+/// Réservé aux membres qui gèrent le serveur
+pub async fn list_audit_log(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    requester_id: Uuid,
+    query: AuditLogQuery,
+) -> Result<Vec<AuditLogEntry>> {
+    require_permission(
+        server_repo,
+        server_id,
+        requester_id,
+        Permissions::MANAGE_SERVER,
+        Error::ServerForbidden,
+    )
+    .await?;
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let entries = server_repo
+        .list_audit_log(server_id, query.actor_id, query.action, query.before, limit)
+        .await?;
+    Ok(entries)
+}
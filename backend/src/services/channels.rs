@@ -1,8 +1,225 @@
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
 use crate::error::{Error, Result};
-use crate::models::{Channel, CreateChannelPayload, MemberRole, UpdateChannelPayload};
-use crate::repositories::{ChannelRepository, ServerRepository};
+use crate::models::{
+    AuditAction, Channel, ChannelFollower, ChannelKind, ChannelOverwrite, ChannelOverwritePayload,
+    ChannelPositionPayload, CreateChannelPayload, FollowChannelPayload, NewAuditLogEntry,
+    OverwriteTarget, Permissions, Role, UpdateChannelPayload,
+};
+use crate::repositories::{ChannelPosition, ChannelRepository, ServerRepository};
+use crate::services::audit;
+use crate::services::roles::{
+    compute_channel_permissions, compute_permissions, manageable_role, member_permissions,
+    require_permission,
+};
+
+/// Slowmode maximal (6 heures)
+const MAX_RATE_LIMIT_PER_USER: i32 = 21_600;
+
+/// Permissions pour publier : MANAGE_MESSAGES en plus dans un channel d'annonces
+pub fn send_permissions(channel: &Channel) -> Permissions {
+    match channel.kind {
+        ChannelKind::Announcement => Permissions::SEND_MESSAGES | Permissions::MANAGE_MESSAGES,
+        _ => Permissions::SEND_MESSAGES,
+    }
+}
+
+/// Les catégories et channels vocaux ne contiennent pas de messages
+pub fn ensure_accepts_messages(channel: &Channel) -> Result<()> {
+    if !channel.kind.accepts_messages() {
+        return Err(Error::BadRequest {
+            message: "This channel does not accept messages".to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn validate_rate_limit(rate_limit: i32) -> Result<()> {
+    if !(0..=MAX_RATE_LIMIT_PER_USER).contains(&rate_limit) {
+        return Err(Error::BadRequest {
+            message: format!(
+                "rate_limit_per_user must be between 0 and {} seconds",
+                MAX_RATE_LIMIT_PER_USER
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Overwrites par channel, pour tous les channels d'un serveur
+async fn overwrites_by_channel(
+    channel_repo: &ChannelRepository,
+    server_id: Uuid,
+) -> Result<HashMap<Uuid, Vec<ChannelOverwrite>>> {
+    let mut overwrites: HashMap<Uuid, Vec<ChannelOverwrite>> = HashMap::new();
+    for overwrite in channel_repo.list_overwrites_by_server(server_id).await? {
+        overwrites
+            .entry(overwrite.channel_id)
+            .or_default()
+            .push(overwrite);
+    }
+    Ok(overwrites)
+}
+
+/// Mêmes cibles avec les mêmes permissions, quel que soit le channel
+fn overwrites_match(a: &[ChannelOverwrite], b: &[ChannelOverwrite]) -> bool {
+    a.len() == b.len()
+        && a.iter().all(|x| {
+            b.iter().any(|y| {
+                x.target_type == y.target_type
+                    && x.target_id == y.target_id
+                    && x.allow == y.allow
+                    && x.deny == y.deny
+            })
+        })
+}
+
+/// Channels d'une catégorie dont les overwrites sont encore ceux de la catégorie
+async fn synced_children(
+    channel_repo: &ChannelRepository,
+    category: &Channel,
+) -> Result<Vec<Channel>> {
+    if category.kind != ChannelKind::Category {
+        return Ok(vec![]);
+    }
+
+    let mut overwrites = overwrites_by_channel(channel_repo, category.server_id).await?;
+    let category_overwrites = overwrites.remove(&category.id).unwrap_or_default();
+
+    let children = channel_repo.list_children(category.id).await?;
+    Ok(children
+        .into_iter()
+        .filter(|child| {
+            let child_overwrites = overwrites.get(&child.id).map(Vec::as_slice);
+            overwrites_match(&category_overwrites, child_overwrites.unwrap_or_default())
+        })
+        .collect())
+}
+
+/// Permissions d'un utilisateur dans un channel (NONE s'il n'est pas membre ou ne le voit pas)
+pub async fn channel_permissions(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    channel: &Channel,
+    user_id: Uuid,
+) -> Result<Permissions> {
+    let Some(member) = member_permissions(server_repo, channel.server_id, user_id).await? else {
+        return Ok(Permissions::NONE);
+    };
+
+    let overwrites = channel_repo.list_overwrites(channel.id).await?;
+    Ok(member.in_channel(&overwrites))
+}
+
+/// Exige une permission dans un channel chargé, `forbidden` sinon (non-membre compris)
+/// MemberTimedOut si seul le timeout du membre la lui retire
+pub async fn ensure_channel_permission(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    channel: &Channel,
+    user_id: Uuid,
+    permission: Permissions,
+    forbidden: Error,
+) -> Result<Permissions> {
+    let Some(member) = member_permissions(server_repo, channel.server_id, user_id).await? else {
+        return Err(forbidden);
+    };
+
+    let overwrites = channel_repo.list_overwrites(channel.id).await?;
+    let permissions = member.in_channel(&overwrites);
+    if permissions.contains(permission) {
+        return Ok(permissions);
+    }
+
+    let without_timeout = permissions | (permission & Permissions::COMMUNICATION);
+    if member.is_timed_out()
+        && permissions.contains(Permissions::VIEW_CHANNEL)
+        && without_timeout.contains(permission)
+    {
+        return Err(Error::MemberTimedOut);
+    }
+    Err(forbidden)
+}
+
+/// Exige une permission dans un channel, `forbidden` sinon (non-membre compris)
+pub async fn require_channel_permission(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    channel_id: Uuid,
+    user_id: Uuid,
+    permission: Permissions,
+    forbidden: Error,
+) -> Result<Channel> {
+    let channel = channel_repo
+        .find_by_id(channel_id)
+        .await?
+        .ok_or(Error::ChannelNotFound)?;
+
+    ensure_channel_permission(
+        server_repo,
+        channel_repo,
+        &channel,
+        user_id,
+        permission,
+        forbidden,
+    )
+    .await?;
+
+    Ok(channel)
+}
+
+/// Membres qui voient un channel, None si tous les membres du serveur le voient
+pub async fn channel_viewers(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    server_id: Uuid,
+    channel_id: Uuid,
+) -> Result<Option<HashSet<Uuid>>> {
+    let overwrites = channel_repo.list_overwrites(channel_id).await?;
+    let roles = server_repo.list_roles(server_id).await?;
+
+    let everyone_can_view = roles
+        .iter()
+        .any(|role| role.is_default && role.permissions.contains(Permissions::VIEW_CHANNEL));
+    if overwrites.is_empty() && everyone_can_view {
+        return Ok(None);
+    }
+
+    let server = server_repo
+        .find_by_id(server_id)
+        .await?
+        .ok_or(Error::ServerNotFound)?;
+    let members = server_repo.list_members(server_id).await?;
+    let assignments = server_repo.list_role_assignments(server_id).await?;
+
+    let roles_by_id: HashMap<Uuid, &Role> = roles.iter().map(|role| (role.id, role)).collect();
+    let mut member_roles: HashMap<Uuid, Vec<Role>> = HashMap::new();
+    for (user_id, role_id) in assignments {
+        if let Some(role) = roles_by_id.get(&role_id) {
+            member_roles
+                .entry(user_id)
+                .or_default()
+                .push((*role).clone());
+        }
+    }
+    let default_role = roles.iter().find(|role| role.is_default);
+
+    let viewers = members
+        .iter()
+        .filter(|member| {
+            let mut roles = member_roles.remove(&member.user_id).unwrap_or_default();
+            roles.extend(default_role.cloned());
+
+            let base = compute_permissions(&server, member, &roles);
+            compute_channel_permissions(base, member.user_id, &roles, &overwrites)
+                .contains(Permissions::VIEW_CHANNEL)
+        })
+        .map(|member| member.user_id)
+        .collect();
+
+    Ok(Some(viewers))
+}
 
 pub async fn create_channel(
     server_repo: &ServerRepository,
@@ -11,28 +228,99 @@ pub async fn create_channel(
     user_id: Uuid,
     payload: CreateChannelPayload,
 ) -> Result<Channel> {
-    let member = server_repo
-        .find_member(server_id, user_id)
-        .await?
-        .ok_or(Error::ChannelForbidden)?;
+    let requester = require_permission(
+        server_repo,
+        server_id,
+        user_id,
+        Permissions::MANAGE_CHANNELS,
+        Error::ChannelForbidden,
+    )
+    .await?;
+    validate_rate_limit(payload.rate_limit_per_user)?;
+    if payload.kind == ChannelKind::Thread {
+        return Err(Error::BadRequest {
+            message: "Threads are started from a message".to_string(),
+        });
+    }
 
-    if member.role == MemberRole::Member {
-        return Err(Error::ChannelForbidden);
+    // Le parent doit être une catégorie du serveur que le demandeur voit
+    if let Some(parent_id) = payload.parent_id {
+        let parent = channel_repo
+            .find_by_id(parent_id)
+            .await?
+            .filter(|parent| parent.server_id == server_id)
+            .ok_or(Error::ChannelNotFound)?;
+        if parent.kind != ChannelKind::Category || payload.kind == ChannelKind::Category {
+            return Err(Error::BadRequest {
+                message: "Only channels can be placed in a category".to_string(),
+            });
+        }
+        let overwrites = channel_repo.list_overwrites(parent_id).await?;
+        if !requester
+            .in_channel(&overwrites)
+            .contains(Permissions::VIEW_CHANNEL)
+        {
+            return Err(Error::ChannelForbidden);
+        }
     }
 
     let position = match payload.position {
         Some(p) => p,
         None => {
-            let max = channel_repo.get_max_position(server_id).await?;
+            let max = channel_repo
+                .get_max_position(server_id, payload.parent_id)
+                .await?;
             max.map(|m| m + 1).unwrap_or(0)
         }
     };
 
     let channel_id = Uuid::new_v4();
     let channel = channel_repo
-        .create(channel_id, server_id, &payload.name, position)
+        .create(
+            channel_id,
+            server_id,
+            &payload.name,
+            payload.kind,
+            payload.parent_id,
+            position,
+            payload.rate_limit_per_user,
+        )
         .await?;
 
+    // Un channel créé dans une catégorie démarre synchronisé avec elle
+    if let Some(parent_id) = payload.parent_id {
+        channel_repo.sync_overwrites(channel_id, parent_id).await?;
+    }
+
+    // Channel privé : masqué pour @everyone, visible par son créateur
+    if payload.private {
+        if let Some(everyone) = requester.roles.iter().find(|role| role.is_default) {
+            channel_repo
+                .upsert_overwrite(
+                    channel_id,
+                    OverwriteTarget::Role,
+                    everyone.id,
+                    Permissions::NONE,
+                    Permissions::VIEW_CHANNEL,
+                )
+                .await?;
+        }
+        channel_repo
+            .upsert_overwrite(
+                channel_id,
+                OverwriteTarget::Member,
+                user_id,
+                Permissions::VIEW_CHANNEL,
+                Permissions::NONE,
+            )
+            .await?;
+    }
+
+    let entry = NewAuditLogEntry::new(server_id, user_id, AuditAction::ChannelCreate)
+        .target(channel_id)
+        .after(&channel);
+    audit::record(server_repo, entry).await;
+
     Ok(channel)
 }
 
@@ -42,12 +330,24 @@ pub async fn list_channels(
     server_id: Uuid,
     user_id: Uuid,
 ) -> Result<Vec<Channel>> {
-    server_repo
-        .find_member(server_id, user_id)
+    let member = member_permissions(server_repo, server_id, user_id)
         .await?
         .ok_or(Error::ChannelForbidden)?;
 
-    let channels = channel_repo.list_by_server(server_id).await?;
+    let overwrites = overwrites_by_channel(channel_repo, server_id).await?;
+
+    // Les channels que le membre ne voit pas n'existent pas pour lui
+    let channels = channel_repo
+        .list_by_server(server_id)
+        .await?
+        .into_iter()
+        .filter(|channel| {
+            let channel_overwrites = overwrites.get(&channel.id).map(Vec::as_slice);
+            member
+                .in_channel(channel_overwrites.unwrap_or_default())
+                .contains(Permissions::VIEW_CHANNEL)
+        })
+        .collect();
     Ok(channels)
 }
 
@@ -57,17 +357,15 @@ pub async fn get_channel(
     channel_id: Uuid,
     user_id: Uuid,
 ) -> Result<Channel> {
-    let channel = channel_repo
-        .find_by_id(channel_id)
-        .await?
-        .ok_or(Error::ChannelNotFound)?;
-
-    server_repo
-        .find_member(channel.server_id, user_id)
-        .await?
-        .ok_or(Error::ChannelForbidden)?;
-
-    Ok(channel)
+    require_channel_permission(
+        server_repo,
+        channel_repo,
+        channel_id,
+        user_id,
+        Permissions::VIEW_CHANNEL,
+        Error::ChannelForbidden,
+    )
+    .await
 }
 
 pub async fn update_channel(
@@ -82,43 +380,561 @@ pub async fn update_channel(
         .await?
         .ok_or(Error::ChannelNotFound)?;
 
-    let member = server_repo
-        .find_member(existing.server_id, user_id)
-        .await?
-        .ok_or(Error::ChannelForbidden)?;
+    require_permission(
+        server_repo,
+        existing.server_id,
+        user_id,
+        Permissions::MANAGE_CHANNELS,
+        Error::ChannelForbidden,
+    )
+    .await?;
 
-    if member.role == MemberRole::Member {
-        return Err(Error::ChannelForbidden);
+    if let Some(rate_limit) = payload.rate_limit_per_user {
+        validate_rate_limit(rate_limit)?;
     }
 
     let channel = channel_repo
-        .update(channel_id, payload.name, payload.position)
+        .update(
+            channel_id,
+            payload.name,
+            payload.position,
+            payload.rate_limit_per_user,
+        )
         .await?
         .ok_or(Error::ChannelNotFound)?;
 
+    let entry = NewAuditLogEntry::new(channel.server_id, user_id, AuditAction::ChannelUpdate)
+        .target(channel_id)
+        .before(&existing)
+        .after(&channel);
+    audit::record(server_repo, entry).await;
+
     Ok(channel)
 }
 
+/// Valide un lot de déplacements contre les channels du serveur
+fn resolve_positions(
+    channels: &[Channel],
+    changes: &[ChannelPositionPayload],
+) -> Result<Vec<ChannelPosition>> {
+    let bad_request = |message: &str| Error::BadRequest {
+        message: message.to_string(),
+    };
+
+    if changes.is_empty() {
+        return Err(bad_request("No channel to move"));
+    }
+
+    let by_id: HashMap<Uuid, &Channel> = channels.iter().map(|c| (c.id, c)).collect();
+    let mut seen = HashSet::new();
+
+    changes
+        .iter()
+        .map(|change| {
+            let channel = by_id.get(&change.id).ok_or(Error::ChannelNotFound)?;
+            if !seen.insert(change.id) {
+                return Err(bad_request("A channel can only be moved once per request"));
+            }
+            if change.position < 0 {
+                return Err(bad_request("position must be positive"));
+            }
+
+            let parent_id = change.parent_id.unwrap_or(channel.parent_id);
+            if let Some(parent_id) = parent_id {
+                let parent = by_id.get(&parent_id).ok_or(Error::ChannelNotFound)?;
+                if parent.kind != ChannelKind::Category || channel.kind == ChannelKind::Category {
+                    return Err(bad_request("Only channels can be placed in a category"));
+                }
+            } else if change.lock_permissions {
+                return Err(bad_request("lock_permissions requires a category"));
+            }
+
+            Ok(ChannelPosition {
+                channel_id: change.id,
+                parent_id,
+                position: change.position,
+                sync_with: parent_id.filter(|_| change.lock_permissions),
+            })
+        })
+        .collect()
+}
+
+/// Déplace un lot de channels (position, catégorie) en une transaction
+/// Retourne les channels dont la position, le parent ou les overwrites ont changé
+pub async fn update_channel_positions(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    server_id: Uuid,
+    user_id: Uuid,
+    changes: Vec<ChannelPositionPayload>,
+) -> Result<Vec<Channel>> {
+    let requester = require_permission(
+        server_repo,
+        server_id,
+        user_id,
+        Permissions::MANAGE_CHANNELS,
+        Error::ChannelForbidden,
+    )
+    .await?;
+
+    let channels = channel_repo.list_by_server(server_id).await?;
+    let positions = resolve_positions(&channels, &changes)?;
+
+    // Le demandeur ne déplace que ce qu'il voit, vers une catégorie qu'il voit
+    let overwrites = overwrites_by_channel(channel_repo, server_id).await?;
+    let can_view = |channel_id: Uuid| {
+        let channel_overwrites = overwrites.get(&channel_id).map(Vec::as_slice);
+        requester
+            .in_channel(channel_overwrites.unwrap_or_default())
+            .contains(Permissions::VIEW_CHANNEL)
+    };
+    let visible = positions
+        .iter()
+        .all(|position| can_view(position.channel_id) && position.parent_id.is_none_or(can_view));
+    if !visible {
+        return Err(Error::ChannelForbidden);
+    }
+
+    let updated = channel_repo.update_positions(server_id, &positions).await?;
+
+    let before: HashMap<Uuid, &Channel> = channels.iter().map(|c| (c.id, c)).collect();
+    let synced: HashSet<Uuid> = positions
+        .iter()
+        .filter(|position| position.sync_with.is_some())
+        .map(|position| position.channel_id)
+        .collect();
+    let moved: HashSet<Uuid> = positions.iter().map(|p| p.channel_id).collect();
+
+    let changed: Vec<Channel> = updated
+        .into_iter()
+        .filter(|channel| {
+            synced.contains(&channel.id)
+                || before.get(&channel.id).is_none_or(|previous| {
+                    previous.position != channel.position || previous.parent_id != channel.parent_id
+                })
+        })
+        .collect();
+
+    // Journaliser les channels déplacés, pas ceux simplement renumérotés
+    for channel in changed.iter().filter(|channel| moved.contains(&channel.id)) {
+        let mut entry = NewAuditLogEntry::new(server_id, user_id, AuditAction::ChannelUpdate)
+            .target(channel.id)
+            .after(channel);
+        if let Some(previous) = before.get(&channel.id) {
+            entry = entry.before(previous);
+        }
+        audit::record(server_repo, entry).await;
+    }
+
+    Ok(changed)
+}
+
+/// Supprime un channel, retourne le channel supprimé
 pub async fn delete_channel(
     server_repo: &ServerRepository,
     channel_repo: &ChannelRepository,
     channel_id: Uuid,
     user_id: Uuid,
-) -> Result<()> {
+) -> Result<Channel> {
     let existing = channel_repo
         .find_by_id(channel_id)
         .await?
         .ok_or(Error::ChannelNotFound)?;
 
-    let member = server_repo
-        .find_member(existing.server_id, user_id)
+    require_permission(
+        server_repo,
+        existing.server_id,
+        user_id,
+        Permissions::MANAGE_CHANNELS,
+        Error::ChannelForbidden,
+    )
+    .await?;
+
+    channel_repo.delete(channel_id).await?;
+
+    let entry = NewAuditLogEntry::new(existing.server_id, user_id, AuditAction::ChannelDelete)
+        .target(channel_id)
+        .before(&existing);
+    audit::record(server_repo, entry).await;
+
+    Ok(existing)
+}
+
+/// Abonne un channel texte au channel d'annonces `channel_id`
+/// Le demandeur doit voir les deux channels et gérer ceux du serveur abonné
+pub async fn follow_channel(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    channel_id: Uuid,
+    user_id: Uuid,
+    payload: FollowChannelPayload,
+) -> Result<ChannelFollower> {
+    let source = get_channel(server_repo, channel_repo, channel_id, user_id).await?;
+    if source.kind != ChannelKind::Announcement {
+        return Err(Error::BadRequest {
+            message: "Only announcement channels can be followed".to_string(),
+        });
+    }
+
+    let target = get_channel(
+        server_repo,
+        channel_repo,
+        payload.target_channel_id,
+        user_id,
+    )
+    .await?;
+    require_permission(
+        server_repo,
+        target.server_id,
+        user_id,
+        Permissions::MANAGE_CHANNELS,
+        Error::ChannelForbidden,
+    )
+    .await?;
+    if target.kind != ChannelKind::Text {
+        return Err(Error::BadRequest {
+            message: "Announcements can only be followed from a text channel".to_string(),
+        });
+    }
+
+    let follower = channel_repo
+        .add_follower(source.id, target.id, user_id)
+        .await?;
+
+    let entry = NewAuditLogEntry::new(target.server_id, user_id, AuditAction::ChannelFollow)
+        .target(target.id)
+        .after(&follower);
+    audit::record(server_repo, entry).await;
+
+    Ok(follower)
+}
+
+/// Désabonne un channel, par un gestionnaire de l'un ou l'autre serveur
+pub async fn unfollow_channel(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    channel_id: Uuid,
+    target_channel_id: Uuid,
+    user_id: Uuid,
+) -> Result<()> {
+    let follower = channel_repo
+        .list_followers(channel_id)
         .await?
-        .ok_or(Error::ChannelForbidden)?;
+        .into_iter()
+        .find(|follower| follower.target_channel_id == target_channel_id)
+        .ok_or(Error::ChannelNotFound)?;
 
-    if member.role == MemberRole::Member {
+    let mut allowed = false;
+    for id in [channel_id, target_channel_id] {
+        let Some(channel) = channel_repo.find_by_id(id).await? else {
+            continue;
+        };
+        let requester = member_permissions(server_repo, channel.server_id, user_id).await?;
+        if requester.is_some_and(|requester| requester.has(Permissions::MANAGE_CHANNELS)) {
+            allowed = true;
+            break;
+        }
+    }
+    if !allowed {
         return Err(Error::ChannelForbidden);
     }
 
-    channel_repo.delete(channel_id).await?;
+    channel_repo
+        .remove_follower(channel_id, target_channel_id)
+        .await?;
+
+    if let Some(target) = channel_repo.find_by_id(target_channel_id).await? {
+        let entry = NewAuditLogEntry::new(target.server_id, user_id, AuditAction::ChannelUnfollow)
+            .target(target_channel_id)
+            .before(&follower);
+        audit::record(server_repo, entry).await;
+    }
+
     Ok(())
 }
+
+/// Channels abonnés à un channel d'annonces (gestionnaires de son serveur)
+pub async fn list_followers(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    channel_id: Uuid,
+    user_id: Uuid,
+) -> Result<Vec<ChannelFollower>> {
+    let channel = get_channel(server_repo, channel_repo, channel_id, user_id).await?;
+    require_permission(
+        server_repo,
+        channel.server_id,
+        user_id,
+        Permissions::MANAGE_CHANNELS,
+        Error::ChannelForbidden,
+    )
+    .await?;
+
+    let followers = channel_repo.list_followers(channel_id).await?;
+    Ok(followers)
+}
+
+pub async fn list_overwrites(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    channel_id: Uuid,
+    user_id: Uuid,
+) -> Result<Vec<ChannelOverwrite>> {
+    get_channel(server_repo, channel_repo, channel_id, user_id).await?;
+
+    let overwrites = channel_repo.list_overwrites(channel_id).await?;
+    Ok(overwrites)
+}
+
+/// Vérifie que le demandeur peut gérer l'overwrite de cette cible dans ce channel
+/// Retourne le channel, les permissions du demandeur dedans et l'overwrite actuel de la cible
+async fn manageable_overwrite_target(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    channel_id: Uuid,
+    user_id: Uuid,
+    target_type: OverwriteTarget,
+    target_id: Uuid,
+) -> Result<(Channel, Permissions, Option<ChannelOverwrite>)> {
+    let channel = channel_repo
+        .find_by_id(channel_id)
+        .await?
+        .ok_or(Error::ChannelNotFound)?;
+
+    let requester = require_permission(
+        server_repo,
+        channel.server_id,
+        user_id,
+        Permissions::MANAGE_CHANNELS,
+        Error::ChannelForbidden,
+    )
+    .await?;
+
+    let overwrites = channel_repo.list_overwrites(channel_id).await?;
+    let permissions = requester.in_channel(&overwrites);
+    if !permissions.contains(Permissions::VIEW_CHANNEL) {
+        return Err(Error::ChannelForbidden);
+    }
+
+    if target_type == OverwriteTarget::Role {
+        manageable_role(server_repo, &requester, target_id).await?;
+    }
+
+    let current = overwrites
+        .into_iter()
+        .find(|ow| ow.target_type == target_type && ow.target_id == target_id);
+    Ok((channel, permissions, current))
+}
+
+/// Crée ou remplace l'overwrite d'un rôle ou d'un membre sur un channel
+/// Le demandeur ne peut accorder ou retirer que des permissions qu'il possède dans ce channel
+/// Sur une catégorie, les channels encore synchronisés avec elle suivent
+pub async fn set_overwrite(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    channel_id: Uuid,
+    user_id: Uuid,
+    target_type: OverwriteTarget,
+    target_id: Uuid,
+    payload: ChannelOverwritePayload,
+) -> Result<(Channel, ChannelOverwrite)> {
+    let (channel, permissions, current) = manageable_overwrite_target(
+        server_repo,
+        channel_repo,
+        channel_id,
+        user_id,
+        target_type,
+        target_id,
+    )
+    .await?;
+
+    if channel.kind == ChannelKind::Thread {
+        return Err(Error::BadRequest {
+            message: "Threads inherit the permissions of their channel".to_string(),
+        });
+    }
+
+    if target_type == OverwriteTarget::Member {
+        server_repo
+            .find_member(channel.server_id, target_id)
+            .await?
+            .ok_or(Error::UserNotFound)?;
+    }
+
+    let validate = |bits: i64| -> Result<Permissions> {
+        let value = Permissions::from_bits(bits)
+            .filter(|value| Permissions::CHANNEL_OVERWRITABLE.contains(*value))
+            .ok_or_else(|| Error::BadRequest {
+                message: "Permission bits cannot be overwritten per channel".to_string(),
+            })?;
+        if !permissions.contains(value) {
+            return Err(Error::ChannelForbidden);
+        }
+        Ok(value)
+    };
+    let allow = validate(payload.allow)?;
+    let deny = validate(payload.deny)?;
+
+    if allow.bits() & deny.bits() != 0 {
+        return Err(Error::BadRequest {
+            message: "A permission cannot be both allowed and denied".to_string(),
+        });
+    }
+
+    let children = synced_children(channel_repo, &channel).await?;
+    let overwrite = channel_repo
+        .upsert_overwrite(channel_id, target_type, target_id, allow, deny)
+        .await?;
+    for child in children {
+        channel_repo.sync_overwrites(child.id, channel_id).await?;
+    }
+
+    let mut entry = NewAuditLogEntry::new(
+        channel.server_id,
+        user_id,
+        AuditAction::ChannelOverwriteUpdate,
+    )
+    .target(channel_id)
+    .after(&overwrite);
+    if let Some(current) = &current {
+        entry = entry.before(current);
+    }
+    audit::record(server_repo, entry).await;
+
+    Ok((channel, overwrite))
+}
+
+/// Supprime un overwrite (propagé aux channels synchronisés), retourne le channel concerné
+pub async fn delete_overwrite(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    channel_id: Uuid,
+    user_id: Uuid,
+    target_type: OverwriteTarget,
+    target_id: Uuid,
+) -> Result<Channel> {
+    let (channel, _, current) = manageable_overwrite_target(
+        server_repo,
+        channel_repo,
+        channel_id,
+        user_id,
+        target_type,
+        target_id,
+    )
+    .await?;
+
+    let current = current.ok_or(Error::OverwriteNotFound)?;
+    let children = synced_children(channel_repo, &channel).await?;
+    if !channel_repo
+        .delete_overwrite(channel_id, target_type, target_id)
+        .await?
+    {
+        return Err(Error::OverwriteNotFound);
+    }
+    for child in children {
+        channel_repo.sync_overwrites(child.id, channel_id).await?;
+    }
+
+    let entry = NewAuditLogEntry::new(
+        channel.server_id,
+        user_id,
+        AuditAction::ChannelOverwriteDelete,
+    )
+    .target(channel_id)
+    .before(&current);
+    audit::record(server_repo, entry).await;
+
+    Ok(channel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn channel(kind: ChannelKind, parent_id: Option<Uuid>, position: i32) -> Channel {
+        Channel {
+            id: Uuid::new_v4(),
+            server_id: Uuid::nil(),
+            name: "channel".to_string(),
+            kind,
+            parent_id,
+            position,
+            rate_limit_per_user: 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn change(id: Uuid, position: i32, parent_id: Option<Option<Uuid>>) -> ChannelPositionPayload {
+        ChannelPositionPayload {
+            id,
+            position,
+            parent_id,
+            lock_permissions: false,
+        }
+    }
+
+    #[test]
+    fn positions_keep_or_change_parent() {
+        let category = channel(ChannelKind::Category, None, 0);
+        let inside = channel(ChannelKind::Text, Some(category.id), 0);
+        let outside = channel(ChannelKind::Text, None, 1);
+        let channels = [category.clone(), inside.clone(), outside.clone()];
+
+        let positions = resolve_positions(
+            &channels,
+            &[
+                change(inside.id, 2, None),
+                change(outside.id, 0, Some(Some(category.id))),
+            ],
+        )
+        .unwrap();
+        assert_eq!(positions[0].parent_id, Some(category.id));
+        assert_eq!(positions[1].parent_id, Some(category.id));
+
+        let mut lock = change(inside.id, 0, Some(None));
+        let positions = resolve_positions(&channels, &[lock]).unwrap();
+        assert_eq!(positions[0].parent_id, None);
+
+        // Synchroniser suppose une catégorie
+        lock = change(inside.id, 0, None);
+        lock.lock_permissions = true;
+        let positions = resolve_positions(&channels, &[lock]).unwrap();
+        assert_eq!(positions[0].sync_with, Some(category.id));
+    }
+
+    #[test]
+    fn positions_reject_invalid_moves() {
+        let category = channel(ChannelKind::Category, None, 0);
+        let other_category = channel(ChannelKind::Category, None, 1);
+        let text = channel(ChannelKind::Text, None, 2);
+        let channels = [category.clone(), other_category.clone(), text.clone()];
+
+        // Pas de catégorie dans une catégorie, ni de channel dans un channel texte
+        for invalid in [
+            change(other_category.id, 0, Some(Some(category.id))),
+            change(category.id, 0, Some(Some(text.id))),
+        ] {
+            assert!(matches!(
+                resolve_positions(&channels, &[invalid]),
+                Err(Error::BadRequest { .. })
+            ));
+        }
+
+        assert!(matches!(
+            resolve_positions(&channels, &[change(Uuid::new_v4(), 0, None)]),
+            Err(Error::ChannelNotFound)
+        ));
+        assert!(resolve_positions(&channels, &[change(text.id, -1, None)]).is_err());
+        assert!(resolve_positions(
+            &channels,
+            &[change(text.id, 0, None), change(text.id, 1, None)]
+        )
+        .is_err());
+
+        let mut lock = change(text.id, 0, None);
+        lock.lock_permissions = true;
+        assert!(resolve_positions(&channels, &[lock]).is_err());
+    }
+}
@@ -0,0 +1,337 @@
+//! Logique métier des messages privés (partagée entre REST et WebSocket)
+
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::{
+    CreateDMMessagePayload, DirectMessageItem, DirectMessageItemResponse, MessagePreview,
+    MessageReactionPublic,
+};
+use crate::repositories::message::is_duplicate_key_error;
+use crate::repositories::{DirectMessageRepository, DmRepository, UserRepository};
+use crate::services::messages::{
+    deleted_preview, ensure_pin_capacity, is_within_nonce_window, normalize_nonce, truncate_preview,
+};
+
+pub fn to_public_reactions(
+    reactions: Vec<crate::models::MessageReaction>,
+) -> Vec<MessageReactionPublic> {
+    reactions
+        .into_iter()
+        .map(MessageReactionPublic::from)
+        .collect()
+}
+
+pub fn to_response(message: DirectMessageItem, username: String) -> DirectMessageItemResponse {
+    DirectMessageItemResponse {
+        id: message.message_id,
+        dm_id: message.dm_id,
+        author_id: message.author_id,
+        username,
+        content: message.content,
+        created_at: message.created_at,
+        edited_at: message.edited_at,
+        reactions: to_public_reactions(message.reactions),
+        nonce: message.nonce,
+        reply_to: None,
+        pinned_at: message.pinned_at,
+        pinned_by: message.pinned_by,
+    }
+}
+
+/// Aperçu d'un message privé cité en réponse (`referenced` absent ou supprimé = marqueur)
+pub fn reply_preview(
+    message_id: Uuid,
+    referenced: Option<&DirectMessageItem>,
+    username: Option<String>,
+) -> MessagePreview {
+    match referenced {
+        Some(referenced) if referenced.deleted_at.is_none() => MessagePreview {
+            id: message_id,
+            deleted: false,
+            author_id: Some(referenced.author_id),
+            username,
+            nickname: None,
+            content: Some(truncate_preview(&referenced.content)),
+        },
+        _ => deleted_preview(message_id),
+    }
+}
+
+/// Messages cités en réponse par `messages`, récupérés en une requête (supprimés compris)
+async fn find_referenced(
+    dm_message_repo: &DirectMessageRepository,
+    messages: &[DirectMessageItem],
+) -> Result<HashMap<Uuid, DirectMessageItem>> {
+    let reply_ids: Vec<Uuid> = messages.iter().filter_map(|m| m.reply_to).collect();
+    if reply_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let referenced =
+        dm_message_repo
+            .find_by_ids(&reply_ids)
+            .await
+            .map_err(|e| Error::DatabaseError {
+                message: format!("MongoDB query failed: {}", e),
+            })?;
+
+    Ok(referenced
+        .into_iter()
+        .map(|referenced| (referenced.message_id, referenced))
+        .collect())
+}
+
+/// Réponses d'une liste de messages privés, avec auteurs et aperçus des messages cités
+pub async fn with_authors(
+    dm_message_repo: &DirectMessageRepository,
+    user_repo: &UserRepository,
+    messages: Vec<DirectMessageItem>,
+) -> Result<Vec<DirectMessageItemResponse>> {
+    if messages.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let referenced = find_referenced(dm_message_repo, &messages).await?;
+
+    let author_ids: Vec<Uuid> = messages
+        .iter()
+        .map(|message| message.author_id)
+        .chain(referenced.values().map(|referenced| referenced.author_id))
+        .collect();
+    let usernames = user_repo.get_usernames_batch(&author_ids).await?;
+
+    Ok(messages
+        .into_iter()
+        .map(|message| {
+            let username = usernames
+                .get(&message.author_id)
+                .cloned()
+                .unwrap_or_else(|| "Unknown".to_string());
+            let reply_to = message.reply_to.map(|reply_to| {
+                let referenced = referenced
+                    .get(&reply_to)
+                    .filter(|referenced| referenced.dm_id == message.dm_id);
+                let username = referenced
+                    .and_then(|referenced| usernames.get(&referenced.author_id))
+                    .cloned();
+                reply_preview(reply_to, referenced, username)
+            });
+            DirectMessageItemResponse {
+                reply_to,
+                ..to_response(message, username)
+            }
+        })
+        .collect())
+}
+
+/// Messages épinglés d'une conversation, les derniers épinglés d'abord
+pub async fn list_pins(
+    dm_repo: &DmRepository,
+    dm_message_repo: &DirectMessageRepository,
+    user_repo: &UserRepository,
+    dm_id: Uuid,
+    user_id: Uuid,
+) -> Result<Vec<DirectMessageItemResponse>> {
+    if !dm_repo.user_has_access(dm_id, user_id).await? {
+        return Err(Error::MessageForbidden);
+    }
+
+    let messages = dm_message_repo
+        .list_pins(dm_id)
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB query failed: {}", e),
+        })?;
+
+    with_authors(dm_message_repo, user_repo, messages).await
+}
+
+/// Épingle ou désépingle un message privé (l'un ou l'autre participant)
+/// Retourne le message à jour, et `false` s'il était déjà dans cet état
+pub async fn set_pinned(
+    dm_repo: &DmRepository,
+    dm_message_repo: &DirectMessageRepository,
+    dm_id: Uuid,
+    message_id: Uuid,
+    user_id: Uuid,
+    pinned: bool,
+) -> Result<(DirectMessageItem, bool)> {
+    if !dm_repo.user_has_access(dm_id, user_id).await? {
+        return Err(Error::MessageForbidden);
+    }
+
+    let find = || async {
+        dm_message_repo
+            .find_by_id(message_id)
+            .await
+            .map_err(|e| Error::DatabaseError {
+                message: format!("MongoDB query failed: {}", e),
+            })?
+            .filter(|message| message.dm_id == dm_id && message.deleted_at.is_none())
+            .ok_or(Error::MessageNotFound)
+    };
+    let message = find().await?;
+    if message.pinned_at.is_some() == pinned {
+        return Ok((message, false));
+    }
+
+    if pinned {
+        let count = dm_message_repo
+            .count_pins(dm_id)
+            .await
+            .map_err(|e| Error::DatabaseError {
+                message: format!("MongoDB query failed: {}", e),
+            })?;
+        ensure_pin_capacity(count)?;
+    }
+
+    let changed = dm_message_repo
+        .set_pinned(message_id, pinned.then_some(user_id))
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB update failed: {}", e),
+        })?;
+
+    Ok((find().await?, changed))
+}
+
+/// Vérifie que le message cité existe dans la même conversation et construit son aperçu
+async fn find_reply_preview(
+    dm_message_repo: &DirectMessageRepository,
+    user_repo: &UserRepository,
+    dm_id: Uuid,
+    reply_to: Uuid,
+) -> Result<MessagePreview> {
+    let referenced = dm_message_repo
+        .find_by_id(reply_to)
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB query failed: {}", e),
+        })?
+        .filter(|referenced| referenced.dm_id == dm_id && referenced.deleted_at.is_none())
+        .ok_or_else(|| Error::BadRequest {
+            message: "Referenced message not found".to_string(),
+        })?;
+
+    let username = user_repo.get_username(referenced.author_id).await?;
+    Ok(reply_preview(reply_to, Some(&referenced), username))
+}
+
+/// Message privé déjà créé avec ce nonce, s'il est encore dans la fenêtre de déduplication
+async fn find_by_nonce(
+    dm_message_repo: &DirectMessageRepository,
+    dm_id: Uuid,
+    user_id: Uuid,
+    nonce: &str,
+) -> Result<Option<DirectMessageItem>> {
+    let Some(existing) = dm_message_repo
+        .find_by_author_nonce(user_id, nonce)
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB query failed: {}", e),
+        })?
+    else {
+        return Ok(None);
+    };
+
+    if !is_within_nonce_window(existing.created_at, chrono::Utc::now()) {
+        dm_message_repo
+            .clear_nonce(existing.message_id)
+            .await
+            .map_err(|e| Error::DatabaseError {
+                message: format!("MongoDB update failed: {}", e),
+            })?;
+        return Ok(None);
+    }
+
+    if existing.dm_id != dm_id {
+        return Err(Error::BadRequest {
+            message: "Nonce already used in another conversation".to_string(),
+        });
+    }
+
+    Ok(Some(existing))
+}
+
+/// Crée un message privé
+/// Retourne `false` en second si un envoi précédent avec le même nonce a été renvoyé
+pub async fn create_message(
+    dm_repo: &DmRepository,
+    dm_message_repo: &DirectMessageRepository,
+    user_repo: &UserRepository,
+    dm_id: Uuid,
+    user_id: Uuid,
+    payload: CreateDMMessagePayload,
+) -> Result<(DirectMessageItemResponse, bool)> {
+    let content = payload.content.trim();
+    if content.is_empty() {
+        return Err(Error::BadRequest {
+            message: "Message content cannot be empty".to_string(),
+        });
+    }
+
+    if !dm_repo.user_has_access(dm_id, user_id).await? {
+        return Err(Error::MessageForbidden);
+    }
+
+    let username = user_repo
+        .get_username(user_id)
+        .await?
+        .ok_or(Error::UserNotFound)?;
+
+    let preview = match payload.reply_to {
+        Some(reply_to) => {
+            Some(find_reply_preview(dm_message_repo, user_repo, dm_id, reply_to).await?)
+        }
+        None => None,
+    };
+    let respond = |message: DirectMessageItem| {
+        let reply_to = preview
+            .clone()
+            .filter(|preview| message.reply_to == Some(preview.id));
+        DirectMessageItemResponse {
+            reply_to,
+            ..to_response(message, username.clone())
+        }
+    };
+
+    let nonce = normalize_nonce(payload.nonce)?;
+    if let Some(nonce) = nonce.as_deref() {
+        if let Some(existing) = find_by_nonce(dm_message_repo, dm_id, user_id, nonce).await? {
+            return Ok((respond(existing), false));
+        }
+    }
+
+    let message = DirectMessageItem {
+        id: None,
+        message_id: Uuid::new_v4(),
+        dm_id,
+        author_id: user_id,
+        content: content.to_string(),
+        created_at: chrono::Utc::now(),
+        edited_at: None,
+        deleted_at: None,
+        reactions: vec![],
+        nonce,
+        reply_to: payload.reply_to,
+        pinned_at: None,
+        pinned_by: None,
+    };
+
+    if let Err(e) = dm_message_repo.create(&message).await {
+        // Envoi concurrent avec le même nonce : l'index unique a gardé le premier
+        if let (true, Some(nonce)) = (is_duplicate_key_error(&e), message.nonce.as_deref()) {
+            if let Some(existing) = find_by_nonce(dm_message_repo, dm_id, user_id, nonce).await? {
+                return Ok((respond(existing), false));
+            }
+        }
+
+        return Err(Error::DatabaseError {
+            message: format!("MongoDB insert failed: {}", e),
+        });
+    }
+
+    Ok((respond(message), true))
+}
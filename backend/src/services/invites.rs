@@ -2,8 +2,13 @@ use rand::Rng;
 use uuid::Uuid;
 
 use crate::error::{Error, Result};
-use crate::models::{CreateInvitePayload, Invite, JoinServerWithCodePayload, MemberRole};
+use crate::models::{
+    AuditAction, CreateInvitePayload, Invite, JoinServerWithCodePayload, MemberRole,
+    NewAuditLogEntry, Permissions,
+};
 use crate::repositories::{InviteRepository, ServerRepository};
+use crate::services::audit;
+use crate::services::roles::require_permission;
 
 /// Génère un code d'invitation unique (8 caractères alphanumériques)
 fn generate_invite_code() -> String {
@@ -24,21 +29,15 @@ pub async fn create_invite(
     payload: CreateInvitePayload,
     created_by: Uuid,
 ) -> Result<Invite> {
-    // Vérifier que le serveur existe
-    server_repo
-        .find_by_id(server_id)
-        .await?
-        .ok_or(Error::ServerNotFound)?;
-
-    // Vérifier que l'utilisateur est Admin ou Owner
-    let member = server_repo
-        .find_member(server_id, created_by)
-        .await?
-        .ok_or(Error::ServerForbidden)?;
-
-    if member.role == MemberRole::Member {
-        return Err(Error::ServerForbidden);
-    }
+    // Vérifier que le serveur existe et que l'utilisateur peut inviter
+    require_permission(
+        server_repo,
+        server_id,
+        created_by,
+        Permissions::CREATE_INVITE,
+        Error::ServerForbidden,
+    )
+    .await?;
 
     // Générer un code unique
     let mut code = generate_invite_code();
@@ -63,30 +62,68 @@ pub async fn create_invite(
         )
         .await?;
 
+    let entry = NewAuditLogEntry::new(server_id, created_by, AuditAction::InviteCreate)
+        .target(invite.id)
+        .after(&invite);
+    audit::record(server_repo, entry).await;
+
     Ok(invite)
 }
 
-pub async fn list_invites(
+/// Révoque une invitation : son créateur, ou un membre qui gère le serveur
+pub async fn revoke_invite(
     invite_repo: &InviteRepository,
     server_repo: &ServerRepository,
-    server_id: Uuid,
+    code: &str,
     requester_id: Uuid,
-) -> Result<Vec<Invite>> {
-    // Vérifier que le serveur existe
-    server_repo
-        .find_by_id(server_id)
+) -> Result<Invite> {
+    let invite = invite_repo
+        .find_by_code(code)
         .await?
         .ok_or(Error::ServerNotFound)?;
 
-    // Vérifier que l'utilisateur est Admin ou Owner
-    let member = server_repo
-        .find_member(server_id, requester_id)
-        .await?
-        .ok_or(Error::ServerForbidden)?;
+    if invite.created_by != requester_id {
+        require_permission(
+            server_repo,
+            invite.server_id,
+            requester_id,
+            Permissions::MANAGE_SERVER,
+            Error::ServerForbidden,
+        )
+        .await?;
+    }
 
-    if member.role == MemberRole::Member {
-        return Err(Error::ServerForbidden);
+    if invite.revoked {
+        return Ok(invite);
     }
+    invite_repo.revoke(invite.id).await?;
+
+    let entry = NewAuditLogEntry::new(invite.server_id, requester_id, AuditAction::InviteRevoke)
+        .target(invite.id)
+        .before(&invite);
+    audit::record(server_repo, entry).await;
+
+    Ok(Invite {
+        revoked: true,
+        ..invite
+    })
+}
+
+pub async fn list_invites(
+    invite_repo: &InviteRepository,
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    requester_id: Uuid,
+) -> Result<Vec<Invite>> {
+    // Vérifier que le serveur existe et que l'utilisateur le gère
+    require_permission(
+        server_repo,
+        server_id,
+        requester_id,
+        Permissions::MANAGE_SERVER,
+        Error::ServerForbidden,
+    )
+    .await?;
 
     let invites = invite_repo.list_by_server(server_id).await?;
     Ok(invites)
@@ -112,6 +149,10 @@ pub async fn join_server_with_code(
         .await?
         .ok_or(Error::ServerNotFound)?; // Utiliser ServerNotFound pour ne pas révéler que le code est invalide
 
+    if invite.revoked {
+        return Err(Error::ServerNotFound);
+    }
+
     // Vérifier l'expiration
     if let Some(expires_at) = invite.expires_at {
         if chrono::Utc::now() > expires_at {
@@ -1,13 +1,201 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::error::{Error, Result};
 use crate::models::{
-    ChannelMessage, CreateMessagePayload, MessageReactionPayload, MessageReactionPublic,
-    MessageWithUser, UpdateMessagePayload,
+    AuditAction, BulkDeleteMessagesPayload, Channel, ChannelKind, ChannelMessage,
+    CreateMessagePayload, MemberProfile, MessagePreview, MessageReactionPayload,
+    MessageReactionPublic, MessageWithUser, NewAuditLogEntry, Permissions, UpdateMessagePayload,
 };
-use crate::repositories::{ChannelRepository, MessageRepository, ServerRepository, UserRepository};
-use crate::services::{channels, servers};
+use crate::repositories::message::is_duplicate_key_error;
+use crate::repositories::{ChannelRepository, MessageRepository, ServerRepository};
+use crate::services::{audit, channels, servers};
+
+/// Fenêtre pendant laquelle un nonce client déduplique les envois rejoués
+const NONCE_WINDOW_SECS: i64 = 300;
+const MAX_NONCE_LENGTH: usize = 64;
+/// Nombre maximum de messages d'une suppression groupée
+const MAX_BULK_DELETE: usize = 100;
+/// Longueur du contenu repris dans l'aperçu d'un message cité en réponse
+const MAX_REPLY_PREVIEW_CHARS: usize = 100;
+/// Nombre maximum de messages épinglés par channel ou conversation
+const MAX_PINS: u64 = 50;
+/// Nombre maximum de membres (et de rôles) mentionnés retenus par message
+const MAX_MENTIONS: usize = 50;
+
+/// Mentions trouvées dans le contenu d'un message
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Mentions {
+    pub users: Vec<Uuid>,
+    pub roles: Vec<Uuid>,
+    /// `@everyone` ou `@here`
+    pub everyone: bool,
+}
+
+/// Messages visés par une suppression groupée
+#[derive(Debug, PartialEq, Eq)]
+pub enum BulkDeleteTarget {
+    Messages(Vec<Uuid>),
+    LastFromAuthor { author_id: Uuid, limit: i64 },
+}
+
+/// Exactement un mode : des ids (dédupliqués), ou un auteur avec un nombre de messages
+pub fn bulk_delete_target(payload: BulkDeleteMessagesPayload) -> Result<BulkDeleteTarget> {
+    let too_many = || Error::BadRequest {
+        message: format!("Cannot delete more than {} messages", MAX_BULK_DELETE),
+    };
+
+    match (payload.message_ids.is_empty(), payload.author_id) {
+        (false, None) => {
+            let mut message_ids = payload.message_ids;
+            let mut seen = std::collections::HashSet::new();
+            message_ids.retain(|id| seen.insert(*id));
+            if message_ids.len() > MAX_BULK_DELETE {
+                return Err(too_many());
+            }
+            Ok(BulkDeleteTarget::Messages(message_ids))
+        }
+        (true, Some(author_id)) => {
+            let limit = payload.limit.ok_or_else(|| Error::BadRequest {
+                message: "limit is required with author_id".to_string(),
+            })?;
+            if limit < 1 {
+                return Err(Error::BadRequest {
+                    message: "limit must be at least 1".to_string(),
+                });
+            }
+            if limit > MAX_BULK_DELETE as i64 {
+                return Err(too_many());
+            }
+            Ok(BulkDeleteTarget::LastFromAuthor { author_id, limit })
+        }
+        _ => Err(Error::BadRequest {
+            message: "Provide either message_ids or author_id".to_string(),
+        }),
+    }
+}
+
+/// Nettoie le nonce client (vide = absent) et vérifie sa longueur
+pub fn normalize_nonce(nonce: Option<String>) -> Result<Option<String>> {
+    let Some(nonce) = nonce
+        .map(|nonce| nonce.trim().to_string())
+        .filter(|nonce| !nonce.is_empty())
+    else {
+        return Ok(None);
+    };
+
+    if nonce.chars().count() > MAX_NONCE_LENGTH {
+        return Err(Error::BadRequest {
+            message: format!("Nonce is too long (max {} chars)", MAX_NONCE_LENGTH),
+        });
+    }
+
+    Ok(Some(nonce))
+}
+
+/// Un fichier uploadé est référencé dans le contenu par son URL `/files/...`
+pub fn has_attachment(content: &str) -> bool {
+    content.contains("/files/")
+}
+
+/// Extrait les mentions `<@user_id>`, `<@&role_id>`, `@everyone` et `@here`, sans doublons
+pub fn parse_mentions(content: &str) -> Mentions {
+    let mut mentions = Mentions::default();
+
+    let mut rest = content;
+    while let Some(start) = rest.find("<@") {
+        rest = &rest[start + 2..];
+        let (ids, body) = match rest.strip_prefix('&') {
+            Some(body) => (&mut mentions.roles, body),
+            None => (&mut mentions.users, rest),
+        };
+        let Some(end) = body.find('>') else {
+            break;
+        };
+        if let Ok(id) = Uuid::parse_str(&body[..end]) {
+            if !ids.contains(&id) && ids.len() < MAX_MENTIONS {
+                ids.push(id);
+            }
+        }
+    }
+
+    mentions.everyone = ["@everyone", "@here"]
+        .into_iter()
+        .any(|keyword| contains_word(content, keyword));
+    mentions
+}
+
+/// Vrai si `word` apparaît sans être collé à une lettre, un chiffre ou `_` (ex. une adresse mail)
+fn contains_word(content: &str, word: &str) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    content.match_indices(word).any(|(index, _)| {
+        let before = content[..index].chars().next_back();
+        let after = content[index + word.len()..].chars().next();
+        !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+    })
+}
+
+/// Mentions retenues : membres et rôles du serveur ; `@everyone` / `@here` seulement avec
+/// MENTION_EVERYONE, sinon le message est envoyé sans notifier tout le channel
+async fn resolve_mentions(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    content: &str,
+    permissions: Permissions,
+) -> Result<Mentions> {
+    let mut mentions = parse_mentions(content);
+
+    let members = server_repo
+        .filter_members(server_id, &mentions.users)
+        .await?;
+    mentions.users.retain(|user_id| members.contains(user_id));
+
+    let roles = server_repo.filter_roles(server_id, &mentions.roles).await?;
+    mentions.roles.retain(|role_id| roles.contains(role_id));
+
+    mentions.everyone &= permissions.contains(Permissions::MENTION_EVERYONE);
+    Ok(mentions)
+}
+
+/// Refuse un nouvel épinglage quand `pinned` messages le sont déjà
+pub fn ensure_pin_capacity(pinned: u64) -> Result<()> {
+    if pinned >= MAX_PINS {
+        return Err(Error::BadRequest {
+            message: format!("Cannot pin more than {} messages", MAX_PINS),
+        });
+    }
+    Ok(())
+}
+
+/// Contenu d'un message cité en réponse, tronqué avec une ellipse
+pub fn truncate_preview(content: &str) -> String {
+    if content.chars().count() <= MAX_REPLY_PREVIEW_CHARS {
+        return content.to_string();
+    }
+
+    let mut truncated: String = content.chars().take(MAX_REPLY_PREVIEW_CHARS).collect();
+    truncated.truncate(truncated.trim_end().len());
+    truncated.push('…');
+    truncated
+}
+
+/// Aperçu d'un message cité dont il ne reste rien à montrer
+pub fn deleted_preview(message_id: Uuid) -> MessagePreview {
+    MessagePreview {
+        id: message_id,
+        deleted: true,
+        author_id: None,
+        username: None,
+        nickname: None,
+        content: None,
+    }
+}
+
+/// Vrai si un message créé à `created_at` bloque encore la réutilisation de son nonce
+pub fn is_within_nonce_window(created_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    now.signed_duration_since(created_at) < chrono::Duration::seconds(NONCE_WINDOW_SECS)
+}
 
 fn validate_reaction_emoji(emoji: &str) -> Result<()> {
     let trimmed = emoji.trim();
@@ -35,69 +223,327 @@ fn to_public_reactions(
         .collect()
 }
 
+fn to_message_with_user(message: ChannelMessage, author: MemberProfile) -> MessageWithUser {
+    MessageWithUser {
+        id: message.message_id,
+        server_id: message.server_id,
+        channel_id: message.channel_id,
+        author_id: message.author_id,
+        username: author.username,
+        nickname: author.nickname,
+        avatar_url: author.avatar_url,
+        content: message.content,
+        created_at: message.created_at,
+        edited_at: message.edited_at,
+        reactions: to_public_reactions(message.reactions),
+        nonce: message.nonce,
+        crossposted_from: message.crossposted_from,
+        reply_to: None,
+        thread_id: message.thread_id,
+        thread_message_count: message.thread_message_count,
+        thread_last_activity_at: message.thread_last_activity_at,
+        pinned_at: message.pinned_at,
+        pinned_by: message.pinned_by,
+        mentions: message.mentions,
+        mention_roles: message.mention_roles,
+        mention_everyone: message.mention_everyone,
+    }
+}
+
+/// Aperçu d'un message de channel cité en réponse (`referenced` absent ou supprimé = marqueur)
+fn reply_preview(
+    message_id: Uuid,
+    referenced: Option<&ChannelMessage>,
+    author: Option<&MemberProfile>,
+) -> MessagePreview {
+    match referenced {
+        Some(referenced) if referenced.deleted_at.is_none() => MessagePreview {
+            id: message_id,
+            deleted: false,
+            author_id: Some(referenced.author_id),
+            username: author.map(|author| author.username.clone()),
+            nickname: author.and_then(|author| author.nickname.clone()),
+            content: Some(truncate_preview(&referenced.content)),
+        },
+        _ => deleted_preview(message_id),
+    }
+}
+
+/// Vérifie que le message cité existe dans le même channel et construit son aperçu
+async fn find_reply_preview(
+    server_repo: &ServerRepository,
+    message_repo: &MessageRepository,
+    channel: &Channel,
+    reply_to: Uuid,
+) -> Result<MessagePreview> {
+    let referenced = message_repo
+        .find_by_id(reply_to)
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB query failed: {}", e),
+        })?
+        .filter(|referenced| referenced.channel_id == channel.id && referenced.deleted_at.is_none())
+        .ok_or_else(|| Error::BadRequest {
+            message: "Referenced message not found".to_string(),
+        })?;
+
+    let author = server_repo
+        .get_member_profile(channel.server_id, referenced.author_id)
+        .await?;
+    Ok(reply_preview(reply_to, Some(&referenced), author.as_ref()))
+}
+
+/// Message déjà créé avec ce nonce, s'il est encore dans la fenêtre de déduplication
+async fn find_by_nonce(
+    message_repo: &MessageRepository,
+    channel_id: Uuid,
+    user_id: Uuid,
+    nonce: &str,
+) -> Result<Option<ChannelMessage>> {
+    let Some(existing) = message_repo
+        .find_by_author_nonce(user_id, nonce)
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB query failed: {}", e),
+        })?
+    else {
+        return Ok(None);
+    };
+
+    if !is_within_nonce_window(existing.created_at, Utc::now()) {
+        message_repo
+            .clear_nonce(existing.message_id)
+            .await
+            .map_err(|e| Error::DatabaseError {
+                message: format!("MongoDB update failed: {}", e),
+            })?;
+        return Ok(None);
+    }
+
+    if existing.channel_id != channel_id {
+        return Err(Error::BadRequest {
+            message: "Nonce already used in another channel".to_string(),
+        });
+    }
+
+    Ok(Some(existing))
+}
+
+/// Secondes restantes avant de pouvoir republier, None si le slowmode est écoulé
+fn slowmode_retry_after(
+    rate_limit_per_user: i32,
+    last_message_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Option<u64> {
+    let available_at = last_message_at + chrono::Duration::seconds(rate_limit_per_user.into());
+    let remaining_ms = (available_at - now).num_milliseconds();
+    (remaining_ms > 0).then(|| (remaining_ms as u64).div_ceil(1000))
+}
+
+async fn ensure_slowmode_elapsed(
+    message_repo: &MessageRepository,
+    channel: &Channel,
+    user_id: Uuid,
+) -> Result<()> {
+    if channel.rate_limit_per_user <= 0 {
+        return Ok(());
+    }
+
+    let last = message_repo
+        .find_last_by_author(channel.id, user_id)
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB query failed: {}", e),
+        })?;
+
+    let retry_after = last.and_then(|last| {
+        slowmode_retry_after(channel.rate_limit_per_user, last.created_at, Utc::now())
+    });
+    match retry_after {
+        Some(retry_after) => Err(Error::SlowmodeActive { retry_after }),
+        None => Ok(()),
+    }
+}
+
+/// Crée un message dans un channel
+/// Retourne aussi le type du channel (suites de l'envoi) et `false` si un envoi précédent
+/// avec le même nonce a été renvoyé
 pub async fn create_message(
     server_repo: &ServerRepository,
     channel_repo: &ChannelRepository,
-    user_repo: &UserRepository,
     message_repo: &MessageRepository,
     channel_id: Uuid,
     user_id: Uuid,
     payload: CreateMessagePayload,
-) -> Result<MessageWithUser> {
+) -> Result<(MessageWithUser, ChannelKind, bool)> {
     let channel = channels::get_channel(server_repo, channel_repo, channel_id, user_id).await?;
+    channels::ensure_accepts_messages(&channel)?;
 
-    servers::get_member(server_repo, channel.server_id, user_id)
-        .await?
-        .ok_or(Error::MessageForbidden)?;
+    let mut required = channels::send_permissions(&channel);
+    if has_attachment(&payload.content) {
+        required |= Permissions::ATTACH_FILES;
+    }
+    let permissions = channels::ensure_channel_permission(
+        server_repo,
+        channel_repo,
+        &channel,
+        user_id,
+        required,
+        Error::MessageForbidden,
+    )
+    .await?;
 
-    let username = user_repo
-        .get_username(user_id)
+    let author = server_repo
+        .get_member_profile(channel.server_id, user_id)
         .await?
         .ok_or(Error::UserNotFound)?;
 
-    let message_id = Uuid::new_v4();
-    let now = Utc::now();
-    let content = payload.content.clone();
+    let preview = match payload.reply_to {
+        Some(reply_to) => {
+            Some(find_reply_preview(server_repo, message_repo, &channel, reply_to).await?)
+        }
+        None => None,
+    };
+    let respond = |message: ChannelMessage| {
+        let reply_to = preview
+            .clone()
+            .filter(|preview| message.reply_to == Some(preview.id));
+        MessageWithUser {
+            reply_to,
+            ..to_message_with_user(message, author.clone())
+        }
+    };
+
+    let nonce = normalize_nonce(payload.nonce)?;
+    if let Some(nonce) = nonce.as_deref() {
+        if let Some(existing) = find_by_nonce(message_repo, channel_id, user_id, nonce).await? {
+            return Ok((respond(existing), channel.kind, false));
+        }
+    }
+
+    // Les envois rejoués ci-dessus ne sont pas freinés par le slowmode
+    if !permissions.contains(Permissions::MANAGE_MESSAGES) {
+        ensure_slowmode_elapsed(message_repo, &channel, user_id).await?;
+    }
+
+    let mentions = resolve_mentions(
+        server_repo,
+        channel.server_id,
+        &payload.content,
+        permissions,
+    )
+    .await?;
 
     let message = ChannelMessage {
         id: None,
-        message_id,
+        message_id: Uuid::new_v4(),
         server_id: channel.server_id,
         channel_id,
         author_id: user_id,
-        content: content.clone(),
-        created_at: now,
+        content: payload.content,
+        created_at: Utc::now(),
         edited_at: None,
         deleted_at: None,
         deleted_by: None,
         reactions: vec![],
+        nonce,
+        crossposted_from: None,
+        reply_to: payload.reply_to,
+        thread_id: None,
+        thread_message_count: 0,
+        thread_last_activity_at: None,
+        pinned_at: None,
+        pinned_by: None,
+        mentions: mentions.users,
+        mention_roles: mentions.roles,
+        mention_everyone: mentions.everyone,
     };
 
-    message_repo
-        .create(&message)
-        .await
-        .map_err(|e| Error::DatabaseError {
+    if let Err(e) = message_repo.create(&message).await {
+        // Envoi concurrent avec le même nonce : l'index unique a gardé le premier
+        if let (true, Some(nonce)) = (is_duplicate_key_error(&e), message.nonce.as_deref()) {
+            if let Some(existing) = find_by_nonce(message_repo, channel_id, user_id, nonce).await? {
+                return Ok((respond(existing), channel.kind, false));
+            }
+        }
+
+        return Err(Error::DatabaseError {
             message: format!("MongoDB insert failed: {}", e),
-        })?;
+        });
+    }
 
-    Ok(MessageWithUser {
-        id: message_id,
-        server_id: channel.server_id,
-        channel_id,
-        author_id: user_id,
-        username,
-        content,
-        created_at: now,
-        edited_at: None,
-        reactions: vec![],
-    })
+    Ok((respond(message), channel.kind, true))
+}
+
+/// Recopie un message d'annonce dans les channels qui suivent son channel
+/// Retourne les copies créées ; un abonné en échec est ignoré
+pub async fn crosspost_message(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    message_repo: &MessageRepository,
+    message: &MessageWithUser,
+) -> Result<Vec<MessageWithUser>> {
+    let followers = channel_repo.list_followers(message.channel_id).await?;
+
+    let mut copies = Vec::with_capacity(followers.len());
+    for follower in followers {
+        let Some(target) = channel_repo.find_by_id(follower.target_channel_id).await? else {
+            continue;
+        };
+
+        let copy = ChannelMessage {
+            id: None,
+            message_id: Uuid::new_v4(),
+            server_id: target.server_id,
+            channel_id: target.id,
+            author_id: message.author_id,
+            content: message.content.clone(),
+            created_at: message.created_at,
+            edited_at: None,
+            deleted_at: None,
+            deleted_by: None,
+            reactions: vec![],
+            nonce: None,
+            crossposted_from: Some(message.channel_id),
+            reply_to: None,
+            thread_id: None,
+            thread_message_count: 0,
+            thread_last_activity_at: None,
+            pinned_at: None,
+            pinned_by: None,
+            // Les mentions visent le serveur d'origine
+            mentions: vec![],
+            mention_roles: vec![],
+            mention_everyone: false,
+        };
+
+        if let Err(e) = message_repo.create(&copy).await {
+            tracing::warn!(
+                "Failed to crosspost message {} to channel {}: {}",
+                message.id,
+                target.id,
+                e
+            );
+            continue;
+        }
+        // L'auteur apparaît avec son profil du serveur abonné, s'il en est membre
+        let author = server_repo
+            .get_member_profile(target.server_id, message.author_id)
+            .await?
+            .unwrap_or_else(|| MemberProfile {
+                user_id: message.author_id,
+                username: message.username.clone(),
+                ..Default::default()
+            });
+        copies.push(to_message_with_user(copy, author));
+    }
+
+    Ok(copies)
 }
 
-#[allow(clippy::too_many_arguments)]
 pub async fn list_messages(
     server_repo: &ServerRepository,
     channel_repo: &ChannelRepository,
-    user_repo: &UserRepository,
     message_repo: &MessageRepository,
     channel_id: Uuid,
     user_id: Uuid,
@@ -117,41 +563,176 @@ pub async fn list_messages(
             message: format!("MongoDB query failed: {}", e),
         })?;
 
+    let mut result = with_authors(server_repo, message_repo, channel.server_id, messages).await?;
+    result.reverse();
+    Ok(result)
+}
+
+/// Profils des auteurs et aperçus des messages cités, résolus en une requête chacun
+pub async fn with_authors(
+    server_repo: &ServerRepository,
+    message_repo: &MessageRepository,
+    server_id: Uuid,
+    messages: Vec<ChannelMessage>,
+) -> Result<Vec<MessageWithUser>> {
     if messages.is_empty() {
         return Ok(vec![]);
     }
 
-    let author_ids: Vec<Uuid> = messages.iter().map(|m| m.author_id).collect();
-    let usernames = user_repo.get_usernames_batch(&author_ids).await?;
+    // Messages cités en réponse, récupérés en une requête (supprimés compris)
+    let reply_ids: Vec<Uuid> = messages.iter().filter_map(|m| m.reply_to).collect();
+    let referenced: HashMap<Uuid, ChannelMessage> = if reply_ids.is_empty() {
+        HashMap::new()
+    } else {
+        message_repo
+            .find_by_ids(&reply_ids)
+            .await
+            .map_err(|e| Error::DatabaseError {
+                message: format!("MongoDB query failed: {}", e),
+            })?
+            .into_iter()
+            .map(|referenced| (referenced.message_id, referenced))
+            .collect()
+    };
+
+    let author_ids: Vec<Uuid> = messages
+        .iter()
+        .map(|m| m.author_id)
+        .chain(referenced.values().map(|referenced| referenced.author_id))
+        .collect();
+    let authors = server_repo
+        .get_member_profiles(server_id, &author_ids)
+        .await?;
 
-    let mut result: Vec<MessageWithUser> = messages
+    let result = messages
         .into_iter()
-        .map(|m| MessageWithUser {
-            id: m.message_id,
-            server_id: m.server_id,
-            channel_id: m.channel_id,
-            author_id: m.author_id,
-            username: usernames
+        .map(|m| {
+            let author = authors
                 .get(&m.author_id)
                 .cloned()
-                .unwrap_or_else(|| "Unknown".to_string()),
-            content: m.content,
-            created_at: m.created_at,
-            edited_at: m.edited_at,
-            reactions: to_public_reactions(m.reactions),
+                .unwrap_or_else(|| MemberProfile {
+                    user_id: m.author_id,
+                    username: "Unknown".to_string(),
+                    ..Default::default()
+                });
+            let reply_to = m.reply_to.map(|reply_to| {
+                let referenced = referenced
+                    .get(&reply_to)
+                    .filter(|referenced| referenced.channel_id == m.channel_id);
+                let author = referenced.and_then(|referenced| authors.get(&referenced.author_id));
+                reply_preview(reply_to, referenced, author)
+            });
+            MessageWithUser {
+                reply_to,
+                ..to_message_with_user(m, author)
+            }
         })
         .collect();
-
-    result.reverse();
     Ok(result)
 }
 
+/// Messages épinglés d'un channel, les derniers épinglés d'abord
+pub async fn list_pins(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    message_repo: &MessageRepository,
+    channel_id: Uuid,
+    user_id: Uuid,
+) -> Result<Vec<MessageWithUser>> {
+    let channel = channels::get_channel(server_repo, channel_repo, channel_id, user_id).await?;
+
+    let messages = message_repo
+        .list_pins(channel_id)
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB query failed: {}", e),
+        })?;
+
+    with_authors(server_repo, message_repo, channel.server_id, messages).await
+}
+
+/// Épingle ou désépingle un message de channel (MANAGE_MESSAGES)
+/// Retourne le message à jour, et `false` s'il était déjà dans cet état
+pub async fn set_pinned(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    message_repo: &MessageRepository,
+    channel_id: Uuid,
+    message_id: Uuid,
+    user_id: Uuid,
+    pinned: bool,
+) -> Result<(ChannelMessage, bool)> {
+    let channel = channels::get_channel(server_repo, channel_repo, channel_id, user_id).await?;
+    channels::ensure_channel_permission(
+        server_repo,
+        channel_repo,
+        &channel,
+        user_id,
+        Permissions::MANAGE_MESSAGES,
+        Error::MessageForbidden,
+    )
+    .await?;
+
+    let find = || async {
+        message_repo
+            .find_by_id(message_id)
+            .await
+            .map_err(|e| Error::DatabaseError {
+                message: format!("MongoDB query failed: {}", e),
+            })?
+            .filter(|message| message.channel_id == channel_id && message.deleted_at.is_none())
+            .ok_or(Error::MessageNotFound)
+    };
+    let message = find().await?;
+    if message.pinned_at.is_some() == pinned {
+        return Ok((message, false));
+    }
+
+    if pinned {
+        let count =
+            message_repo
+                .count_pins(channel_id)
+                .await
+                .map_err(|e| Error::DatabaseError {
+                    message: format!("MongoDB query failed: {}", e),
+                })?;
+        ensure_pin_capacity(count)?;
+    }
+
+    let changed = message_repo
+        .set_pinned(message_id, pinned.then_some(user_id))
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB update failed: {}", e),
+        })?;
+    let message = find().await?;
+
+    if changed {
+        let action = match pinned {
+            true => AuditAction::MessagePin,
+            false => AuditAction::MessageUnpin,
+        };
+        let entry = NewAuditLogEntry::new(channel.server_id, user_id, action)
+            .target(message_id)
+            .after(&serde_json::json!({
+                "channel_id": channel_id,
+                "author_id": message.author_id,
+            }));
+        audit::record(server_repo, entry).await;
+    }
+
+    Ok((message, changed))
+}
+
+/// Supprime (soft delete) un message, retourne le message tel qu'avant suppression
+/// L'auteur supprime ses messages, MANAGE_MESSAGES ceux des autres
 pub async fn delete_message(
     server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
     message_repo: &MessageRepository,
     message_id: Uuid,
     user_id: Uuid,
-) -> Result<Uuid> {
+) -> Result<ChannelMessage> {
     let message = message_repo
         .find_by_id(message_id)
         .await
@@ -160,11 +741,19 @@ pub async fn delete_message(
         })?
         .ok_or(Error::MessageNotFound)?;
 
-    servers::get_member(server_repo, message.server_id, user_id)
+    let channel = channel_repo
+        .find_by_id(message.channel_id)
         .await?
-        .ok_or(Error::MessageForbidden)?;
+        .ok_or(Error::MessageNotFound)?;
+    let permissions =
+        channels::channel_permissions(server_repo, channel_repo, &channel, user_id).await?;
 
-    if message.author_id != user_id {
+    let required = if message.author_id == user_id {
+        Permissions::VIEW_CHANNEL
+    } else {
+        Permissions::MANAGE_MESSAGES
+    };
+    if !permissions.contains(required) {
         return Err(Error::MessageForbidden);
     }
 
@@ -179,11 +768,79 @@ pub async fn delete_message(
             message: format!("MongoDB update failed: {}", e),
         })?;
 
-    Ok(message.channel_id)
+    if message.author_id != user_id {
+        let entry = NewAuditLogEntry::new(message.server_id, user_id, AuditAction::MessageDelete)
+            .target(message_id)
+            .before(&serde_json::json!({
+                "channel_id": message.channel_id,
+                "author_id": message.author_id,
+                "content": message.content,
+            }));
+        audit::record(server_repo, entry).await;
+    }
+
+    Ok(message)
+}
+
+/// Supprime (soft delete) plusieurs messages d'un channel en une opération (MANAGE_MESSAGES)
+/// Retourne les ids effectivement supprimés (les ids inconnus ou déjà supprimés sont ignorés)
+pub async fn bulk_delete_messages(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    message_repo: &MessageRepository,
+    channel_id: Uuid,
+    user_id: Uuid,
+    payload: BulkDeleteMessagesPayload,
+) -> Result<(Channel, Vec<Uuid>)> {
+    let target = bulk_delete_target(payload)?;
+
+    let channel = channels::require_channel_permission(
+        server_repo,
+        channel_repo,
+        channel_id,
+        user_id,
+        Permissions::MANAGE_MESSAGES,
+        Error::MessageForbidden,
+    )
+    .await?;
+
+    let messages = match target {
+        BulkDeleteTarget::Messages(message_ids) => {
+            message_repo.list_by_ids(channel_id, &message_ids).await
+        }
+        BulkDeleteTarget::LastFromAuthor { author_id, limit } => {
+            message_repo
+                .list_recent_by_author(channel_id, author_id, limit)
+                .await
+        }
+    }
+    .map_err(|e| Error::DatabaseError {
+        message: format!("MongoDB query failed: {}", e),
+    })?;
+
+    let message_ids: Vec<Uuid> = messages.iter().map(|m| m.message_id).collect();
+    if message_ids.is_empty() {
+        return Ok((channel, message_ids));
+    }
+
+    message_repo
+        .soft_delete_many(&message_ids, user_id)
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB update failed: {}", e),
+        })?;
+
+    let entry = NewAuditLogEntry::new(channel.server_id, user_id, AuditAction::MessageBulkDelete)
+        .target(channel_id)
+        .after(&serde_json::json!({ "message_ids": message_ids }));
+    audit::record(server_repo, entry).await;
+
+    Ok((channel, message_ids))
 }
 
 pub async fn update_message(
     server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
     message_repo: &MessageRepository,
     message_id: Uuid,
     user_id: Uuid,
@@ -197,9 +854,19 @@ pub async fn update_message(
         })?
         .ok_or(Error::MessageNotFound)?;
 
-    servers::get_member(server_repo, message.server_id, user_id)
+    let channel = channel_repo
+        .find_by_id(message.channel_id)
         .await?
-        .ok_or(Error::MessageForbidden)?;
+        .ok_or(Error::ChannelNotFound)?;
+    let permissions = channels::ensure_channel_permission(
+        server_repo,
+        channel_repo,
+        &channel,
+        user_id,
+        Permissions::VIEW_CHANNEL,
+        Error::MessageForbidden,
+    )
+    .await?;
 
     if message.author_id != user_id {
         return Err(Error::MessageForbidden);
@@ -209,8 +876,21 @@ pub async fn update_message(
         return Err(Error::MessageNotFound);
     }
 
+    let mentions = resolve_mentions(
+        server_repo,
+        message.server_id,
+        &payload.content,
+        permissions,
+    )
+    .await?;
     message_repo
-        .update_content(message_id, &payload.content)
+        .update_content(
+            message_id,
+            &payload.content,
+            &mentions.users,
+            &mentions.roles,
+            mentions.everyone,
+        )
         .await
         .map_err(|e| Error::DatabaseError {
             message: format!("MongoDB update failed: {}", e),
@@ -222,15 +902,29 @@ pub async fn update_message(
         channel_id: message.channel_id,
         author_id: message.author_id,
         username: String::new(),
+        nickname: None,
+        avatar_url: None,
         content: payload.content,
         created_at: message.created_at,
         edited_at: Some(Utc::now()),
         reactions: to_public_reactions(message.reactions),
+        nonce: message.nonce,
+        crossposted_from: message.crossposted_from,
+        reply_to: None,
+        thread_id: message.thread_id,
+        thread_message_count: message.thread_message_count,
+        thread_last_activity_at: message.thread_last_activity_at,
+        pinned_at: message.pinned_at,
+        pinned_by: message.pinned_by,
+        mentions: mentions.users,
+        mention_roles: mentions.roles,
+        mention_everyone: mentions.everyone,
     })
 }
 
 pub async fn add_reaction(
     server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
     message_repo: &MessageRepository,
     message_id: Uuid,
     user_id: Uuid,
@@ -246,9 +940,15 @@ pub async fn add_reaction(
         })?
         .ok_or(Error::MessageNotFound)?;
 
-    servers::get_member(server_repo, message.server_id, user_id)
-        .await?
-        .ok_or(Error::MessageForbidden)?;
+    channels::require_channel_permission(
+        server_repo,
+        channel_repo,
+        message.channel_id,
+        user_id,
+        Permissions::ADD_REACTIONS,
+        Error::MessageForbidden,
+    )
+    .await?;
 
     if message.deleted_at.is_some() {
         return Err(Error::MessageNotFound);
@@ -269,21 +969,12 @@ pub async fn add_reaction(
         })?
         .ok_or(Error::MessageNotFound)?;
 
-    Ok(MessageWithUser {
-        id: updated.message_id,
-        server_id: updated.server_id,
-        channel_id: updated.channel_id,
-        author_id: updated.author_id,
-        username: String::new(),
-        content: updated.content,
-        created_at: updated.created_at,
-        edited_at: updated.edited_at,
-        reactions: to_public_reactions(updated.reactions),
-    })
+    Ok(to_message_with_user(updated, MemberProfile::default()))
 }
 
 pub async fn remove_reaction(
     server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
     message_repo: &MessageRepository,
     message_id: Uuid,
     user_id: Uuid,
@@ -299,9 +990,15 @@ pub async fn remove_reaction(
         })?
         .ok_or(Error::MessageNotFound)?;
 
-    servers::get_member(server_repo, message.server_id, user_id)
-        .await?
-        .ok_or(Error::MessageForbidden)?;
+    channels::require_channel_permission(
+        server_repo,
+        channel_repo,
+        message.channel_id,
+        user_id,
+        Permissions::ADD_REACTIONS,
+        Error::MessageForbidden,
+    )
+    .await?;
 
     if message.deleted_at.is_some() {
         return Err(Error::MessageNotFound);
@@ -322,15 +1019,106 @@ pub async fn remove_reaction(
         })?
         .ok_or(Error::MessageNotFound)?;
 
-    Ok(MessageWithUser {
-        id: updated.message_id,
-        server_id: updated.server_id,
-        channel_id: updated.channel_id,
-        author_id: updated.author_id,
-        username: String::new(),
-        content: updated.content,
-        created_at: updated.created_at,
-        edited_at: updated.edited_at,
-        reactions: to_public_reactions(updated.reactions),
-    })
+    Ok(to_message_with_user(updated, MemberProfile::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(
+        message_ids: Vec<Uuid>,
+        author_id: Option<Uuid>,
+        limit: Option<i64>,
+    ) -> BulkDeleteMessagesPayload {
+        BulkDeleteMessagesPayload {
+            message_ids,
+            author_id,
+            limit,
+        }
+    }
+
+    #[test]
+    fn bulk_delete_target_dedupes_ids_and_caps_count() {
+        let id = Uuid::new_v4();
+        let target = bulk_delete_target(payload(vec![id, id], None, None)).unwrap();
+        assert_eq!(target, BulkDeleteTarget::Messages(vec![id]));
+
+        let too_many = (0..=MAX_BULK_DELETE).map(|_| Uuid::new_v4()).collect();
+        assert!(bulk_delete_target(payload(too_many, None, None)).is_err());
+    }
+
+    #[test]
+    fn bulk_delete_target_requires_exactly_one_mode() {
+        let author_id = Uuid::new_v4();
+        let target = bulk_delete_target(payload(vec![], Some(author_id), Some(20))).unwrap();
+        assert_eq!(
+            target,
+            BulkDeleteTarget::LastFromAuthor {
+                author_id,
+                limit: 20
+            }
+        );
+
+        assert!(bulk_delete_target(payload(vec![], None, None)).is_err());
+        assert!(bulk_delete_target(payload(vec![], Some(author_id), None)).is_err());
+        assert!(bulk_delete_target(payload(vec![], Some(author_id), Some(101))).is_err());
+        assert!(
+            bulk_delete_target(payload(vec![Uuid::new_v4()], Some(author_id), Some(1))).is_err()
+        );
+    }
+
+    #[test]
+    fn slowmode_rounds_remaining_wait_up() {
+        let last = Utc::now();
+        let at = |ms| last + chrono::Duration::milliseconds(ms);
+
+        assert_eq!(slowmode_retry_after(10, last, at(0)), Some(10));
+        assert_eq!(slowmode_retry_after(10, last, at(8_500)), Some(2));
+        assert_eq!(slowmode_retry_after(10, last, at(10_000)), None);
+        assert_eq!(slowmode_retry_after(0, last, at(0)), None);
+    }
+
+    #[test]
+    fn truncate_preview_keeps_short_content_and_cuts_on_chars() {
+        assert_eq!(truncate_preview("salut"), "salut");
+
+        let long = "é".repeat(MAX_REPLY_PREVIEW_CHARS + 5);
+        let preview = truncate_preview(&long);
+        assert_eq!(preview.chars().count(), MAX_REPLY_PREVIEW_CHARS + 1);
+        assert!(preview.ends_with('…'));
+
+        let spaced = format!("{} suite", "a".repeat(MAX_REPLY_PREVIEW_CHARS - 1));
+        assert_eq!(
+            truncate_preview(&spaced),
+            format!("{}…", "a".repeat(MAX_REPLY_PREVIEW_CHARS - 1))
+        );
+    }
+
+    #[test]
+    fn pin_capacity_stops_at_limit() {
+        assert!(ensure_pin_capacity(0).is_ok());
+        assert!(ensure_pin_capacity(MAX_PINS - 1).is_ok());
+        assert!(ensure_pin_capacity(MAX_PINS).is_err());
+    }
+
+    #[test]
+    fn parse_mentions_extracts_users_and_roles_once() {
+        let user = Uuid::new_v4();
+        let role = Uuid::new_v4();
+        let content = format!("<@{user}> et <@&{role}>, encore <@{user}> <@pas-un-id> <@{role}");
+
+        let mentions = parse_mentions(&content);
+        assert_eq!(mentions.users, vec![user]);
+        assert_eq!(mentions.roles, vec![role]);
+        assert!(!mentions.everyone);
+    }
+
+    #[test]
+    fn parse_mentions_detects_everyone_and_here_as_words() {
+        assert!(parse_mentions("@everyone réunion").everyone);
+        assert!(parse_mentions("qui est là ? @here.").everyone);
+        assert!(!parse_mentions("contact@everyone.fr").everyone);
+        assert!(!parse_mentions("@heretique").everyone);
+    }
 }
@@ -1,23 +1,25 @@
+pub mod audit;
 pub mod auth;
 pub mod bootstrap;
 pub mod channels;
+pub mod dm;
 pub mod invites;
 pub mod jwt;
 pub mod messages;
 pub mod password;
 pub mod realtime;
+pub mod roles;
+pub mod search;
 pub mod servers;
+pub mod threads;
 pub mod usernames;
 
 pub use auth::{login, logout, signup};
-pub use channels::{create_channel, delete_channel, get_channel, list_channels, update_channel};
-pub use invites::{create_invite, get_invite_by_code, join_server_with_code, list_invites};
+pub use channels::{get_channel, list_channels};
+pub use invites::{create_invite, get_invite_by_code, list_invites};
 pub use jwt::{create_token, verify_token};
-pub use messages::{create_message, delete_message, list_messages, update_message};
+pub use messages::{create_message, list_messages};
 //pub use invite::{accept_invite, create_invite, get_invite_by_code};
 pub use password::{hash_password, verify_password};
-pub use servers::{
-    ban_member, create_server, delete_server, get_member, get_server, join_server, kick_member,
-    leave_server, list_bans, list_members, list_user_servers, transfer_ownership, unban_member,
-    update_member_role, update_server,
-};
+pub use roles::list_roles;
+pub use servers::{get_member, get_server, list_bans, list_members, list_user_servers};
@@ -0,0 +1,368 @@
+//! Événements temps réel liés aux channels (création, modification, déplacement, suppression,
+//! overwrites)
+//! Les événements d'un channel privé ne partent qu'aux membres qui le voient
+
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::{
+    Channel, ChannelKind, ChannelOverwrite, ChannelOverwritePayload, ChannelPositionPayload,
+    CreateChannelPayload, OverwriteTarget, UpdateChannelPayload,
+};
+use crate::services::channels;
+use crate::services::realtime::servers::broadcast_to_server_members;
+use crate::services::realtime::voice;
+use crate::web::ws::protocol::ServerEvent;
+use crate::AppState;
+
+/// Utilisateurs qui voient un channel, None si tout le serveur le voit
+/// En cas d'erreur, personne : un channel privé ne doit pas fuiter
+pub async fn channel_viewers(
+    state: &AppState,
+    server_id: Uuid,
+    channel_id: Uuid,
+) -> Option<HashSet<Uuid>> {
+    match channels::channel_viewers(
+        &state.server_repo,
+        &state.channel_repo,
+        server_id,
+        channel_id,
+    )
+    .await
+    {
+        Ok(viewers) => viewers,
+        Err(err) => {
+            tracing::warn!(
+                "[Realtime] Failed to resolve viewers of channel {}: {}",
+                channel_id,
+                err
+            );
+            Some(HashSet::new())
+        }
+    }
+}
+
+/// Viewers explicites : tous les membres du serveur si le channel n'est pas restreint
+async fn resolved_viewers(state: &AppState, channel: &Channel) -> HashSet<Uuid> {
+    if let Some(viewers) = channel_viewers(state, channel.server_id, channel.id).await {
+        return viewers;
+    }
+
+    match state.server_repo.list_members(channel.server_id).await {
+        Ok(members) => members.into_iter().map(|member| member.user_id).collect(),
+        Err(err) => {
+            tracing::warn!(
+                "[Realtime] Failed to list members for server {}: {}",
+                channel.server_id,
+                err
+            );
+            HashSet::new()
+        }
+    }
+}
+
+/// Envoie un événement de channel aux membres qui le voient
+pub async fn broadcast_to_viewers(
+    state: &AppState,
+    server_id: Uuid,
+    viewers: Option<&HashSet<Uuid>>,
+    event: &ServerEvent,
+) {
+    match viewers {
+        None => broadcast_to_server_members(state, server_id, event).await,
+        Some(viewers) => state.ws_hub.send_to_users(viewers, event).await,
+    }
+}
+
+fn channel_create_event(channel: &Channel) -> ServerEvent {
+    ServerEvent::ChannelCreate {
+        id: channel.id,
+        server_id: channel.server_id,
+        name: channel.name.clone(),
+        kind: channel.kind,
+        parent_id: channel.parent_id,
+        position: channel.position,
+        rate_limit_per_user: channel.rate_limit_per_user,
+        created_at: channel.created_at,
+        updated_at: channel.updated_at,
+    }
+}
+
+fn channel_update_event(channel: &Channel) -> ServerEvent {
+    ServerEvent::ChannelUpdate {
+        id: channel.id,
+        server_id: channel.server_id,
+        name: channel.name.clone(),
+        kind: channel.kind,
+        parent_id: channel.parent_id,
+        position: channel.position,
+        rate_limit_per_user: channel.rate_limit_per_user,
+        created_at: channel.created_at,
+        updated_at: channel.updated_at,
+    }
+}
+
+/// CHANNEL_UPDATE aux membres qui voient le channel
+async fn broadcast_channel_update(state: &AppState, channel: &Channel) {
+    let viewers = channel_viewers(state, channel.server_id, channel.id).await;
+    let event = channel_update_event(channel);
+    broadcast_to_viewers(state, channel.server_id, viewers.as_ref(), &event).await;
+}
+
+fn channel_delete_event(channel: &Channel) -> ServerEvent {
+    ServerEvent::ChannelDelete {
+        id: channel.id,
+        server_id: channel.server_id,
+    }
+}
+
+/// Crée un channel et broadcast CHANNEL_CREATE aux membres du serveur qui le voient
+pub async fn handle_create_channel(
+    state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    payload: CreateChannelPayload,
+) -> Result<Channel> {
+    let channel = channels::create_channel(
+        &state.server_repo,
+        &state.channel_repo,
+        server_id,
+        user_id,
+        payload,
+    )
+    .await?;
+
+    let viewers = channel_viewers(state, channel.server_id, channel.id).await;
+    let event = channel_create_event(&channel);
+    broadcast_to_viewers(state, channel.server_id, viewers.as_ref(), &event).await;
+
+    Ok(channel)
+}
+
+/// Modifie un channel et broadcast CHANNEL_UPDATE aux membres du serveur qui le voient
+pub async fn handle_update_channel(
+    state: &AppState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    payload: UpdateChannelPayload,
+) -> Result<Channel> {
+    let channel = channels::update_channel(
+        &state.server_repo,
+        &state.channel_repo,
+        channel_id,
+        user_id,
+        payload,
+    )
+    .await?;
+
+    broadcast_channel_update(state, &channel).await;
+    Ok(channel)
+}
+
+/// Déplace un lot de channels, broadcast CHANNEL_UPDATE pour chaque channel modifié
+/// et annonce les changements de visibilité des channels synchronisés avec leur catégorie
+pub async fn handle_update_channel_positions(
+    state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    changes: Vec<ChannelPositionPayload>,
+) -> Result<()> {
+    // Les overwrites des channels synchronisés vont changer : viewers avant le déplacement
+    let mut before = Vec::new();
+    for change in changes.iter().filter(|change| change.lock_permissions) {
+        if let Some(channel) = state.channel_repo.find_by_id(change.id).await? {
+            before.push((channel.id, resolved_viewers(state, &channel).await));
+        }
+    }
+
+    let changed = channels::update_channel_positions(
+        &state.server_repo,
+        &state.channel_repo,
+        server_id,
+        user_id,
+        changes,
+    )
+    .await?;
+
+    for (channel_id, viewers) in before {
+        if let Some(channel) = changed.iter().find(|channel| channel.id == channel_id) {
+            announce_visibility_change(state, channel, viewers).await;
+        }
+    }
+    for channel in &changed {
+        broadcast_channel_update(state, channel).await;
+    }
+
+    Ok(())
+}
+
+/// Supprime un channel et broadcast CHANNEL_DELETE aux membres du serveur qui le voyaient
+pub async fn handle_delete_channel(
+    state: &AppState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<()> {
+    // Les overwrites disparaissent avec le channel : résoudre les viewers avant
+    let existing = state
+        .channel_repo
+        .find_by_id(channel_id)
+        .await?
+        .ok_or(Error::ChannelNotFound)?;
+    let viewers = channel_viewers(state, existing.server_id, channel_id).await;
+    let children = match existing.kind {
+        ChannelKind::Category => state.channel_repo.list_children(channel_id).await?,
+        _ => vec![],
+    };
+    let thread = match existing.kind {
+        ChannelKind::Thread => state.channel_repo.find_thread(channel_id).await?,
+        _ => None,
+    };
+
+    let channel =
+        channels::delete_channel(&state.server_repo, &state.channel_repo, channel_id, user_id)
+            .await?;
+
+    let event = channel_delete_event(&channel);
+    broadcast_to_viewers(state, channel.server_id, viewers.as_ref(), &event).await;
+
+    if channel.kind == ChannelKind::Voice {
+        voice::clear_voice_channel(state, channel.id).await;
+    }
+
+    // Le message d'origine d'un thread supprimé peut en ouvrir un nouveau
+    if let Some(thread) = thread {
+        if let Err(err) = state
+            .message_repo
+            .clear_thread(thread.parent_message_id)
+            .await
+        {
+            tracing::warn!(
+                "[Realtime] Failed to detach thread {} from its message: {}",
+                thread.id,
+                err
+            );
+        }
+    }
+
+    // Les channels d'une catégorie supprimée remontent au premier niveau
+    for child in children {
+        if let Ok(Some(child)) = state.channel_repo.find_by_id(child.id).await {
+            broadcast_channel_update(state, &child).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Viewers de chaque channel d'une catégorie, avant un changement de ses overwrites
+async fn children_viewers(state: &AppState, channel: &Channel) -> Vec<(Channel, HashSet<Uuid>)> {
+    if channel.kind != ChannelKind::Category {
+        return vec![];
+    }
+
+    let children = match state.channel_repo.list_children(channel.id).await {
+        Ok(children) => children,
+        Err(err) => {
+            tracing::warn!(
+                "[Realtime] Failed to list children of category {}: {}",
+                channel.id,
+                err
+            );
+            return vec![];
+        }
+    };
+
+    let mut viewers = Vec::with_capacity(children.len());
+    for child in children {
+        let before = resolved_viewers(state, &child).await;
+        viewers.push((child, before));
+    }
+    viewers
+}
+
+/// Après un changement d'overwrite : CHANNEL_CREATE à ceux qui voient désormais le channel,
+/// CHANNEL_DELETE à ceux qui ne le voient plus
+async fn announce_visibility_change(state: &AppState, channel: &Channel, before: HashSet<Uuid>) {
+    let after = resolved_viewers(state, channel).await;
+
+    let gained = after.difference(&before).copied().collect();
+    state
+        .ws_hub
+        .send_to_users(&gained, &channel_create_event(channel))
+        .await;
+
+    let lost = before.difference(&after).copied().collect();
+    state
+        .ws_hub
+        .send_to_users(&lost, &channel_delete_event(channel))
+        .await;
+}
+
+/// Crée ou remplace un overwrite et annonce les changements de visibilité
+pub async fn handle_set_overwrite(
+    state: &AppState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    target_type: OverwriteTarget,
+    target_id: Uuid,
+    payload: ChannelOverwritePayload,
+) -> Result<ChannelOverwrite> {
+    let existing = state
+        .channel_repo
+        .find_by_id(channel_id)
+        .await?
+        .ok_or(Error::ChannelNotFound)?;
+    let before = resolved_viewers(state, &existing).await;
+    let children = children_viewers(state, &existing).await;
+
+    let (channel, overwrite) = channels::set_overwrite(
+        &state.server_repo,
+        &state.channel_repo,
+        channel_id,
+        user_id,
+        target_type,
+        target_id,
+        payload,
+    )
+    .await?;
+
+    announce_visibility_change(state, &channel, before).await;
+    for (child, before) in children {
+        announce_visibility_change(state, &child, before).await;
+    }
+    Ok(overwrite)
+}
+
+/// Supprime un overwrite et annonce les changements de visibilité
+pub async fn handle_delete_overwrite(
+    state: &AppState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    target_type: OverwriteTarget,
+    target_id: Uuid,
+) -> Result<()> {
+    let existing = state
+        .channel_repo
+        .find_by_id(channel_id)
+        .await?
+        .ok_or(Error::ChannelNotFound)?;
+    let before = resolved_viewers(state, &existing).await;
+    let children = children_viewers(state, &existing).await;
+
+    let channel = channels::delete_overwrite(
+        &state.server_repo,
+        &state.channel_repo,
+        channel_id,
+        user_id,
+        target_type,
+        target_id,
+    )
+    .await?;
+
+    announce_visibility_change(state, &channel, before).await;
+    for (child, before) in children {
+        announce_visibility_change(state, &child, before).await;
+    }
+    Ok(())
+}
@@ -1,21 +1,30 @@
 //! Gestion des messages temps réel via WebSocket
 
+use std::collections::HashSet;
 use uuid::Uuid;
 
 use crate::error::{Error, Result};
-use crate::models::CreateMessagePayload;
-use crate::services::{channels, messages, servers};
+use crate::models::{
+    BulkDeleteMessagesPayload, ChannelKind, CreateDMMessagePayload, CreateMessagePayload,
+    DirectMessageItemResponse, MessageReactionPayload, MessageWithUser, UpdateMessagePayload,
+};
+use crate::services::realtime::channels::channel_viewers;
+use crate::services::realtime::threads::handle_thread_activity;
+use crate::services::{dm, messages};
 use crate::web::ws::protocol::ServerEvent;
 use crate::AppState;
 
 /// Traite l'envoi d'un message via WebSocket
 /// Crée le message en DB et le broadcast aux abonnés du channel
+/// Un envoi rejoué (même nonce) renvoie le message existant sans nouveau broadcast
 pub async fn handle_send_message(
     state: &AppState,
     user_id: Uuid,
     channel_id: Uuid,
     content: String,
-) -> Result<()> {
+    nonce: Option<String>,
+    reply_to: Option<Uuid>,
+) -> Result<MessageWithUser> {
     // Validation basique
     if content.trim().is_empty() {
         return Err(Error::InternalError {
@@ -29,14 +38,6 @@ pub async fn handle_send_message(
         });
     }
 
-    // Vérifier les permissions (même logique que le service HTTP)
-    let channel =
-        channels::get_channel(&state.server_repo, &state.channel_repo, channel_id, user_id).await?;
-
-    servers::get_member(&state.server_repo, channel.server_id, user_id)
-        .await?
-        .ok_or(Error::MessageForbidden)?;
-
     // Récupérer le username (sera inclus dans le message créé)
     let _username = state
         .user_repo
@@ -44,15 +45,16 @@ pub async fn handle_send_message(
         .await?
         .ok_or(Error::UserNotFound)?;
 
-    // Créer le message (même logique que le service HTTP)
+    // Créer le message (même logique que le service HTTP, permissions comprises)
     let payload = CreateMessagePayload {
         content: content.clone(),
+        nonce,
+        reply_to,
     };
 
-    let message_with_user = messages::create_message(
+    let (message_with_user, channel_kind, created) = messages::create_message(
         &state.server_repo,
         &state.channel_repo,
-        &state.user_repo,
         &state.message_repo,
         channel_id,
         user_id,
@@ -60,23 +62,394 @@ pub async fn handle_send_message(
     )
     .await?;
 
-    // Broadcast via WebSocket aux abonnés du channel
+    if created {
+        handle_message_created(state, channel_kind, &message_with_user).await;
+    }
+
+    Ok(message_with_user)
+}
+
+/// Suites d'un nouveau message, communes au REST et au WS : MESSAGE_CREATE puis,
+/// selon le type du channel, recopie de l'annonce ou activité du thread
+pub async fn handle_message_created(
+    state: &AppState,
+    channel_kind: ChannelKind,
+    message: &MessageWithUser,
+) {
+    broadcast_message_create(state, message).await;
+
+    match channel_kind {
+        ChannelKind::Announcement => handle_crosspost(state, message).await,
+        ChannelKind::Thread => {
+            handle_thread_activity(state, message.channel_id, message.author_id).await
+        }
+        _ => {}
+    }
+}
+
+/// MESSAGE_CREATE aux abonnés du channel qui le voient
+async fn broadcast_message_create(state: &AppState, message: &MessageWithUser) {
     let event = ServerEvent::MessageCreate {
-        id: message_with_user.id,
-        channel_id: message_with_user.channel_id,
-        server_id: message_with_user.server_id,
-        author_id: message_with_user.author_id,
-        username: message_with_user.username,
-        content: message_with_user.content,
-        created_at: message_with_user.created_at,
-        edited_at: message_with_user.edited_at,
-        reactions: message_with_user.reactions,
+        id: message.id,
+        channel_id: message.channel_id,
+        server_id: message.server_id,
+        author_id: message.author_id,
+        username: message.username.clone(),
+        nickname: message.nickname.clone(),
+        avatar_url: message.avatar_url.clone(),
+        content: message.content.clone(),
+        created_at: message.created_at,
+        edited_at: message.edited_at,
+        reactions: message.reactions.clone(),
+        nonce: message.nonce.clone(),
+        crossposted_from: message.crossposted_from,
+        reply_to: message.reply_to.clone(),
+        mentions: message.mentions.clone(),
+        mention_roles: message.mention_roles.clone(),
+        mention_everyone: message.mention_everyone,
+    };
+
+    let viewers = channel_viewers(state, message.server_id, message.channel_id).await;
+
+    state
+        .ws_hub
+        .broadcast_to_channel_with_metrics(
+            message.server_id,
+            message.channel_id,
+            viewers.as_ref(),
+            &event,
+            Some(&state.ws_metrics),
+        )
+        .await;
+}
+
+/// Recopie une annonce chez les channels abonnés et broadcast leurs MESSAGE_CREATE
+/// Un échec n'annule pas l'envoi de l'annonce elle-même
+async fn handle_crosspost(state: &AppState, message: &MessageWithUser) {
+    let copies = match messages::crosspost_message(
+        &state.server_repo,
+        &state.channel_repo,
+        &state.message_repo,
+        message,
+    )
+    .await
+    {
+        Ok(copies) => copies,
+        Err(err) => {
+            tracing::warn!(
+                "[Realtime] Failed to crosspost message {}: {}",
+                message.id,
+                err
+            );
+            return;
+        }
+    };
+
+    for copy in &copies {
+        broadcast_message_create(state, copy).await;
+    }
+}
+
+/// Modifie un message et broadcast MESSAGE_UPDATE aux abonnés du channel
+pub async fn handle_edit_message(
+    state: &AppState,
+    user_id: Uuid,
+    message_id: Uuid,
+    content: String,
+) -> Result<MessageWithUser> {
+    let message = messages::update_message(
+        &state.server_repo,
+        &state.channel_repo,
+        &state.message_repo,
+        message_id,
+        user_id,
+        UpdateMessagePayload { content },
+    )
+    .await?;
+
+    if let Some(edited_at) = message.edited_at {
+        let event = ServerEvent::MessageUpdate {
+            id: message.id,
+            channel_id: message.channel_id,
+            content: message.content.clone(),
+            edited_at,
+        };
+
+        let viewers = channel_viewers(state, message.server_id, message.channel_id).await;
+
+        state
+            .ws_hub
+            .broadcast_to_channel_with_metrics(
+                message.server_id,
+                message.channel_id,
+                viewers.as_ref(),
+                &event,
+                Some(&state.ws_metrics),
+            )
+            .await;
+    }
+
+    Ok(message)
+}
+
+/// Supprime un message et broadcast MESSAGE_DELETE, retourne le channel du message
+pub async fn handle_delete_message(
+    state: &AppState,
+    user_id: Uuid,
+    message_id: Uuid,
+) -> Result<Uuid> {
+    let message = messages::delete_message(
+        &state.server_repo,
+        &state.channel_repo,
+        &state.message_repo,
+        message_id,
+        user_id,
+    )
+    .await?;
+
+    let event = ServerEvent::MessageDelete {
+        id: message_id,
+        channel_id: message.channel_id,
+    };
+    let viewers = channel_viewers(state, message.server_id, message.channel_id).await;
+    state
+        .ws_hub
+        .broadcast_to_channel_with_metrics(
+            message.server_id,
+            message.channel_id,
+            viewers.as_ref(),
+            &event,
+            Some(&state.ws_metrics),
+        )
+        .await;
+
+    Ok(message.channel_id)
+}
+
+/// Supprime plusieurs messages d'un channel et broadcast MESSAGE_DELETE_BULK
+pub async fn handle_bulk_delete_messages(
+    state: &AppState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    payload: BulkDeleteMessagesPayload,
+) -> Result<Vec<Uuid>> {
+    let (channel, message_ids) = messages::bulk_delete_messages(
+        &state.server_repo,
+        &state.channel_repo,
+        &state.message_repo,
+        channel_id,
+        user_id,
+        payload,
+    )
+    .await?;
+
+    if message_ids.is_empty() {
+        return Ok(message_ids);
+    }
+
+    let event = ServerEvent::MessageDeleteBulk {
+        ids: message_ids.clone(),
+        channel_id,
+    };
+    let viewers = channel_viewers(state, channel.server_id, channel_id).await;
+    state
+        .ws_hub
+        .broadcast_to_channel_with_metrics(
+            channel.server_id,
+            channel_id,
+            viewers.as_ref(),
+            &event,
+            Some(&state.ws_metrics),
+        )
+        .await;
+
+    Ok(message_ids)
+}
+
+/// Ajoute une réaction et broadcast MESSAGE_REACTION_UPDATE
+pub async fn handle_add_reaction(
+    state: &AppState,
+    user_id: Uuid,
+    message_id: Uuid,
+    emoji: String,
+) -> Result<MessageWithUser> {
+    let message = messages::add_reaction(
+        &state.server_repo,
+        &state.channel_repo,
+        &state.message_repo,
+        message_id,
+        user_id,
+        MessageReactionPayload { emoji },
+    )
+    .await?;
+
+    broadcast_reaction_update(state, &message).await;
+    Ok(message)
+}
+
+/// Retire une réaction et broadcast MESSAGE_REACTION_UPDATE
+pub async fn handle_remove_reaction(
+    state: &AppState,
+    user_id: Uuid,
+    message_id: Uuid,
+    emoji: String,
+) -> Result<MessageWithUser> {
+    let message = messages::remove_reaction(
+        &state.server_repo,
+        &state.channel_repo,
+        &state.message_repo,
+        message_id,
+        user_id,
+        MessageReactionPayload { emoji },
+    )
+    .await?;
+
+    broadcast_reaction_update(state, &message).await;
+    Ok(message)
+}
+
+async fn broadcast_reaction_update(state: &AppState, message: &MessageWithUser) {
+    let event = ServerEvent::MessageReactionUpdate {
+        id: message.id,
+        channel_id: message.channel_id,
+        reactions: message.reactions.clone(),
     };
+    let viewers = channel_viewers(state, message.server_id, message.channel_id).await;
+    state
+        .ws_hub
+        .broadcast_to_channel_with_metrics(
+            message.server_id,
+            message.channel_id,
+            viewers.as_ref(),
+            &event,
+            Some(&state.ws_metrics),
+        )
+        .await;
+}
+
+/// Épingle ou désépingle un message de channel et broadcast CHANNEL_PINS_UPDATE
+pub async fn handle_set_message_pinned(
+    state: &AppState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+    pinned: bool,
+) -> Result<()> {
+    let (message, changed) = messages::set_pinned(
+        &state.server_repo,
+        &state.channel_repo,
+        &state.message_repo,
+        channel_id,
+        message_id,
+        user_id,
+        pinned,
+    )
+    .await?;
+    if !changed {
+        return Ok(());
+    }
 
+    let event = ServerEvent::ChannelPinsUpdate {
+        channel_id,
+        message_id,
+        pinned_at: message.pinned_at,
+    };
+    let viewers = channel_viewers(state, message.server_id, channel_id).await;
     state
         .ws_hub
-        .broadcast_to_channel_with_metrics(channel_id, &event, Some(&state.ws_metrics))
+        .broadcast_to_channel_with_metrics(
+            message.server_id,
+            channel_id,
+            viewers.as_ref(),
+            &event,
+            Some(&state.ws_metrics),
+        )
         .await;
 
     Ok(())
 }
+
+/// Épingle ou désépingle un message privé et prévient les deux participants
+pub async fn handle_set_direct_message_pinned(
+    state: &AppState,
+    user_id: Uuid,
+    dm_id: Uuid,
+    message_id: Uuid,
+    pinned: bool,
+) -> Result<()> {
+    let (message, changed) = dm::set_pinned(
+        &state.dm_repo,
+        &state.dm_message_repo,
+        dm_id,
+        message_id,
+        user_id,
+        pinned,
+    )
+    .await?;
+    if !changed {
+        return Ok(());
+    }
+
+    let event = ServerEvent::DirectMessagePinsUpdate {
+        dm_id,
+        message_id,
+        pinned_at: message.pinned_at,
+    };
+    broadcast_to_dm_participants(state, dm_id, &event).await
+}
+
+/// Envoie un message privé et le transmet aux deux participants
+/// Un envoi rejoué (même nonce) renvoie le message existant sans nouveau broadcast
+pub async fn handle_send_direct_message(
+    state: &AppState,
+    user_id: Uuid,
+    dm_id: Uuid,
+    payload: CreateDMMessagePayload,
+) -> Result<DirectMessageItemResponse> {
+    let (response, created) = dm::create_message(
+        &state.dm_repo,
+        &state.dm_message_repo,
+        &state.user_repo,
+        dm_id,
+        user_id,
+        payload,
+    )
+    .await?;
+
+    if !created {
+        return Ok(response);
+    }
+
+    let event = ServerEvent::DirectMessageCreate {
+        id: response.id,
+        dm_id: response.dm_id,
+        author_id: response.author_id,
+        username: response.username.clone(),
+        content: response.content.clone(),
+        created_at: response.created_at,
+        edited_at: response.edited_at,
+        reactions: response.reactions.clone(),
+        nonce: response.nonce.clone(),
+        reply_to: response.reply_to.clone(),
+    };
+
+    broadcast_to_dm_participants(state, dm_id, &event).await?;
+
+    Ok(response)
+}
+
+/// Envoie un événement aux deux participants d'une conversation privée
+pub async fn broadcast_to_dm_participants(
+    state: &AppState,
+    dm_id: Uuid,
+    event: &ServerEvent,
+) -> Result<()> {
+    let Some((user1_id, user2_id)) = state.dm_repo.get_participants(dm_id).await? else {
+        return Ok(());
+    };
+
+    let participants = HashSet::from([user1_id, user2_id]);
+    state.ws_hub.send_to_users(&participants, event).await;
+
+    Ok(())
+}
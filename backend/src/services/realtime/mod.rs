@@ -1,10 +1,40 @@
 //! Service realtime : logique métier pour les événements WebSocket
 //! Séparé du transport (web/ws) pour respecter la séparation des responsabilités
 
+pub mod channels;
 pub mod messaging;
 pub mod presence;
+pub mod roles;
+pub mod servers;
+pub mod threads;
 pub mod typing;
+pub mod voice;
 
-pub use messaging::handle_send_message;
+pub use channels::{
+    handle_create_channel, handle_delete_channel, handle_delete_overwrite, handle_set_overwrite,
+    handle_update_channel, handle_update_channel_positions,
+};
+pub use messaging::{
+    broadcast_to_dm_participants, handle_add_reaction, handle_bulk_delete_messages,
+    handle_delete_message, handle_edit_message, handle_message_created, handle_remove_reaction,
+    handle_send_direct_message, handle_send_message, handle_set_direct_message_pinned,
+    handle_set_message_pinned,
+};
 pub use presence::{handle_presence_update, handle_user_offline, handle_user_online};
-pub use typing::{handle_typing_start, handle_typing_stop};
+pub use roles::{
+    handle_create_role, handle_delete_role, handle_set_member_role, handle_update_role,
+};
+pub use servers::{
+    handle_ban_member, handle_create_server, handle_delete_server, handle_join_server,
+    handle_join_server_with_code, handle_kick_member, handle_leave_server, handle_subscribe_server,
+    handle_timeout_member, handle_transfer_ownership, handle_unban_member,
+    handle_update_member_profile, handle_update_member_role, handle_update_server,
+    subscribe_user_servers,
+};
+pub use threads::{handle_create_thread, handle_update_thread};
+pub use typing::{
+    handle_dm_typing_start, handle_dm_typing_stop, handle_typing_start, handle_typing_stop,
+};
+pub use voice::{
+    handle_voice_disconnect, handle_voice_join, handle_voice_leave, handle_voice_signal,
+};
@@ -45,9 +45,7 @@ async fn broadcast_presence_to_shared_servers(state: &AppState, user_id: Uuid, s
         }
     }
 
-    for recipient_id in recipients {
-        state.ws_hub.send_to_user(recipient_id, &event).await;
-    }
+    state.ws_hub.send_to_users(&recipients, &event).await;
 }
 
 /// Marque un utilisateur comme en ligne lors de la connexion WebSocket
@@ -0,0 +1,126 @@
+//! Événements temps réel liés aux rôles (CRUD et attribution aux membres)
+
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::{CreateRolePayload, Role, UpdateRolePayload};
+use crate::services::realtime::servers::broadcast_to_server_members;
+use crate::services::roles;
+use crate::web::ws::protocol::ServerEvent;
+use crate::AppState;
+
+/// MEMBER_UPDATE avec le rang et les rôles attribués actuels du membre
+pub async fn announce_member_update(state: &AppState, server_id: Uuid, user_id: Uuid) {
+    let member = match state.server_repo.find_member(server_id, user_id).await {
+        Ok(Some(member)) => member,
+        Ok(None) => return,
+        Err(err) => {
+            tracing::warn!("[Realtime] Failed to load member {}: {}", user_id, err);
+            return;
+        }
+    };
+
+    let roles = match roles::assigned_role_ids(&state.server_repo, server_id, user_id).await {
+        Ok(roles) => roles,
+        Err(err) => {
+            tracing::warn!("[Realtime] Failed to load roles of {}: {}", user_id, err);
+            return;
+        }
+    };
+
+    let event = ServerEvent::MemberUpdate {
+        server_id,
+        user_id,
+        role: member.role,
+        roles,
+        communication_disabled_until: member.communication_disabled_until,
+        nickname: member.nickname.clone(),
+        server_avatar_url: member.avatar_url.clone(),
+    };
+    broadcast_to_server_members(state, server_id, &event).await;
+}
+
+/// Crée un rôle et broadcast ROLE_CREATE
+pub async fn handle_create_role(
+    state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    payload: CreateRolePayload,
+) -> Result<Role> {
+    let role = roles::create_role(&state.server_repo, server_id, user_id, payload).await?;
+
+    let event = ServerEvent::RoleCreate { role: role.clone() };
+    broadcast_to_server_members(state, server_id, &event).await;
+
+    Ok(role)
+}
+
+/// Modifie un rôle et broadcast ROLE_UPDATE
+pub async fn handle_update_role(
+    state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    role_id: Uuid,
+    payload: UpdateRolePayload,
+) -> Result<Role> {
+    let role = roles::update_role(&state.server_repo, server_id, role_id, user_id, payload).await?;
+
+    let event = ServerEvent::RoleUpdate { role: role.clone() };
+    broadcast_to_server_members(state, server_id, &event).await;
+
+    Ok(role)
+}
+
+/// Supprime un rôle et broadcast ROLE_DELETE
+pub async fn handle_delete_role(
+    state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    role_id: Uuid,
+) -> Result<()> {
+    roles::delete_role(&state.server_repo, server_id, role_id, user_id).await?;
+
+    let event = ServerEvent::RoleDelete { server_id, role_id };
+    broadcast_to_server_members(state, server_id, &event).await;
+
+    Ok(())
+}
+
+/// Attribue ou retire un rôle à un membre et broadcast MEMBER_UPDATE
+pub async fn handle_set_member_role(
+    state: &AppState,
+    requester_id: Uuid,
+    server_id: Uuid,
+    target_user_id: Uuid,
+    role_id: Uuid,
+    assign: bool,
+) -> Result<Vec<Uuid>> {
+    let roles = roles::set_member_role(
+        &state.server_repo,
+        server_id,
+        target_user_id,
+        role_id,
+        requester_id,
+        assign,
+    )
+    .await?;
+
+    let member = state
+        .server_repo
+        .find_member(server_id, target_user_id)
+        .await?
+        .ok_or(Error::UserNotFound)?;
+
+    let event = ServerEvent::MemberUpdate {
+        server_id,
+        user_id: target_user_id,
+        role: member.role,
+        roles: roles.clone(),
+        communication_disabled_until: member.communication_disabled_until,
+        nickname: member.nickname.clone(),
+        server_avatar_url: member.avatar_url.clone(),
+    };
+    broadcast_to_server_members(state, server_id, &event).await;
+
+    Ok(roles)
+}
@@ -0,0 +1,364 @@
+//! Événements temps réel liés aux serveurs : subscriptions, membres, bans
+//! Un membre reçoit les événements de tous les channels de ses serveurs sans
+//! SUBSCRIBE par channel
+
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::{
+    BanMemberPayload, CreateServerPayload, JoinServerWithCodePayload, MemberRole, Server,
+    ServerBan, ServerMember, TimeoutMemberPayload, TransferOwnershipPayload,
+    UpdateMemberProfilePayload, UpdateServerPayload,
+};
+use crate::services::realtime::roles::announce_member_update;
+use crate::services::{invites, servers};
+use crate::web::ws::hub::ConnectionId;
+use crate::web::ws::protocol::ServerEvent;
+use crate::AppState;
+
+/// Abonne une connexion à tous les serveurs de l'utilisateur (après IDENTIFY)
+/// Retourne le nombre de serveurs abonnés
+pub async fn subscribe_user_servers(
+    state: &AppState,
+    conn_id: ConnectionId,
+    user_id: Uuid,
+) -> Result<usize> {
+    // list_by_user ne retourne que les serveurs dont l'utilisateur est membre
+    let servers = state.server_repo.list_by_user(user_id).await?;
+
+    for server in &servers {
+        state.ws_hub.subscribe_server(conn_id, server.id).await;
+    }
+
+    Ok(servers.len())
+}
+
+/// Abonne une connexion à un serveur (op SUBSCRIBE_SERVER), membres uniquement
+pub async fn handle_subscribe_server(
+    state: &AppState,
+    conn_id: ConnectionId,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<()> {
+    state
+        .server_repo
+        .find_member(server_id, user_id)
+        .await?
+        .ok_or(Error::ServerForbidden)?;
+
+    state.ws_hub.subscribe_server(conn_id, server_id).await;
+    Ok(())
+}
+
+/// Envoie un événement à toutes les connexions des membres d'un serveur
+pub async fn broadcast_to_server_members(state: &AppState, server_id: Uuid, event: &ServerEvent) {
+    let members = match state.server_repo.list_members(server_id).await {
+        Ok(members) => members,
+        Err(err) => {
+            tracing::warn!(
+                "[Realtime] Failed to list members for server {}: {}",
+                server_id,
+                err
+            );
+            return;
+        }
+    };
+
+    let user_ids = members.into_iter().map(|member| member.user_id).collect();
+    state.ws_hub.send_to_users(&user_ids, event).await;
+}
+
+fn server_update_event(server: &Server) -> ServerEvent {
+    ServerEvent::ServerUpdate {
+        id: server.id,
+        name: server.name.clone(),
+        owner_id: server.owner_id,
+        updated_at: server.updated_at,
+    }
+}
+
+/// Nouveau membre : abonne ses connexions ouvertes et annonce MEMBER_ADD
+async fn announce_member_add(state: &AppState, member: &ServerMember) {
+    state
+        .ws_hub
+        .subscribe_user_to_server(member.user_id, member.server_id)
+        .await;
+
+    let user = match state.user_repo.find_by_id(member.user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return,
+        Err(err) => {
+            tracing::warn!("[Realtime] Failed to load user {}: {}", member.user_id, err);
+            return;
+        }
+    };
+
+    let event = ServerEvent::MemberAdd {
+        server_id: member.server_id,
+        user_id: member.user_id,
+        role: member.role.clone(),
+        joined_at: member.joined_at,
+        username: user.username,
+        avatar_url: user.avatar_url,
+    };
+    broadcast_to_server_members(state, member.server_id, &event).await;
+}
+
+/// Membre retiré : annonce MEMBER_REMOVE (à lui aussi) puis désabonne ses connexions
+async fn announce_member_remove(state: &AppState, server_id: Uuid, user_id: Uuid) {
+    let event = ServerEvent::MemberRemove { server_id, user_id };
+    broadcast_to_server_members(state, server_id, &event).await;
+    state.ws_hub.send_to_user(user_id, &event).await;
+
+    state
+        .ws_hub
+        .unsubscribe_user_from_server(user_id, server_id)
+        .await;
+}
+
+/// Crée un serveur et abonne les connexions du propriétaire
+pub async fn handle_create_server(
+    state: &AppState,
+    user_id: Uuid,
+    payload: CreateServerPayload,
+) -> Result<Server> {
+    let server =
+        servers::create_server(&state.server_repo, &state.user_repo, user_id, payload).await?;
+
+    state
+        .ws_hub
+        .subscribe_user_to_server(user_id, server.id)
+        .await;
+
+    Ok(server)
+}
+
+/// Modifie un serveur et broadcast SERVER_UPDATE aux membres
+pub async fn handle_update_server(
+    state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    payload: UpdateServerPayload,
+) -> Result<Server> {
+    let server = servers::update_server(&state.server_repo, server_id, user_id, payload).await?;
+
+    broadcast_to_server_members(state, server_id, &server_update_event(&server)).await;
+    Ok(server)
+}
+
+/// Supprime un serveur et envoie SERVER_DELETE aux anciens membres
+pub async fn handle_delete_server(state: &AppState, user_id: Uuid, server_id: Uuid) -> Result<()> {
+    // Les membres disparaissent avec le serveur : les lister avant la suppression
+    let members = state.server_repo.list_members(server_id).await?;
+
+    servers::delete_server(&state.server_repo, server_id, user_id).await?;
+
+    let event = ServerEvent::ServerDelete { id: server_id };
+    let user_ids = members.into_iter().map(|member| member.user_id).collect();
+    state.ws_hub.send_to_users(&user_ids, &event).await;
+    state.ws_hub.remove_server(server_id).await;
+
+    Ok(())
+}
+
+/// Rejoint un serveur et broadcast MEMBER_ADD
+pub async fn handle_join_server(
+    state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<ServerMember> {
+    let member = servers::join_server(&state.server_repo, server_id, user_id).await?;
+
+    announce_member_add(state, &member).await;
+    Ok(member)
+}
+
+/// Rejoint un serveur via un code d'invitation et broadcast MEMBER_ADD
+pub async fn handle_join_server_with_code(
+    state: &AppState,
+    user_id: Uuid,
+    payload: JoinServerWithCodePayload,
+) -> Result<Uuid> {
+    let server_id =
+        invites::join_server_with_code(&state.invite_repo, &state.server_repo, payload, user_id)
+            .await?;
+
+    if let Some(member) = state.server_repo.find_member(server_id, user_id).await? {
+        announce_member_add(state, &member).await;
+    }
+
+    Ok(server_id)
+}
+
+/// Quitte un serveur et broadcast MEMBER_REMOVE
+pub async fn handle_leave_server(state: &AppState, user_id: Uuid, server_id: Uuid) -> Result<()> {
+    servers::leave_server(&state.server_repo, server_id, user_id).await?;
+
+    announce_member_remove(state, server_id, user_id).await;
+    Ok(())
+}
+
+/// Exclut un membre et broadcast MEMBER_REMOVE
+pub async fn handle_kick_member(
+    state: &AppState,
+    requester_id: Uuid,
+    server_id: Uuid,
+    target_user_id: Uuid,
+) -> Result<()> {
+    servers::kick_member(&state.server_repo, server_id, target_user_id, requester_id).await?;
+
+    announce_member_remove(state, server_id, target_user_id).await;
+    Ok(())
+}
+
+/// Applique ou lève un timeout et broadcast MEMBER_UPDATE
+pub async fn handle_timeout_member(
+    state: &AppState,
+    requester_id: Uuid,
+    server_id: Uuid,
+    target_user_id: Uuid,
+    payload: TimeoutMemberPayload,
+) -> Result<ServerMember> {
+    let member = servers::timeout_member(
+        &state.server_repo,
+        server_id,
+        target_user_id,
+        payload,
+        requester_id,
+    )
+    .await?;
+
+    announce_member_update(state, server_id, target_user_id).await;
+    Ok(member)
+}
+
+/// Modifie le pseudo / l'avatar de serveur d'un membre et broadcast MEMBER_UPDATE
+pub async fn handle_update_member_profile(
+    state: &AppState,
+    requester_id: Uuid,
+    server_id: Uuid,
+    target_user_id: Uuid,
+    payload: UpdateMemberProfilePayload,
+) -> Result<ServerMember> {
+    let member = servers::update_member_profile(
+        &state.server_repo,
+        server_id,
+        target_user_id,
+        payload,
+        requester_id,
+    )
+    .await?;
+
+    announce_member_update(state, server_id, target_user_id).await;
+    Ok(member)
+}
+
+/// Bannit un membre et broadcast MEMBER_REMOVE puis BAN_ADD
+pub async fn handle_ban_member(
+    state: &AppState,
+    requester_id: Uuid,
+    server_id: Uuid,
+    target_user_id: Uuid,
+    payload: BanMemberPayload,
+) -> Result<ServerBan> {
+    let ban = servers::ban_member(
+        &state.server_repo,
+        server_id,
+        target_user_id,
+        payload,
+        requester_id,
+    )
+    .await?;
+
+    announce_member_remove(state, server_id, target_user_id).await;
+
+    let event = ServerEvent::BanAdd {
+        server_id: ban.server_id,
+        user_id: ban.user_id,
+        banned_by: ban.banned_by,
+        reason: ban.reason.clone(),
+        expires_at: ban.expires_at,
+        banned_at: ban.banned_at,
+    };
+    broadcast_to_server_members(state, server_id, &event).await;
+
+    Ok(ban)
+}
+
+/// Lève un ban et broadcast BAN_REMOVE
+pub async fn handle_unban_member(
+    state: &AppState,
+    requester_id: Uuid,
+    server_id: Uuid,
+    target_user_id: Uuid,
+) -> Result<()> {
+    servers::unban_member(&state.server_repo, server_id, target_user_id, requester_id).await?;
+
+    let event = ServerEvent::BanRemove {
+        server_id,
+        user_id: target_user_id,
+    };
+    broadcast_to_server_members(state, server_id, &event).await;
+    Ok(())
+}
+
+/// Lève les bans expirés et broadcast BAN_REMOVE (tâche périodique)
+pub async fn sweep_expired_bans(state: &AppState) {
+    let expired = match servers::sweep_expired_bans(&state.server_repo).await {
+        Ok(expired) => expired,
+        Err(err) => {
+            tracing::warn!("[Realtime] Failed to sweep expired bans: {}", err);
+            return;
+        }
+    };
+
+    for ban in expired {
+        let event = ServerEvent::BanRemove {
+            server_id: ban.server_id,
+            user_id: ban.user_id,
+        };
+        broadcast_to_server_members(state, ban.server_id, &event).await;
+    }
+}
+
+/// Change le rôle d'un membre et broadcast MEMBER_UPDATE
+pub async fn handle_update_member_role(
+    state: &AppState,
+    requester_id: Uuid,
+    server_id: Uuid,
+    target_user_id: Uuid,
+    role: MemberRole,
+) -> Result<ServerMember> {
+    let member = servers::update_member_role(
+        &state.server_repo,
+        server_id,
+        target_user_id,
+        role,
+        requester_id,
+    )
+    .await?;
+
+    announce_member_update(state, server_id, member.user_id).await;
+
+    Ok(member)
+}
+
+/// Transfère la propriété et broadcast SERVER_UPDATE + MEMBER_UPDATE des deux membres
+pub async fn handle_transfer_ownership(
+    state: &AppState,
+    requester_id: Uuid,
+    server_id: Uuid,
+    payload: TransferOwnershipPayload,
+) -> Result<Server> {
+    let new_owner_id = payload.new_owner_id;
+    let server =
+        servers::transfer_ownership(&state.server_repo, server_id, payload, requester_id).await?;
+
+    broadcast_to_server_members(state, server_id, &server_update_event(&server)).await;
+
+    for user_id in [requester_id, new_owner_id] {
+        announce_member_update(state, server_id, user_id).await;
+    }
+
+    Ok(server)
+}
@@ -0,0 +1,115 @@
+//! Événements temps réel des threads (création, modification, archivage automatique)
+//! Ils partent aux membres qui voient le channel d'origine
+
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::models::{CreateThreadPayload, Thread, UpdateThreadPayload};
+use crate::services::realtime::channels::{broadcast_to_viewers, channel_viewers};
+use crate::services::threads;
+use crate::web::ws::protocol::ServerEvent;
+use crate::AppState;
+
+async fn broadcast_thread_event(state: &AppState, thread: &Thread, event: &ServerEvent) {
+    let viewers = channel_viewers(state, thread.server_id, thread.parent_id).await;
+    broadcast_to_viewers(state, thread.server_id, viewers.as_ref(), event).await;
+}
+
+/// Ouvre un thread depuis un message et broadcast THREAD_CREATE
+pub async fn handle_create_thread(
+    state: &AppState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+    payload: CreateThreadPayload,
+) -> Result<Thread> {
+    let thread = threads::create_thread(
+        &state.server_repo,
+        &state.channel_repo,
+        &state.message_repo,
+        channel_id,
+        message_id,
+        user_id,
+        payload,
+    )
+    .await?;
+
+    let event = ServerEvent::ThreadCreate {
+        thread: thread.clone(),
+    };
+    broadcast_thread_event(state, &thread, &event).await;
+
+    Ok(thread)
+}
+
+/// Modifie un thread et broadcast THREAD_UPDATE
+pub async fn handle_update_thread(
+    state: &AppState,
+    user_id: Uuid,
+    thread_id: Uuid,
+    payload: UpdateThreadPayload,
+) -> Result<Thread> {
+    let thread = threads::update_thread(
+        &state.server_repo,
+        &state.channel_repo,
+        thread_id,
+        user_id,
+        payload,
+    )
+    .await?;
+
+    let event = ServerEvent::ThreadUpdate {
+        thread: thread.clone(),
+    };
+    broadcast_thread_event(state, &thread, &event).await;
+
+    Ok(thread)
+}
+
+/// Activité d'un thread après un nouveau message ; THREAD_UPDATE s'il est désarchivé
+/// Un échec n'annule pas l'envoi du message
+pub async fn handle_thread_activity(state: &AppState, channel_id: Uuid, author_id: Uuid) {
+    let reopened = match threads::record_activity(
+        &state.channel_repo,
+        &state.message_repo,
+        channel_id,
+        author_id,
+    )
+    .await
+    {
+        Ok(reopened) => reopened,
+        Err(err) => {
+            tracing::warn!(
+                "[Realtime] Failed to record activity of thread {}: {}",
+                channel_id,
+                err
+            );
+            return;
+        }
+    };
+
+    if let Some(thread) = reopened {
+        let event = ServerEvent::ThreadUpdate {
+            thread: thread.clone(),
+        };
+        broadcast_thread_event(state, &thread, &event).await;
+    }
+}
+
+/// Archive les threads inactifs (émet THREAD_UPDATE)
+pub async fn sweep_inactive_threads(state: &AppState) {
+    let archived = match state.channel_repo.archive_inactive_threads().await {
+        Ok(archived) => archived,
+        Err(err) => {
+            tracing::warn!("[Realtime] Failed to archive inactive threads: {}", err);
+            return;
+        }
+    };
+
+    for thread in archived {
+        let event = ServerEvent::ThreadUpdate {
+            thread: thread.clone(),
+        };
+        broadcast_thread_event(state, &thread, &event).await;
+    }
+}
@@ -1,4 +1,5 @@
 //! Gestion des événements "typing" (quelqu'un écrit...)
+//! Channels de serveur et conversations privées
 
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -7,12 +8,21 @@ use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::error::{Error, Result};
+use crate::services::channels;
+use crate::services::realtime::channels::channel_viewers;
 use crate::web::ws::protocol::ServerEvent;
 use crate::AppState;
 
-/// Cache des événements typing (user_id + channel_id -> timestamp)
+/// Cible d'un indicateur de frappe, avec de quoi router le TYPING_STOP
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TypingTarget {
+    Channel { server_id: Uuid, channel_id: Uuid },
+    Dm { dm_id: Uuid, recipient_id: Uuid },
+}
+
+/// Cache des événements typing (user_id + cible -> timestamp)
 /// Expire automatiquement après 3 secondes
-type TypingCache = Arc<Mutex<HashMap<(Uuid, Uuid), Instant>>>;
+type TypingCache = Arc<Mutex<HashMap<(Uuid, TypingTarget), Instant>>>;
 
 lazy_static::lazy_static! {
     static ref TYPING_CACHE: TypingCache = Arc::new(Mutex::new(HashMap::new()));
@@ -22,82 +32,182 @@ const TYPING_TIMEOUT: Duration = Duration::from_secs(3);
 
 /// Traite un événement "typing start"
 pub async fn handle_typing_start(state: &AppState, user_id: Uuid, channel_id: Uuid) -> Result<()> {
-    let channel = state
-        .channel_repo
-        .find_by_id(channel_id)
-        .await?
-        .ok_or(Error::ChannelNotFound)?;
+    let channel =
+        channels::get_channel(&state.server_repo, &state.channel_repo, channel_id, user_id).await?;
+    channels::ensure_accepts_messages(&channel)?;
+    channels::ensure_channel_permission(
+        &state.server_repo,
+        &state.channel_repo,
+        &channel,
+        user_id,
+        channels::send_permissions(&channel),
+        Error::ChannelForbidden,
+    )
+    .await?;
+
+    let target = TypingTarget::Channel {
+        server_id: channel.server_id,
+        channel_id,
+    };
+    start_typing(state, user_id, target).await;
+
+    Ok(())
+}
+
+/// Traite un événement "typing stop"
+pub async fn handle_typing_stop(state: &AppState, user_id: Uuid, channel_id: Uuid) -> Result<()> {
+    let channel =
+        channels::get_channel(&state.server_repo, &state.channel_repo, channel_id, user_id).await?;
+
+    let target = TypingTarget::Channel {
+        server_id: channel.server_id,
+        channel_id,
+    };
+    stop_typing(state, user_id, target).await;
+
+    Ok(())
+}
 
-    state
-        .server_repo
-        .find_member(channel.server_id, user_id)
+/// Traite un "typing start" dans une conversation privée
+pub async fn handle_dm_typing_start(state: &AppState, user_id: Uuid, dm_id: Uuid) -> Result<()> {
+    let target = dm_target(state, user_id, dm_id).await?;
+    start_typing(state, user_id, target).await;
+    Ok(())
+}
+
+/// Traite un "typing stop" dans une conversation privée
+pub async fn handle_dm_typing_stop(state: &AppState, user_id: Uuid, dm_id: Uuid) -> Result<()> {
+    let target = dm_target(state, user_id, dm_id).await?;
+    stop_typing(state, user_id, target).await;
+    Ok(())
+}
+
+/// Vérifie l'accès à la conversation et retrouve l'autre participant
+async fn dm_target(state: &AppState, user_id: Uuid, dm_id: Uuid) -> Result<TypingTarget> {
+    if !state.dm_repo.user_has_access(dm_id, user_id).await? {
+        return Err(Error::MessageForbidden);
+    }
+
+    let (user1_id, user2_id) = state
+        .dm_repo
+        .get_participants(dm_id)
         .await?
-        .ok_or(Error::ChannelForbidden)?;
+        .ok_or(Error::MessageForbidden)?;
+    let recipient_id = if user1_id == user_id {
+        user2_id
+    } else {
+        user1_id
+    };
+
+    Ok(TypingTarget::Dm {
+        dm_id,
+        recipient_id,
+    })
+}
 
-    // Récupérer le username
-    let username = match state.user_repo.get_username(user_id).await {
-        Ok(Some(name)) => name,
-        _ => return Ok(()), // User not found, ignore
+async fn start_typing(state: &AppState, user_id: Uuid, target: TypingTarget) {
+    // Récupérer le username (et le pseudo de serveur pour un channel)
+    let (username, nickname) = match target {
+        TypingTarget::Channel { server_id, .. } => {
+            match state
+                .server_repo
+                .get_member_profile(server_id, user_id)
+                .await
+            {
+                Ok(Some(profile)) => (profile.username, profile.nickname),
+                _ => return, // User not found, ignore
+            }
+        }
+        TypingTarget::Dm { .. } => match state.user_repo.get_username(user_id).await {
+            Ok(Some(name)) => (name, None),
+            _ => return, // User not found, ignore
+        },
     };
 
     // Mettre à jour le cache
     let mut cache = TYPING_CACHE.lock().await;
-    let key = (user_id, channel_id);
-    let now = Instant::now();
 
     // Vérifier si c'est un nouveau typing (pas déjà présent)
-    let is_new = cache.insert(key, now).is_none();
+    let is_new = cache.insert((user_id, target), Instant::now()).is_none();
+    drop(cache); // Libérer le lock avant l'await
 
     if is_new {
         // Nouveau typing, broadcaster l'événement
-        let event = ServerEvent::TypingStart {
-            channel_id,
-            user_id,
-            username: username.clone(),
+        let event = match target {
+            TypingTarget::Channel { channel_id, .. } => ServerEvent::TypingStart {
+                channel_id,
+                user_id,
+                username,
+                nickname,
+            },
+            TypingTarget::Dm { dm_id, .. } => ServerEvent::DirectMessageTypingStart {
+                dm_id,
+                user_id,
+                username,
+            },
         };
-
-        drop(cache); // Libérer le lock avant l'await
-        state.ws_hub.broadcast_to_channel(channel_id, &event).await;
+        dispatch(state, target, &event).await;
     }
-
-    Ok(())
 }
 
-/// Traite un événement "typing stop"
-pub async fn handle_typing_stop(state: &AppState, user_id: Uuid, channel_id: Uuid) -> Result<()> {
-    let channel = state
-        .channel_repo
-        .find_by_id(channel_id)
-        .await?
-        .ok_or(Error::ChannelNotFound)?;
-
-    state
-        .server_repo
-        .find_member(channel.server_id, user_id)
-        .await?
-        .ok_or(Error::ChannelForbidden)?;
-
-    let mut cache = TYPING_CACHE.lock().await;
-    let key = (user_id, channel_id);
+async fn stop_typing(state: &AppState, user_id: Uuid, target: TypingTarget) {
+    let removed = TYPING_CACHE
+        .lock()
+        .await
+        .remove(&(user_id, target))
+        .is_some();
 
-    if cache.remove(&key).is_some() {
+    if removed {
         // Broadcaster l'arrêt
-        let event = ServerEvent::TypingStop {
+        dispatch(state, target, &stop_event(user_id, target)).await;
+    }
+}
+
+fn stop_event(user_id: Uuid, target: TypingTarget) -> ServerEvent {
+    match target {
+        TypingTarget::Channel { channel_id, .. } => ServerEvent::TypingStop {
             channel_id,
             user_id,
-        };
-
-        drop(cache);
-        state.ws_hub.broadcast_to_channel(channel_id, &event).await;
+        },
+        TypingTarget::Dm { dm_id, .. } => ServerEvent::DirectMessageTypingStop { dm_id, user_id },
     }
+}
 
-    Ok(())
+/// Channel : abonnés du channel et du serveur qui le voient ; DM : l'autre participant uniquement
+async fn dispatch(state: &AppState, target: TypingTarget, event: &ServerEvent) {
+    match target {
+        TypingTarget::Channel {
+            server_id,
+            channel_id,
+        } => {
+            let viewers = channel_viewers(state, server_id, channel_id).await;
+            state
+                .ws_hub
+                .broadcast_to_channel_viewers(server_id, channel_id, viewers.as_ref(), event)
+                .await;
+        }
+        TypingTarget::Dm { recipient_id, .. } => {
+            state.ws_hub.send_to_user(recipient_id, event).await;
+        }
+    }
 }
 
-/// Nettoyage périodique du cache typing (expiration)
-pub async fn cleanup_typing_cache() {
+/// Nettoyage périodique du cache typing : les entrées expirées émettent TYPING_STOP
+pub async fn cleanup_typing_cache(state: &AppState) {
     let mut cache = TYPING_CACHE.lock().await;
     let now = Instant::now();
 
-    cache.retain(|_, timestamp| now.duration_since(*timestamp) < TYPING_TIMEOUT);
+    let expired: Vec<(Uuid, TypingTarget)> = cache
+        .iter()
+        .filter(|(_, timestamp)| now.duration_since(**timestamp) >= TYPING_TIMEOUT)
+        .map(|(key, _)| *key)
+        .collect();
+    for key in &expired {
+        cache.remove(key);
+    }
+    drop(cache);
+
+    for (user_id, target) in expired {
+        dispatch(state, target, &stop_event(user_id, target)).await;
+    }
 }
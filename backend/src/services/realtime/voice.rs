@@ -0,0 +1,167 @@
+//! Channels vocaux : qui est connecté où, et relais de la signalisation WebRTC
+//! Les médias restent en pair-à-pair, le serveur ne transporte que les offres/réponses/ICE
+//! Les états vocaux sont répliqués par le hub sur chaque instance, et la signalisation
+//! est relayée à l'instance qui porte la connexion vocale du destinataire
+
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::{ChannelKind, Permissions};
+use crate::services::channels;
+use crate::services::realtime::channels::{broadcast_to_viewers, channel_viewers};
+use crate::services::realtime::servers::broadcast_to_server_members;
+use crate::web::ws::hub::ConnectionId;
+use crate::web::ws::protocol::{ServerEvent, VoiceSignal};
+use crate::web::ws::voice::VoiceState;
+use crate::AppState;
+
+/// Taille maximale d'une trame de signalisation sérialisée (SDP compris)
+pub const MAX_SIGNAL_BYTES: usize = 16 * 1024;
+
+/// Rejoint un channel vocal (en quittant l'éventuel précédent)
+/// La connexion reçoit VOICE_READY avec les participants déjà présents
+pub async fn handle_voice_join(
+    state: &AppState,
+    conn_id: ConnectionId,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<()> {
+    let channel =
+        channels::get_channel(&state.server_repo, &state.channel_repo, channel_id, user_id).await?;
+    if channel.kind != ChannelKind::Voice {
+        return Err(Error::BadRequest {
+            message: "Channel is not a voice channel".to_string(),
+        });
+    }
+    channels::ensure_channel_permission(
+        &state.server_repo,
+        &state.channel_repo,
+        &channel,
+        user_id,
+        Permissions::SEND_MESSAGES,
+        Error::ChannelForbidden,
+    )
+    .await?;
+
+    let voice_state = VoiceState {
+        server_id: channel.server_id,
+        channel_id,
+        conn_id,
+    };
+    let (previous, participants) = state.ws_hub.voice_join(user_id, voice_state).await;
+
+    if let Some(previous) = previous.filter(|previous| previous.channel_id != channel_id) {
+        announce_voice_state(state, user_id, previous, None).await;
+    }
+    announce_voice_state(state, user_id, voice_state, Some(channel_id)).await;
+
+    let ready = ServerEvent::VoiceReady {
+        channel_id,
+        participants,
+    };
+    state.ws_hub.send_to_connection(conn_id, &ready).await;
+
+    Ok(())
+}
+
+/// Quitte le channel vocal courant (sans effet si l'utilisateur n'en a pas)
+pub async fn handle_voice_leave(state: &AppState, user_id: Uuid) {
+    if let Some(previous) = state.ws_hub.voice_leave(user_id).await {
+        announce_voice_state(state, user_id, previous, None).await;
+    }
+}
+
+/// Fin de connexion : libère la présence vocale portée par cette connexion
+pub async fn handle_voice_disconnect(state: &AppState, conn_id: ConnectionId) {
+    if let Some((user_id, previous)) = state.ws_hub.voice_leave_connection(conn_id).await {
+        announce_voice_state(state, user_id, previous, None).await;
+    }
+}
+
+/// Déconnecte tous les participants d'un channel vocal supprimé
+/// Le channel n'existe plus : les départs sont annoncés à tout le serveur
+pub async fn clear_voice_channel(state: &AppState, channel_id: Uuid) {
+    for (user_id, previous) in state.ws_hub.voice_clear_channel(channel_id).await {
+        let event = ServerEvent::VoiceStateUpdate {
+            server_id: previous.server_id,
+            user_id,
+            channel_id: None,
+        };
+        broadcast_to_server_members(state, previous.server_id, &event).await;
+    }
+}
+
+/// Relaie une trame de signalisation vers un autre participant du même channel
+pub async fn handle_voice_signal(
+    state: &AppState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    target_user_id: Uuid,
+    signal: VoiceSignal,
+) -> Result<()> {
+    let size = serde_json::to_vec(&signal)
+        .map(|bytes| bytes.len())
+        .unwrap_or(usize::MAX);
+    if size > MAX_SIGNAL_BYTES {
+        return Err(Error::BadRequest {
+            message: format!("Signal exceeds {} bytes", MAX_SIGNAL_BYTES),
+        });
+    }
+
+    let in_channel = |voice_state: Option<VoiceState>| {
+        voice_state.filter(|voice_state| voice_state.channel_id == channel_id)
+    };
+    if in_channel(state.ws_hub.voice_state_of(user_id).await).is_none() {
+        return Err(Error::ChannelForbidden);
+    }
+    let target =
+        in_channel(state.ws_hub.voice_state_of(target_user_id).await).ok_or(Error::BadRequest {
+            message: "Target user is not in this voice channel".to_string(),
+        })?;
+
+    let event = ServerEvent::VoiceSignal {
+        channel_id,
+        from_user_id: user_id,
+        signal,
+    };
+    // La connexion vocale du destinataire peut être portée par une autre instance
+    state
+        .ws_hub
+        .send_to_connection_anywhere(target.conn_id, &event)
+        .await;
+
+    Ok(())
+}
+
+/// Participants d'un channel vocal visible par le demandeur
+pub async fn list_voice_participants(
+    state: &AppState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<Vec<Uuid>> {
+    let channel =
+        channels::get_channel(&state.server_repo, &state.channel_repo, channel_id, user_id).await?;
+    if channel.kind != ChannelKind::Voice {
+        return Err(Error::BadRequest {
+            message: "Channel is not a voice channel".to_string(),
+        });
+    }
+
+    Ok(state.ws_hub.voice_participants(channel_id).await)
+}
+
+/// VOICE_STATE_UPDATE aux membres qui voient le channel concerné
+async fn announce_voice_state(
+    state: &AppState,
+    user_id: Uuid,
+    voice_state: VoiceState,
+    channel_id: Option<Uuid>,
+) {
+    let event = ServerEvent::VoiceStateUpdate {
+        server_id: voice_state.server_id,
+        user_id,
+        channel_id,
+    };
+    let viewers = channel_viewers(state, voice_state.server_id, voice_state.channel_id).await;
+    broadcast_to_viewers(state, voice_state.server_id, viewers.as_ref(), &event).await;
+}
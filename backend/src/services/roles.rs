@@ -0,0 +1,715 @@
+//! Rôles et permissions d'un serveur
+//! Toutes les vérifications d'autorisation passent par `compute_permissions`
+//! (et `compute_channel_permissions` pour les overwrites de channel)
+
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::{
+    AuditAction, ChannelOverwrite, CreateRolePayload, MemberRole, NewAuditLogEntry,
+    OverwriteTarget, Permissions, Role, Server, ServerMember, UpdateRolePayload,
+};
+use crate::repositories::ServerRepository;
+use crate::services::audit;
+
+/// Rôle attribué aux membres promus via l'ancien endpoint `PUT /members/{userId}`
+pub const LEGACY_ADMIN_ROLE_NAME: &str = "Admin";
+
+const MAX_ROLE_NAME_LENGTH: usize = 100;
+const MAX_ROLE_COLOR: i32 = 0xFF_FF_FF;
+
+/// Permissions effectives d'un membre : union de ses rôles (dont @everyone).
+/// Le propriétaire et ADMINISTRATOR ont toutes les permissions.
+pub fn compute_permissions(server: &Server, member: &ServerMember, roles: &[Role]) -> Permissions {
+    if member.user_id == server.owner_id {
+        return Permissions::ALL;
+    }
+
+    let permissions = roles
+        .iter()
+        .fold(Permissions::NONE, |acc, role| acc | role.permissions);
+
+    if permissions.contains(Permissions::ADMINISTRATOR) {
+        Permissions::ALL
+    } else {
+        permissions
+    }
+}
+
+/// Permissions dans un channel : overwrite @everyone, puis ceux des rôles du membre, puis
+/// celui du membre. Sans VIEW_CHANNEL, le membre n'a aucune permission dans le channel.
+pub fn compute_channel_permissions(
+    base: Permissions,
+    user_id: Uuid,
+    roles: &[Role],
+    overwrites: &[ChannelOverwrite],
+) -> Permissions {
+    if base.contains(Permissions::ADMINISTRATOR) {
+        return Permissions::ALL;
+    }
+
+    let find = |target_type: OverwriteTarget, target_id: Uuid| {
+        overwrites
+            .iter()
+            .find(|ow| ow.target_type == target_type && ow.target_id == target_id)
+    };
+
+    let mut permissions = base;
+
+    let everyone = roles.iter().find(|role| role.is_default);
+    if let Some(ow) = everyone.and_then(|role| find(OverwriteTarget::Role, role.id)) {
+        permissions = permissions.apply_overwrite(ow.allow, ow.deny);
+    }
+
+    let (allow, deny) = roles
+        .iter()
+        .filter(|role| !role.is_default)
+        .filter_map(|role| find(OverwriteTarget::Role, role.id))
+        .fold(
+            (Permissions::NONE, Permissions::NONE),
+            |(allow, deny), ow| (allow | ow.allow, deny | ow.deny),
+        );
+    permissions = permissions.apply_overwrite(allow, deny);
+
+    if let Some(ow) = find(OverwriteTarget::Member, user_id) {
+        permissions = permissions.apply_overwrite(ow.allow, ow.deny);
+    }
+
+    if permissions.contains(Permissions::VIEW_CHANNEL) {
+        permissions
+    } else {
+        Permissions::NONE
+    }
+}
+
+/// Position hiérarchique d'un membre : son rôle le plus haut, le propriétaire au-dessus de tout
+pub fn top_position(server: &Server, member: &ServerMember, roles: &[Role]) -> i32 {
+    if member.user_id == server.owner_id {
+        return i32::MAX;
+    }
+
+    roles.iter().map(|role| role.position).max().unwrap_or(0)
+}
+
+/// Membre d'un serveur avec ses permissions effectives
+pub struct MemberPermissions {
+    pub server: Server,
+    pub member: ServerMember,
+    pub permissions: Permissions,
+    pub top_position: i32,
+    /// Rôles du membre, @everyone compris
+    pub roles: Vec<Role>,
+}
+
+impl MemberPermissions {
+    pub fn has(&self, permission: Permissions) -> bool {
+        self.permissions.contains(permission)
+    }
+
+    pub fn is_owner(&self) -> bool {
+        self.member.user_id == self.server.owner_id
+    }
+
+    /// Timeout en cours, sans effet sur le propriétaire et les administrateurs
+    pub fn is_timed_out(&self) -> bool {
+        !self.has(Permissions::ADMINISTRATOR) && self.member.is_timed_out()
+    }
+
+    /// Permissions du membre dans un channel du serveur (sans écriture ni réaction en timeout)
+    pub fn in_channel(&self, overwrites: &[ChannelOverwrite]) -> Permissions {
+        let permissions = compute_channel_permissions(
+            self.permissions,
+            self.member.user_id,
+            &self.roles,
+            overwrites,
+        );
+
+        if self.is_timed_out() {
+            permissions & !Permissions::COMMUNICATION
+        } else {
+            permissions
+        }
+    }
+
+    /// Un membre n'agit que sur les rôles et membres strictement en dessous de lui
+    pub fn outranks(&self, position: i32) -> bool {
+        self.is_owner() || self.top_position > position
+    }
+}
+
+/// Charge les permissions d'un membre, None s'il n'est pas membre du serveur
+pub async fn member_permissions(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<MemberPermissions>> {
+    let server = server_repo
+        .find_by_id(server_id)
+        .await?
+        .ok_or(Error::ServerNotFound)?;
+
+    let Some(member) = server_repo.find_member(server_id, user_id).await? else {
+        return Ok(None);
+    };
+
+    let roles = server_repo.list_member_roles(server_id, user_id).await?;
+    let permissions = compute_permissions(&server, &member, &roles);
+    let top_position = top_position(&server, &member, &roles);
+
+    Ok(Some(MemberPermissions {
+        server,
+        member,
+        permissions,
+        top_position,
+        roles,
+    }))
+}
+
+/// Exige une permission, `forbidden` sinon (non-membre compris)
+pub async fn require_permission(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    user_id: Uuid,
+    permission: Permissions,
+    forbidden: Error,
+) -> Result<MemberPermissions> {
+    match member_permissions(server_repo, server_id, user_id).await? {
+        Some(requester) if requester.has(permission) => Ok(requester),
+        _ => Err(forbidden),
+    }
+}
+
+fn validate_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_ROLE_NAME_LENGTH {
+        return Err(Error::BadRequest {
+            message: format!("Role name must be 1-{} characters", MAX_ROLE_NAME_LENGTH),
+        });
+    }
+    Ok(name.to_string())
+}
+
+fn validate_color(color: i32) -> Result<i32> {
+    if !(0..=MAX_ROLE_COLOR).contains(&color) {
+        return Err(Error::BadRequest {
+            message: "Role color must be an RGB value".to_string(),
+        });
+    }
+    Ok(color)
+}
+
+/// Bits connus uniquement, et jamais plus que ce que le demandeur possède
+fn validate_permissions(requester: &MemberPermissions, bits: i64) -> Result<Permissions> {
+    let permissions = Permissions::from_bits(bits).ok_or_else(|| Error::BadRequest {
+        message: "Unknown permission bits".to_string(),
+    })?;
+
+    if !requester.permissions.contains(permissions) {
+        return Err(Error::ServerForbidden);
+    }
+    Ok(permissions)
+}
+
+/// Position d'un rôle non défaut : au-dessus de @everyone, en dessous du demandeur
+fn validate_position(requester: &MemberPermissions, position: i32) -> Result<i32> {
+    if position < 1 {
+        return Err(Error::BadRequest {
+            message: "Role position must be at least 1".to_string(),
+        });
+    }
+    if !requester.outranks(position) {
+        return Err(Error::ServerForbidden);
+    }
+    Ok(position)
+}
+
+/// Rôle modifiable par le demandeur (existe dans le serveur et se trouve sous lui)
+pub async fn manageable_role(
+    server_repo: &ServerRepository,
+    requester: &MemberPermissions,
+    role_id: Uuid,
+) -> Result<Role> {
+    let role = server_repo
+        .find_role(requester.server.id, role_id)
+        .await?
+        .ok_or(Error::RoleNotFound)?;
+
+    if !role.is_default && !requester.outranks(role.position) {
+        return Err(Error::ServerForbidden);
+    }
+    Ok(role)
+}
+
+/// Crée le rôle @everyone d'un nouveau serveur
+pub async fn create_default_role(server_repo: &ServerRepository, server_id: Uuid) -> Result<Role> {
+    let role = server_repo
+        .create_role(
+            server_id,
+            "@everyone",
+            0,
+            0,
+            Permissions::DEFAULT_EVERYONE,
+            true,
+        )
+        .await?;
+    Ok(role)
+}
+
+pub async fn list_roles(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    requester_id: Uuid,
+) -> Result<Vec<Role>> {
+    member_permissions(server_repo, server_id, requester_id)
+        .await?
+        .ok_or(Error::ServerForbidden)?;
+
+    let roles = server_repo.list_roles(server_id).await?;
+    Ok(roles)
+}
+
+pub async fn create_role(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    requester_id: Uuid,
+    payload: CreateRolePayload,
+) -> Result<Role> {
+    let requester = require_permission(
+        server_repo,
+        server_id,
+        requester_id,
+        Permissions::MANAGE_ROLES,
+        Error::ServerForbidden,
+    )
+    .await?;
+
+    let name = validate_name(&payload.name)?;
+    let color = validate_color(payload.color.unwrap_or(0))?;
+    let permissions = validate_permissions(&requester, payload.permissions.unwrap_or(0))?;
+    let position = validate_position(&requester, payload.position.unwrap_or(1))?;
+
+    let role = server_repo
+        .create_role(server_id, &name, color, position, permissions, false)
+        .await?;
+
+    let entry = NewAuditLogEntry::new(server_id, requester_id, AuditAction::RoleCreate)
+        .target(role.id)
+        .after(&role);
+    audit::record(server_repo, entry).await;
+
+    Ok(role)
+}
+
+pub async fn update_role(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    role_id: Uuid,
+    requester_id: Uuid,
+    payload: UpdateRolePayload,
+) -> Result<Role> {
+    let requester = require_permission(
+        server_repo,
+        server_id,
+        requester_id,
+        Permissions::MANAGE_ROLES,
+        Error::ServerForbidden,
+    )
+    .await?;
+    let role = manageable_role(server_repo, &requester, role_id).await?;
+
+    // @everyone : seules ses permissions et sa couleur sont modifiables
+    if role.is_default && (payload.name.is_some() || payload.position.is_some()) {
+        return Err(Error::BadRequest {
+            message: "The default role cannot be renamed or moved".to_string(),
+        });
+    }
+
+    let name = payload.name.as_deref().map(validate_name).transpose()?;
+    let color = payload.color.map(validate_color).transpose()?;
+    let permissions = payload
+        .permissions
+        .map(|bits| validate_permissions(&requester, bits))
+        .transpose()?;
+    let position = payload
+        .position
+        .map(|position| validate_position(&requester, position))
+        .transpose()?;
+
+    let updated = server_repo
+        .update_role(role_id, name, color, position, permissions)
+        .await?
+        .ok_or(Error::RoleNotFound)?;
+
+    let entry = NewAuditLogEntry::new(server_id, requester_id, AuditAction::RoleUpdate)
+        .target(role_id)
+        .before(&role)
+        .after(&updated);
+    audit::record(server_repo, entry).await;
+
+    Ok(updated)
+}
+
+pub async fn delete_role(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    role_id: Uuid,
+    requester_id: Uuid,
+) -> Result<Role> {
+    let requester = require_permission(
+        server_repo,
+        server_id,
+        requester_id,
+        Permissions::MANAGE_ROLES,
+        Error::ServerForbidden,
+    )
+    .await?;
+    let role = manageable_role(server_repo, &requester, role_id).await?;
+
+    if role.is_default {
+        return Err(Error::BadRequest {
+            message: "The default role cannot be deleted".to_string(),
+        });
+    }
+
+    server_repo.delete_role(role_id).await?;
+
+    let entry = NewAuditLogEntry::new(server_id, requester_id, AuditAction::RoleDelete)
+        .target(role_id)
+        .before(&role);
+    audit::record(server_repo, entry).await;
+
+    Ok(role)
+}
+
+/// Attribue (`assign`) ou retire un rôle à un membre, retourne ses rôles attribués
+pub async fn set_member_role(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    target_user_id: Uuid,
+    role_id: Uuid,
+    requester_id: Uuid,
+    assign: bool,
+) -> Result<Vec<Uuid>> {
+    let requester = require_permission(
+        server_repo,
+        server_id,
+        requester_id,
+        Permissions::MANAGE_ROLES,
+        Error::ServerForbidden,
+    )
+    .await?;
+    let role = manageable_role(server_repo, &requester, role_id).await?;
+
+    if role.is_default {
+        return Err(Error::BadRequest {
+            message: "The default role applies to every member".to_string(),
+        });
+    }
+
+    server_repo
+        .find_member(server_id, target_user_id)
+        .await?
+        .ok_or(Error::UserNotFound)?;
+    let before = assigned_role_ids(server_repo, server_id, target_user_id).await?;
+
+    if assign {
+        server_repo
+            .add_member_role(server_id, target_user_id, role_id)
+            .await?;
+    } else {
+        server_repo
+            .remove_member_role(server_id, target_user_id, role_id)
+            .await?;
+    }
+
+    let after = assigned_role_ids(server_repo, server_id, target_user_id).await?;
+    if after != before {
+        let entry = NewAuditLogEntry::new(server_id, requester_id, AuditAction::MemberRoleUpdate)
+            .target(target_user_id)
+            .before(&before)
+            .after(&after);
+        audit::record(server_repo, entry).await;
+    }
+
+    Ok(after)
+}
+
+/// Rôles attribués à un membre (hors @everyone)
+pub async fn assigned_role_ids(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    user_id: Uuid,
+) -> Result<Vec<Uuid>> {
+    let roles = server_repo.list_member_roles(server_id, user_id).await?;
+    Ok(roles
+        .into_iter()
+        .filter(|role| !role.is_default)
+        .map(|role| role.id)
+        .collect())
+}
+
+/// Rang historique Admin/Member : synchronise l'attribution du rôle "Admin"
+pub async fn apply_legacy_rank(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    user_id: Uuid,
+    rank: &MemberRole,
+) -> Result<()> {
+    let existing = server_repo
+        .find_role_by_name(server_id, LEGACY_ADMIN_ROLE_NAME)
+        .await?;
+
+    match (rank, existing) {
+        (MemberRole::Admin, Some(role)) => {
+            server_repo
+                .add_member_role(server_id, user_id, role.id)
+                .await?
+        }
+        (MemberRole::Admin, None) => {
+            let role = server_repo
+                .create_role(
+                    server_id,
+                    LEGACY_ADMIN_ROLE_NAME,
+                    0,
+                    1,
+                    Permissions::LEGACY_ADMIN,
+                    false,
+                )
+                .await?;
+            server_repo
+                .add_member_role(server_id, user_id, role.id)
+                .await?;
+        }
+        (_, Some(role)) => {
+            server_repo
+                .remove_member_role(server_id, user_id, role.id)
+                .await?
+        }
+        (_, None) => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn server(owner_id: Uuid) -> Server {
+        Server {
+            id: Uuid::new_v4(),
+            name: "test".to_string(),
+            owner_id,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn member(server: &Server, user_id: Uuid) -> ServerMember {
+        ServerMember {
+            server_id: server.id,
+            user_id,
+            role: MemberRole::Member,
+            joined_at: Utc::now(),
+            communication_disabled_until: None,
+            nickname: None,
+            avatar_url: None,
+        }
+    }
+
+    fn role(server: &Server, position: i32, permissions: Permissions) -> Role {
+        Role {
+            id: Uuid::new_v4(),
+            server_id: server.id,
+            name: format!("role-{}", position),
+            color: 0,
+            position,
+            permissions,
+            is_default: position == 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn permissions_are_the_union_of_member_roles() {
+        let server = server(Uuid::new_v4());
+        let member = member(&server, Uuid::new_v4());
+        let roles = [
+            role(&server, 0, Permissions::CREATE_INVITE),
+            role(&server, 2, Permissions::KICK_MEMBERS),
+        ];
+
+        let permissions = compute_permissions(&server, &member, &roles);
+        assert!(permissions.contains(Permissions::CREATE_INVITE | Permissions::KICK_MEMBERS));
+        assert!(!permissions.contains(Permissions::BAN_MEMBERS));
+        assert_eq!(top_position(&server, &member, &roles), 2);
+    }
+
+    #[test]
+    fn owner_and_administrator_have_every_permission() {
+        let owner_id = Uuid::new_v4();
+        let server = server(owner_id);
+        let everyone = role(&server, 0, Permissions::NONE);
+
+        let owner = member(&server, owner_id);
+        assert_eq!(
+            compute_permissions(&server, &owner, std::slice::from_ref(&everyone)),
+            Permissions::ALL
+        );
+        assert_eq!(top_position(&server, &owner, &[]), i32::MAX);
+
+        let admin = member(&server, Uuid::new_v4());
+        let roles = [everyone, role(&server, 1, Permissions::ADMINISTRATOR)];
+        assert_eq!(
+            compute_permissions(&server, &admin, &roles),
+            Permissions::ALL
+        );
+    }
+
+    fn overwrite(
+        target_type: OverwriteTarget,
+        target_id: Uuid,
+        allow: Permissions,
+        deny: Permissions,
+    ) -> ChannelOverwrite {
+        ChannelOverwrite {
+            channel_id: Uuid::new_v4(),
+            target_type,
+            target_id,
+            allow,
+            deny,
+        }
+    }
+
+    #[test]
+    fn private_channel_is_visible_only_through_overwrites() {
+        let server = server(Uuid::new_v4());
+        let everyone = role(&server, 0, Permissions::DEFAULT_EVERYONE);
+        let staff = role(&server, 1, Permissions::NONE);
+        let overwrites = [
+            overwrite(
+                OverwriteTarget::Role,
+                everyone.id,
+                Permissions::NONE,
+                Permissions::VIEW_CHANNEL,
+            ),
+            overwrite(
+                OverwriteTarget::Role,
+                staff.id,
+                Permissions::VIEW_CHANNEL,
+                Permissions::NONE,
+            ),
+        ];
+
+        let user_id = Uuid::new_v4();
+        let base = Permissions::DEFAULT_EVERYONE;
+        let outsider = compute_channel_permissions(
+            base,
+            user_id,
+            std::slice::from_ref(&everyone),
+            &overwrites,
+        );
+        assert_eq!(outsider, Permissions::NONE);
+
+        let roles = [everyone, staff];
+        let insider = compute_channel_permissions(base, user_id, &roles, &overwrites);
+        assert_eq!(insider, Permissions::DEFAULT_EVERYONE);
+
+        // ADMINISTRATOR ignore les overwrites
+        let admin =
+            compute_channel_permissions(Permissions::ALL, user_id, &roles[..1], &overwrites);
+        assert_eq!(admin, Permissions::ALL);
+    }
+
+    #[test]
+    fn member_overwrite_wins_over_role_overwrites() {
+        let server = server(Uuid::new_v4());
+        let everyone = role(&server, 0, Permissions::DEFAULT_EVERYONE);
+        let speaker = role(&server, 1, Permissions::NONE);
+        let user_id = Uuid::new_v4();
+        let overwrites = [
+            overwrite(
+                OverwriteTarget::Role,
+                everyone.id,
+                Permissions::NONE,
+                Permissions::SEND_MESSAGES,
+            ),
+            overwrite(
+                OverwriteTarget::Role,
+                speaker.id,
+                Permissions::SEND_MESSAGES,
+                Permissions::NONE,
+            ),
+            overwrite(
+                OverwriteTarget::Member,
+                user_id,
+                Permissions::NONE,
+                Permissions::SEND_MESSAGES,
+            ),
+        ];
+        let roles = [everyone, speaker];
+
+        let muted = compute_channel_permissions(
+            Permissions::DEFAULT_EVERYONE,
+            user_id,
+            &roles,
+            &overwrites,
+        );
+        assert!(muted.contains(Permissions::VIEW_CHANNEL));
+        assert!(!muted.contains(Permissions::SEND_MESSAGES));
+
+        let other = compute_channel_permissions(
+            Permissions::DEFAULT_EVERYONE,
+            Uuid::new_v4(),
+            &roles,
+            &overwrites,
+        );
+        assert!(other.contains(Permissions::SEND_MESSAGES));
+    }
+
+    #[test]
+    fn timed_out_member_can_only_read() {
+        let server = server(Uuid::new_v4());
+        let everyone = role(&server, 0, Permissions::DEFAULT_EVERYONE);
+        let mut timed_out = MemberPermissions {
+            member: ServerMember {
+                communication_disabled_until: Some(Utc::now() + chrono::Duration::minutes(5)),
+                ..member(&server, Uuid::new_v4())
+            },
+            permissions: Permissions::DEFAULT_EVERYONE,
+            top_position: 0,
+            roles: vec![everyone],
+            server,
+        };
+
+        let permissions = timed_out.in_channel(&[]);
+        assert!(permissions.contains(Permissions::VIEW_CHANNEL));
+        assert!(!permissions.contains(Permissions::SEND_MESSAGES));
+        assert!(!permissions.contains(Permissions::ADD_REACTIONS));
+
+        // Les administrateurs ne sont pas concernés
+        timed_out.permissions = Permissions::ALL;
+        assert!(!timed_out.is_timed_out());
+        assert_eq!(timed_out.in_channel(&[]), Permissions::ALL);
+
+        // Timeout expiré
+        timed_out.permissions = Permissions::DEFAULT_EVERYONE;
+        timed_out.member.communication_disabled_until = Some(Utc::now());
+        assert_eq!(timed_out.in_channel(&[]), Permissions::DEFAULT_EVERYONE);
+    }
+
+    #[test]
+    fn rejects_unknown_permission_bits() {
+        // Valeurs écrites en dur par les migrations de init.sql
+        assert_eq!(Permissions::LEGACY_ADMIN.bits(), 250);
+        assert_eq!(Permissions::DEFAULT_EVERYONE.bits(), 3840);
+        assert_eq!(
+            Permissions::from_bits(Permissions::LEGACY_ADMIN.bits()),
+            Some(Permissions::LEGACY_ADMIN)
+        );
+        assert!(Permissions::from_bits(1 << 40).is_none());
+        assert!(Permissions::from_bits(-1).is_none());
+    }
+}
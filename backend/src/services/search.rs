@@ -0,0 +1,416 @@
+//! Recherche de messages : texte libre et opérateurs, dans un serveur ou les conversations privées
+
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::{
+    DirectMessageItemResponse, MessageSearchFilter, MessageSearchResponse, MessageSearchResult,
+    MessageWithUser, SearchMessagesQuery,
+};
+use crate::repositories::{
+    ChannelRepository, DirectMessageRepository, DmRepository, MessageRepository, ServerRepository,
+    UserRepository,
+};
+use crate::services::{channels, dm, messages};
+
+const DEFAULT_LIMIT: i64 = 25;
+const MAX_LIMIT: i64 = 50;
+const DEFAULT_CONTEXT: i64 = 2;
+const MAX_CONTEXT: i64 = 5;
+const MAX_QUERY_CHARS: usize = 512;
+
+/// Requête de recherche analysée, noms non encore résolus
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub text: Option<String>,
+    /// `from:username`
+    pub from: Vec<String>,
+    /// `in:#channel` (serveur) ou `in:username` (conversations privées), en minuscules
+    pub within: Vec<String>,
+    /// `mentions:username`
+    pub mentions: Vec<String>,
+    pub has_attachment: bool,
+    pub has_link: bool,
+    /// Borne haute exclue
+    pub before: Option<DateTime<Utc>>,
+    /// Borne basse incluse ; pour une date seule, lendemain à minuit (le jour est exclu)
+    pub after: Option<DateTime<Utc>>,
+}
+
+/// Date d'un opérateur : `AAAA-MM-JJ` (minuit UTC) ou RFC 3339
+fn parse_date(operator: &str, value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(chrono::NaiveTime::MIN).and_utc());
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|_| Error::BadRequest {
+            message: format!(
+                "Invalid date for {}: {} (expected YYYY-MM-DD or RFC 3339)",
+                operator, value
+            ),
+        })
+}
+
+/// Sépare le texte libre des opérateurs ; un mot `inconnu:valeur` reste du texte
+/// `before:` exclut le jour donné, `after:` aussi (recherche à partir du lendemain)
+pub fn parse_search_query(query: &str) -> Result<SearchQuery> {
+    if query.chars().count() > MAX_QUERY_CHARS {
+        return Err(Error::BadRequest {
+            message: format!("Search query is too long (max {} chars)", MAX_QUERY_CHARS),
+        });
+    }
+
+    let mut parsed = SearchQuery::default();
+    let mut text = Vec::new();
+    for token in query.split_whitespace() {
+        let Some((operator, value)) = token.split_once(':').filter(|(_, value)| !value.is_empty())
+        else {
+            text.push(token);
+            continue;
+        };
+
+        match operator.to_lowercase().as_str() {
+            "from" => parsed.from.push(value.trim_start_matches('@').to_string()),
+            "mentions" => parsed
+                .mentions
+                .push(value.trim_start_matches('@').to_string()),
+            "in" => parsed
+                .within
+                .push(value.trim_start_matches(['#', '@']).to_lowercase()),
+            "has" => match value.to_lowercase().as_str() {
+                "attachment" | "file" => parsed.has_attachment = true,
+                "link" => parsed.has_link = true,
+                _ => {
+                    return Err(Error::BadRequest {
+                        message: "has: must be attachment or link".to_string(),
+                    })
+                }
+            },
+            "before" => parsed.before = Some(parse_date("before", value)?),
+            "after" => {
+                let date_only = NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok();
+                let after = parse_date("after", value)?;
+                parsed.after = Some(match date_only {
+                    true => after + chrono::Duration::days(1),
+                    false => after,
+                });
+            }
+            _ => text.push(token),
+        }
+    }
+
+    if !text.is_empty() {
+        parsed.text = Some(text.join(" "));
+    }
+    if parsed == SearchQuery::default() {
+        return Err(Error::BadRequest {
+            message: "Search query cannot be empty".to_string(),
+        });
+    }
+    Ok(parsed)
+}
+
+/// Ids des utilisateurs existants parmi `usernames`
+async fn resolve_usernames(user_repo: &UserRepository, usernames: &[String]) -> Result<Vec<Uuid>> {
+    let mut ids = Vec::with_capacity(usernames.len());
+    for username in usernames {
+        if let Some(user) = user_repo.get_by_username(username).await? {
+            ids.push(user.id);
+        }
+    }
+    Ok(ids)
+}
+
+/// Résout les noms d'utilisateurs de la requête
+/// Retourne None si aucun auteur ou aucune personne mentionnée n'existe : rien ne peut correspondre
+async fn resolve_filter(
+    user_repo: &UserRepository,
+    query: &SearchQuery,
+) -> Result<Option<MessageSearchFilter>> {
+    let author_ids = resolve_usernames(user_repo, &query.from).await?;
+    let mention_ids = resolve_usernames(user_repo, &query.mentions).await?;
+    if (!query.from.is_empty() && author_ids.is_empty())
+        || (!query.mentions.is_empty() && mention_ids.is_empty())
+    {
+        return Ok(None);
+    }
+
+    Ok(Some(MessageSearchFilter {
+        text: query.text.clone(),
+        author_ids,
+        mention_ids,
+        has_attachment: query.has_attachment,
+        has_link: query.has_link,
+        before: query.before,
+        after: query.after,
+    }))
+}
+
+/// Curseur `before` inconnu : répondre la première page ferait boucler le client
+fn unknown_cursor() -> Error {
+    Error::BadRequest {
+        message: "Unknown search cursor".to_string(),
+    }
+}
+
+/// Taille de page, contexte par résultat
+fn page_size(query: &SearchMessagesQuery) -> (i64, i64) {
+    (
+        query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        query
+            .context
+            .unwrap_or(DEFAULT_CONTEXT)
+            .clamp(0, MAX_CONTEXT),
+    )
+}
+
+/// Reconstitue les résultats à partir de la liste aplatie `contexte avant, résultat,
+/// contexte après` ; `shape` donne la taille des contextes de chaque résultat
+pub fn regroup<T>(flattened: Vec<T>, shape: &[(usize, usize)]) -> Vec<MessageSearchResult<T>> {
+    let mut flattened = flattened.into_iter();
+    let mut results = Vec::with_capacity(shape.len());
+    for &(before, after) in shape {
+        let before_messages: Vec<T> = flattened.by_ref().take(before).collect();
+        let Some(message) = flattened.next() else {
+            break;
+        };
+        results.push(MessageSearchResult {
+            message,
+            before: before_messages,
+            after: flattened.by_ref().take(after).collect(),
+        });
+    }
+    results
+}
+
+/// Recherche dans les channels d'un serveur que l'utilisateur peut voir (threads compris)
+pub async fn search_server_messages(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    message_repo: &MessageRepository,
+    user_repo: &UserRepository,
+    server_id: Uuid,
+    user_id: Uuid,
+    query: SearchMessagesQuery,
+) -> Result<MessageSearchResponse<MessageWithUser>> {
+    let parsed = parse_search_query(&query.q)?;
+    let (limit, context) = page_size(&query);
+
+    let mut channels =
+        channels::list_channels(server_repo, channel_repo, server_id, user_id).await?;
+    let visible: HashSet<Uuid> = channels.iter().map(|channel| channel.id).collect();
+    channels.extend(
+        channel_repo
+            .list_threads_by_server(server_id)
+            .await?
+            .into_iter()
+            .filter(|thread| {
+                thread
+                    .parent_id
+                    .is_some_and(|parent| visible.contains(&parent))
+            }),
+    );
+
+    let channel_ids: Vec<Uuid> = channels
+        .iter()
+        .filter(|channel| channel.kind.accepts_messages())
+        .filter(|channel| {
+            parsed.within.is_empty() || parsed.within.contains(&channel.name.to_lowercase())
+        })
+        .map(|channel| channel.id)
+        .collect();
+    if channel_ids.is_empty() {
+        return Ok(MessageSearchResponse::empty());
+    }
+
+    let Some(filter) = resolve_filter(user_repo, &parsed).await? else {
+        return Ok(MessageSearchResponse::empty());
+    };
+
+    // Curseur : le message doit exister dans le périmètre de la recherche
+    let before = match query.before {
+        Some(message_id) => message_repo
+            .find_by_id(message_id)
+            .await
+            .map_err(|e| Error::DatabaseError {
+                message: format!("MongoDB query failed: {}", e),
+            })?
+            .filter(|message| channel_ids.contains(&message.channel_id))
+            .map(|message| Some((message.created_at, message.message_id)))
+            .ok_or_else(unknown_cursor)?,
+        None => None,
+    };
+
+    let hits = message_repo
+        .search(&channel_ids, &filter, before, limit)
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB query failed: {}", e),
+        })?;
+    let next_before = match hits.len() as i64 == limit {
+        true => hits.last().map(|hit| hit.message_id),
+        false => None,
+    };
+
+    let mut flattened = Vec::new();
+    let mut shape = Vec::with_capacity(hits.len());
+    for hit in hits {
+        let (before, after) = match context {
+            0 => (vec![], vec![]),
+            _ => message_repo
+                .list_context(hit.channel_id, hit.created_at, context)
+                .await
+                .map_err(|e| Error::DatabaseError {
+                    message: format!("MongoDB query failed: {}", e),
+                })?,
+        };
+        shape.push((before.len(), after.len()));
+        flattened.extend(before);
+        flattened.push(hit);
+        flattened.extend(after);
+    }
+
+    let flattened = messages::with_authors(server_repo, message_repo, server_id, flattened).await?;
+    Ok(MessageSearchResponse {
+        results: regroup(flattened, &shape),
+        next_before,
+    })
+}
+
+/// Recherche dans les conversations privées de l'utilisateur
+pub async fn search_direct_messages(
+    dm_repo: &DmRepository,
+    dm_message_repo: &DirectMessageRepository,
+    user_repo: &UserRepository,
+    user_id: Uuid,
+    query: SearchMessagesQuery,
+) -> Result<MessageSearchResponse<DirectMessageItemResponse>> {
+    let parsed = parse_search_query(&query.q)?;
+    let (limit, context) = page_size(&query);
+
+    let dm_ids: Vec<Uuid> = dm_repo
+        .list_user_dms(user_id)
+        .await?
+        .into_iter()
+        .filter(|conversation| {
+            parsed.within.is_empty()
+                || parsed
+                    .within
+                    .contains(&conversation.username.to_lowercase())
+        })
+        .map(|conversation| conversation.id)
+        .collect();
+    if dm_ids.is_empty() {
+        return Ok(MessageSearchResponse::empty());
+    }
+
+    let Some(filter) = resolve_filter(user_repo, &parsed).await? else {
+        return Ok(MessageSearchResponse::empty());
+    };
+
+    let before = match query.before {
+        Some(message_id) => dm_message_repo
+            .find_by_id(message_id)
+            .await
+            .map_err(|e| Error::DatabaseError {
+                message: format!("MongoDB query failed: {}", e),
+            })?
+            .filter(|message| dm_ids.contains(&message.dm_id))
+            .map(|message| Some((message.created_at, message.message_id)))
+            .ok_or_else(unknown_cursor)?,
+        None => None,
+    };
+
+    let hits = dm_message_repo
+        .search(&dm_ids, &filter, before, limit)
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB query failed: {}", e),
+        })?;
+    let next_before = match hits.len() as i64 == limit {
+        true => hits.last().map(|hit| hit.message_id),
+        false => None,
+    };
+
+    let mut flattened = Vec::new();
+    let mut shape = Vec::with_capacity(hits.len());
+    for hit in hits {
+        let (before, after) = match context {
+            0 => (vec![], vec![]),
+            _ => dm_message_repo
+                .list_context(hit.dm_id, hit.created_at, context)
+                .await
+                .map_err(|e| Error::DatabaseError {
+                    message: format!("MongoDB query failed: {}", e),
+                })?,
+        };
+        shape.push((before.len(), after.len()));
+        flattened.extend(before);
+        flattened.push(hit);
+        flattened.extend(after);
+    }
+
+    let flattened = dm::with_authors(dm_message_repo, user_repo, flattened).await?;
+    Ok(MessageSearchResponse {
+        results: regroup(flattened, &shape),
+        next_before,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_search_query_separates_text_and_operators() {
+        let parsed = parse_search_query(
+            "sortie from:@Alice in:#Général has:link mentions:bob vendredi https://a.fr",
+        )
+        .unwrap();
+
+        assert_eq!(parsed.text.as_deref(), Some("sortie vendredi https://a.fr"));
+        assert_eq!(parsed.from, vec!["Alice"]);
+        assert_eq!(parsed.within, vec!["général"]);
+        assert_eq!(parsed.mentions, vec!["bob"]);
+        assert!(parsed.has_link);
+        assert!(!parsed.has_attachment);
+    }
+
+    #[test]
+    fn parse_search_query_reads_dates_and_rejects_bad_values() {
+        let parsed = parse_search_query("before:2026-10-18 after:2026-10-01").unwrap();
+        assert_eq!(
+            parsed.before.unwrap().to_rfc3339(),
+            "2026-10-18T00:00:00+00:00"
+        );
+        // after: exclut le jour donné
+        assert_eq!(
+            parsed.after.unwrap().to_rfc3339(),
+            "2026-10-02T00:00:00+00:00"
+        );
+
+        assert!(parse_search_query("before:hier").is_err());
+        assert!(parse_search_query("has:video").is_err());
+        assert!(parse_search_query("   ").is_err());
+    }
+
+    #[test]
+    fn regroup_rebuilds_results_with_their_context() {
+        let results = regroup(vec![1, 2, 3, 4, 5, 6], &[(1, 1), (0, 2)]);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            (results[0].before.clone(), results[0].message),
+            (vec![1], 2)
+        );
+        assert_eq!(results[0].after, vec![3]);
+        assert_eq!(
+            (results[1].before.is_empty(), results[1].message),
+            (true, 4)
+        );
+        assert_eq!(results[1].after, vec![5, 6]);
+    }
+}
@@ -1,12 +1,20 @@
 use crate::error::{Error, Result};
 use crate::models::{
-    BanMemberPayload, CreateServerPayload, MemberRole, Server, ServerBan, ServerMember,
-    TransferOwnershipPayload, UpdateServerPayload,
+    AuditAction, BanMemberPayload, CreateServerPayload, MemberRole, NewAuditLogEntry, Permissions,
+    Server, ServerBan, ServerMember, TimeoutMemberPayload, TransferOwnershipPayload,
+    UpdateMemberProfilePayload, UpdateServerPayload,
 };
 use crate::repositories::{ServerRepository, UserRepository};
+use crate::services::audit;
+use crate::services::roles::{self, require_permission, MemberPermissions};
 use chrono::Utc;
 use uuid::Uuid;
 
+/// Durée maximale d'un timeout
+const MAX_TIMEOUT_DAYS: i64 = 28;
+const MAX_NICKNAME_LENGTH: usize = 32;
+const MAX_AVATAR_URL_LENGTH: usize = 512;
+
 pub async fn create_server(
     server_repo: &ServerRepository,
     user_repo: &UserRepository,
@@ -60,6 +68,8 @@ pub async fn create_server(
             }
         })?;
 
+    roles::create_default_role(server_repo, server_id).await?;
+
     Ok(server)
 }
 
@@ -95,20 +105,26 @@ pub async fn update_server(
     user_id: Uuid,
     payload: UpdateServerPayload,
 ) -> Result<Server> {
-    let member = server_repo
-        .find_member(server_id, user_id)
-        .await?
-        .ok_or(Error::ServerForbidden)?;
-
-    if member.role == MemberRole::Member {
-        return Err(Error::ServerForbidden);
-    }
+    let requester = require_permission(
+        server_repo,
+        server_id,
+        user_id,
+        Permissions::MANAGE_SERVER,
+        Error::ServerForbidden,
+    )
+    .await?;
 
     let server = server_repo
         .update(server_id, payload.name)
         .await?
         .ok_or(Error::ServerNotFound)?;
 
+    let entry = NewAuditLogEntry::new(server_id, user_id, AuditAction::ServerUpdate)
+        .target(server_id)
+        .before(&requester.server)
+        .after(&server);
+    audit::record(server_repo, entry).await;
+
     Ok(server)
 }
 
@@ -141,7 +157,7 @@ pub async fn join_server(
         .ok_or(Error::ServerNotFound)?;
 
     if server_repo.is_user_banned(server_id, user_id).await? {
-        return Err(Error::ServerForbidden);
+        return Err(Error::ServerBanned);
     }
 
     if server_repo.find_member(server_id, user_id).await?.is_some() {
@@ -173,34 +189,38 @@ pub async fn leave_server(
     Ok(())
 }
 
-pub async fn kick_member(
+/// Le membre ciblé doit exister et se trouver sous le demandeur dans la hiérarchie
+async fn ensure_outranks(
     server_repo: &ServerRepository,
-    server_id: Uuid,
+    requester: &MemberPermissions,
     target_user_id: Uuid,
-    requester_id: Uuid,
 ) -> Result<()> {
-    let server = server_repo
-        .find_by_id(server_id)
+    let target = roles::member_permissions(server_repo, requester.server.id, target_user_id)
         .await?
-        .ok_or(Error::ServerNotFound)?;
-
-    let requester = server_repo
-        .find_member(server_id, requester_id)
-        .await?
-        .ok_or(Error::ServerForbidden)?;
+        .ok_or(Error::UserNotFound)?;
 
-    if requester.role == MemberRole::Member {
+    if target.is_owner() || !requester.outranks(target.top_position) {
         return Err(Error::ServerForbidden);
     }
+    Ok(())
+}
 
-    let target = server_repo
-        .find_member(server_id, target_user_id)
-        .await?
-        .ok_or(Error::UserNotFound)?;
+pub async fn kick_member(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    target_user_id: Uuid,
+    requester_id: Uuid,
+) -> Result<()> {
+    let requester = require_permission(
+        server_repo,
+        server_id,
+        requester_id,
+        Permissions::KICK_MEMBERS,
+        Error::ServerForbidden,
+    )
+    .await?;
 
-    if target.user_id == server.owner_id {
-        return Err(Error::ServerForbidden);
-    }
+    ensure_outranks(server_repo, &requester, target_user_id).await?;
 
     // Ban temporaire de 1h lors d'un kick
     let expires_at = Utc::now() + chrono::Duration::hours(1);
@@ -215,41 +235,181 @@ pub async fn kick_member(
         .await?;
 
     server_repo.remove_member(server_id, target_user_id).await?;
+
+    let entry = NewAuditLogEntry::new(server_id, requester_id, AuditAction::MemberKick)
+        .target(target_user_id);
+    audit::record(server_repo, entry).await;
+
     Ok(())
 }
 
-pub async fn ban_member(
+/// Applique (ou lève avec `until: null`) un timeout, même hiérarchie que le kick
+pub async fn timeout_member(
     server_repo: &ServerRepository,
     server_id: Uuid,
     target_user_id: Uuid,
-    payload: BanMemberPayload,
+    payload: TimeoutMemberPayload,
     requester_id: Uuid,
-) -> Result<ServerBan> {
-    let server = server_repo
-        .find_by_id(server_id)
+) -> Result<ServerMember> {
+    let requester = require_permission(
+        server_repo,
+        server_id,
+        requester_id,
+        Permissions::KICK_MEMBERS,
+        Error::ServerForbidden,
+    )
+    .await?;
+
+    ensure_outranks(server_repo, &requester, target_user_id).await?;
+
+    if let Some(until) = payload.until {
+        let now = Utc::now();
+        if until <= now || until > now + chrono::Duration::days(MAX_TIMEOUT_DAYS) {
+            return Err(Error::BadRequest {
+                message: format!("until must be in the next {} days", MAX_TIMEOUT_DAYS),
+            });
+        }
+    }
+
+    let before = server_repo
+        .find_member(server_id, target_user_id)
         .await?
-        .ok_or(Error::ServerNotFound)?;
+        .ok_or(Error::UserNotFound)?;
 
-    let requester = server_repo
-        .find_member(server_id, requester_id)
+    let member = server_repo
+        .set_member_timeout(server_id, target_user_id, payload.until)
         .await?
-        .ok_or(Error::ServerForbidden)?;
+        .ok_or(Error::UserNotFound)?;
 
-    if requester.role == MemberRole::Member {
-        return Err(Error::ServerForbidden);
+    let entry = NewAuditLogEntry::new(server_id, requester_id, AuditAction::MemberTimeout)
+        .target(target_user_id)
+        .before(&before)
+        .after(&member)
+        .reason(payload.reason);
+    audit::record(server_repo, entry).await;
+
+    Ok(member)
+}
+
+/// Pseudo de serveur : vide ou null revient au pseudo global
+fn normalize_nickname(nickname: Option<String>) -> Result<Option<String>> {
+    let Some(nickname) = nickname.map(|n| n.trim().to_string()) else {
+        return Ok(None);
+    };
+    if nickname.is_empty() {
+        return Ok(None);
+    }
+    if nickname.chars().count() > MAX_NICKNAME_LENGTH {
+        return Err(Error::BadRequest {
+            message: format!("Nickname must be 1-{} characters", MAX_NICKNAME_LENGTH),
+        });
     }
+    Ok(Some(nickname))
+}
 
-    let target = server_repo
+/// Avatar de serveur : fichier uploadé (`/files/...`), avatar fourni (`/avatars/...`) ou URL http(s)
+fn normalize_avatar_url(avatar_url: Option<String>) -> Result<Option<String>> {
+    let Some(avatar_url) = avatar_url.map(|url| url.trim().to_string()) else {
+        return Ok(None);
+    };
+    if avatar_url.is_empty() {
+        return Ok(None);
+    }
+    let allowed = ["/files/", "/avatars/", "https://", "http://"]
+        .iter()
+        .any(|prefix| avatar_url.starts_with(prefix));
+    if !allowed || avatar_url.len() > MAX_AVATAR_URL_LENGTH {
+        return Err(Error::BadRequest {
+            message: "Invalid avatar URL".to_string(),
+        });
+    }
+    Ok(Some(avatar_url))
+}
+
+/// Pseudo et avatar de serveur : le membre modifie les siens,
+/// KICK_MEMBERS ceux des membres situés sous son rôle le plus haut
+pub async fn update_member_profile(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    target_user_id: Uuid,
+    payload: UpdateMemberProfilePayload,
+    requester_id: Uuid,
+) -> Result<ServerMember> {
+    if requester_id == target_user_id {
+        server_repo
+            .find_member(server_id, requester_id)
+            .await?
+            .ok_or(Error::ServerForbidden)?;
+    } else {
+        let requester = require_permission(
+            server_repo,
+            server_id,
+            requester_id,
+            Permissions::KICK_MEMBERS,
+            Error::ServerForbidden,
+        )
+        .await?;
+        ensure_outranks(server_repo, &requester, target_user_id).await?;
+    }
+
+    let before = server_repo
         .find_member(server_id, target_user_id)
         .await?
         .ok_or(Error::UserNotFound)?;
 
-    if target.user_id == server.owner_id {
-        return Err(Error::ServerForbidden);
-    }
+    let nickname = match payload.nickname {
+        Some(nickname) => normalize_nickname(nickname)?,
+        None => before.nickname.clone(),
+    };
+    let avatar_url = match payload.avatar_url {
+        Some(avatar_url) => normalize_avatar_url(avatar_url)?,
+        None => before.avatar_url.clone(),
+    };
 
-    if requester.role == MemberRole::Admin && target.role == MemberRole::Admin {
-        return Err(Error::ServerForbidden);
+    let member = server_repo
+        .set_member_profile(
+            server_id,
+            target_user_id,
+            nickname.as_deref(),
+            avatar_url.as_deref(),
+        )
+        .await?
+        .ok_or(Error::UserNotFound)?;
+
+    let entry = NewAuditLogEntry::new(server_id, requester_id, AuditAction::MemberProfileUpdate)
+        .target(target_user_id)
+        .before(&before)
+        .after(&member);
+    audit::record(server_repo, entry).await;
+
+    Ok(member)
+}
+
+pub async fn ban_member(
+    server_repo: &ServerRepository,
+    server_id: Uuid,
+    target_user_id: Uuid,
+    payload: BanMemberPayload,
+    requester_id: Uuid,
+) -> Result<ServerBan> {
+    let requester = require_permission(
+        server_repo,
+        server_id,
+        requester_id,
+        Permissions::BAN_MEMBERS,
+        Error::ServerForbidden,
+    )
+    .await?;
+
+    ensure_outranks(server_repo, &requester, target_user_id).await?;
+
+    if payload
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        return Err(Error::BadRequest {
+            message: "expires_at must be in the future".to_string(),
+        });
     }
 
     let ban = server_repo
@@ -263,6 +423,13 @@ pub async fn ban_member(
         .await?;
 
     server_repo.remove_member(server_id, target_user_id).await?;
+
+    let entry = NewAuditLogEntry::new(server_id, requester_id, AuditAction::MemberBanAdd)
+        .target(target_user_id)
+        .after(&ban)
+        .reason(ban.reason.clone());
+    audit::record(server_repo, entry).await;
+
     Ok(ban)
 }
 
@@ -272,19 +439,42 @@ pub async fn unban_member(
     target_user_id: Uuid,
     requester_id: Uuid,
 ) -> Result<()> {
-    let requester = server_repo
-        .find_member(server_id, requester_id)
-        .await?
-        .ok_or(Error::ServerForbidden)?;
+    require_permission(
+        server_repo,
+        server_id,
+        requester_id,
+        Permissions::BAN_MEMBERS,
+        Error::ServerForbidden,
+    )
+    .await?;
+
+    let Some(ban) = server_repo.find_ban(server_id, target_user_id).await? else {
+        return Ok(());
+    };
+    server_repo.remove_ban(server_id, target_user_id).await?;
 
-    if requester.role == MemberRole::Member {
-        return Err(Error::ServerForbidden);
-    }
+    let entry = NewAuditLogEntry::new(server_id, requester_id, AuditAction::MemberBanRemove)
+        .target(target_user_id)
+        .before(&ban);
+    audit::record(server_repo, entry).await;
 
-    server_repo.remove_ban(server_id, target_user_id).await?;
     Ok(())
 }
 
+/// Lève les bans temporaires expirés et journalise chaque levée
+pub async fn sweep_expired_bans(server_repo: &ServerRepository) -> Result<Vec<ServerBan>> {
+    let expired = server_repo.delete_expired_bans().await?;
+
+    for ban in &expired {
+        let entry = NewAuditLogEntry::system(ban.server_id, AuditAction::MemberBanExpire)
+            .target(ban.user_id)
+            .before(ban);
+        audit::record(server_repo, entry).await;
+    }
+
+    Ok(expired)
+}
+
 pub async fn list_bans(
     server_repo: &ServerRepository,
     server_id: Uuid,
@@ -307,6 +497,7 @@ pub async fn list_members(
     Ok(members)
 }
 
+/// Rang historique (Owner/Admin/Member) : promouvoir Admin attribue le rôle "Admin"
 pub async fn update_member_role(
     server_repo: &ServerRepository,
     server_id: Uuid,
@@ -332,11 +523,19 @@ pub async fn update_member_role(
         return Err(Error::ServerForbidden);
     }
 
+    roles::apply_legacy_rank(server_repo, server_id, target_user_id, &new_role).await?;
+
     let updated_member = server_repo
         .update_member_role(server_id, target_user_id, new_role)
         .await?
         .ok_or(Error::UserNotFound)?;
 
+    let entry = NewAuditLogEntry::new(server_id, requester_id, AuditAction::MemberRoleUpdate)
+        .target(target_user_id)
+        .before(&member)
+        .after(&updated_member);
+    audit::record(server_repo, entry).await;
+
     Ok(updated_member)
 }
 
@@ -368,10 +567,36 @@ pub async fn transfer_ownership(
     server_repo
         .update_member_role(server_id, requester_id, MemberRole::Admin)
         .await?;
+    roles::apply_legacy_rank(server_repo, server_id, requester_id, &MemberRole::Admin).await?;
 
     server_repo
         .update_member_role(server_id, payload.new_owner_id, MemberRole::Owner)
         .await?;
 
+    let entry = NewAuditLogEntry::new(server_id, requester_id, AuditAction::OwnershipTransfer)
+        .target(payload.new_owner_id)
+        .before(&server)
+        .after(&updated_server);
+    audit::record(server_repo, entry).await;
+
     Ok(updated_server)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blank_profile_fields_reset_to_global_profile() {
+        assert_eq!(normalize_nickname(Some("  ".to_string())).unwrap(), None);
+        assert_eq!(
+            normalize_nickname(Some(" Alice ".to_string())).unwrap(),
+            Some("Alice".to_string())
+        );
+        assert!(normalize_nickname(Some("x".repeat(MAX_NICKNAME_LENGTH + 1))).is_err());
+
+        assert_eq!(normalize_avatar_url(Some(String::new())).unwrap(), None);
+        assert!(normalize_avatar_url(Some("/files/abc.png".to_string())).is_ok());
+        assert!(normalize_avatar_url(Some("javascript:alert(1)".to_string())).is_err());
+    }
+}
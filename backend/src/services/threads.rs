@@ -0,0 +1,303 @@
+//! Threads : channels ouverts depuis un message, aux permissions de leur channel d'origine
+
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::{
+    AuditAction, ChannelKind, CreateThreadPayload, NewAuditLogEntry, Permissions, Thread,
+    ThreadMember, UpdateThreadPayload,
+};
+use crate::repositories::{ChannelRepository, MessageRepository, ServerRepository};
+use crate::services::{audit, channels};
+
+/// Délais d'archivage automatique proposés (1 heure, 1 jour, 3 jours, 1 semaine)
+const AUTO_ARCHIVE_MINUTES: [i32; 4] = [60, 1440, 4320, 10080];
+const DEFAULT_AUTO_ARCHIVE_MINUTES: i32 = 1440;
+const MAX_THREAD_NAME_CHARS: usize = 100;
+
+fn validate_auto_archive(minutes: i32) -> Result<()> {
+    if !AUTO_ARCHIVE_MINUTES.contains(&minutes) {
+        return Err(Error::BadRequest {
+            message: "auto_archive_minutes must be one of 60, 1440, 4320, 10080".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Nom d'un thread : celui fourni, sinon le début du message d'origine
+pub fn thread_name(name: Option<&str>, parent_content: &str) -> Result<String> {
+    if let Some(name) = name {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(Error::BadRequest {
+                message: "Thread name cannot be empty".to_string(),
+            });
+        }
+        if name.chars().count() > MAX_THREAD_NAME_CHARS {
+            return Err(Error::BadRequest {
+                message: format!(
+                    "Thread name is too long (max {} chars)",
+                    MAX_THREAD_NAME_CHARS
+                ),
+            });
+        }
+        return Ok(name.to_string());
+    }
+
+    let first_line = parent_content.lines().next().unwrap_or_default().trim();
+    let name: String = first_line.chars().take(MAX_THREAD_NAME_CHARS).collect();
+    match name.trim_end() {
+        "" => Ok("Thread".to_string()),
+        name => Ok(name.to_string()),
+    }
+}
+
+/// Thread visible par l'utilisateur (VIEW_CHANNEL sur son channel d'origine)
+pub async fn get_thread(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    thread_id: Uuid,
+    user_id: Uuid,
+) -> Result<Thread> {
+    channels::get_channel(server_repo, channel_repo, thread_id, user_id).await?;
+    channel_repo
+        .find_thread(thread_id)
+        .await?
+        .ok_or(Error::ChannelNotFound)
+}
+
+/// Ouvre un thread depuis un message d'un channel texte ou d'annonces
+pub async fn create_thread(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    message_repo: &MessageRepository,
+    channel_id: Uuid,
+    message_id: Uuid,
+    user_id: Uuid,
+    payload: CreateThreadPayload,
+) -> Result<Thread> {
+    let channel = channels::get_channel(server_repo, channel_repo, channel_id, user_id).await?;
+    if !matches!(channel.kind, ChannelKind::Text | ChannelKind::Announcement) {
+        return Err(Error::BadRequest {
+            message: "Threads can only be started in text channels".to_string(),
+        });
+    }
+    channels::ensure_channel_permission(
+        server_repo,
+        channel_repo,
+        &channel,
+        user_id,
+        Permissions::SEND_MESSAGES,
+        Error::ChannelForbidden,
+    )
+    .await?;
+
+    let message = message_repo
+        .find_by_id(message_id)
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB query failed: {}", e),
+        })?
+        .filter(|message| message.channel_id == channel_id && message.deleted_at.is_none())
+        .ok_or(Error::MessageNotFound)?;
+    if message.thread_id.is_some() {
+        return Err(Error::ThreadAlreadyExists);
+    }
+
+    let auto_archive_minutes = payload
+        .auto_archive_minutes
+        .unwrap_or(DEFAULT_AUTO_ARCHIVE_MINUTES);
+    validate_auto_archive(auto_archive_minutes)?;
+    let name = thread_name(payload.name.as_deref(), &message.content)?;
+
+    let thread = channel_repo
+        .create_thread(
+            Uuid::new_v4(),
+            &channel,
+            message_id,
+            &name,
+            user_id,
+            auto_archive_minutes,
+        )
+        .await
+        .map_err(|e| {
+            // Thread ouvert en même temps depuis le même message
+            if e.to_string().contains("threads_parent_message_id_key") {
+                Error::ThreadAlreadyExists
+            } else {
+                Error::from(e)
+            }
+        })?;
+
+    message_repo
+        .set_thread(message_id, thread.id)
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB update failed: {}", e),
+        })?;
+
+    Ok(thread)
+}
+
+/// Threads actifs ou archivés d'un channel
+pub async fn list_threads(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    channel_id: Uuid,
+    user_id: Uuid,
+    archived: bool,
+) -> Result<Vec<Thread>> {
+    channels::get_channel(server_repo, channel_repo, channel_id, user_id).await?;
+    Ok(channel_repo.list_threads(channel_id, archived).await?)
+}
+
+/// Renomme, archive ou désarchive un thread : son créateur, ou MANAGE_MESSAGES
+pub async fn update_thread(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    thread_id: Uuid,
+    user_id: Uuid,
+    payload: UpdateThreadPayload,
+) -> Result<Thread> {
+    let existing = get_thread(server_repo, channel_repo, thread_id, user_id).await?;
+
+    let is_owner = existing.owner_id == Some(user_id);
+    if !is_owner {
+        channels::require_channel_permission(
+            server_repo,
+            channel_repo,
+            thread_id,
+            user_id,
+            Permissions::MANAGE_MESSAGES,
+            Error::ChannelForbidden,
+        )
+        .await?;
+    }
+
+    let name = payload
+        .name
+        .as_deref()
+        .map(|name| thread_name(Some(name), ""))
+        .transpose()?;
+    if let Some(minutes) = payload.auto_archive_minutes {
+        validate_auto_archive(minutes)?;
+    }
+
+    let thread = channel_repo
+        .update_thread(
+            thread_id,
+            name,
+            payload.archived,
+            payload.auto_archive_minutes,
+        )
+        .await?
+        .ok_or(Error::ChannelNotFound)?;
+
+    if !is_owner {
+        let entry = NewAuditLogEntry::new(thread.server_id, user_id, AuditAction::ThreadUpdate)
+            .target(thread_id)
+            .before(&existing)
+            .after(&thread);
+        audit::record(server_repo, entry).await;
+    }
+
+    Ok(thread)
+}
+
+/// Nouveau message dans un channel : si c'est un thread, son activité et le compteur du
+/// message d'origine sont mis à jour
+/// Retourne le thread s'il vient d'être désarchivé par ce message
+pub async fn record_activity(
+    channel_repo: &ChannelRepository,
+    message_repo: &MessageRepository,
+    channel_id: Uuid,
+    author_id: Uuid,
+) -> Result<Option<Thread>> {
+    let Some(was_archived) = channel_repo
+        .record_thread_activity(channel_id, author_id)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let Some(thread) = channel_repo.find_thread(channel_id).await? else {
+        return Ok(None);
+    };
+    message_repo
+        .record_thread_message(thread.parent_message_id)
+        .await
+        .map_err(|e| Error::DatabaseError {
+            message: format!("MongoDB update failed: {}", e),
+        })?;
+
+    Ok(was_archived.then_some(thread))
+}
+
+/// Rejoint (suit) un thread
+pub async fn join_thread(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    thread_id: Uuid,
+    user_id: Uuid,
+) -> Result<ThreadMember> {
+    get_thread(server_repo, channel_repo, thread_id, user_id).await?;
+    Ok(channel_repo.add_thread_member(thread_id, user_id).await?)
+}
+
+pub async fn leave_thread(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    thread_id: Uuid,
+    user_id: Uuid,
+) -> Result<()> {
+    get_thread(server_repo, channel_repo, thread_id, user_id).await?;
+    channel_repo
+        .remove_thread_member(thread_id, user_id)
+        .await?;
+    Ok(())
+}
+
+pub async fn list_thread_members(
+    server_repo: &ServerRepository,
+    channel_repo: &ChannelRepository,
+    thread_id: Uuid,
+    user_id: Uuid,
+) -> Result<Vec<ThreadMember>> {
+    get_thread(server_repo, channel_repo, thread_id, user_id).await?;
+    Ok(channel_repo.list_thread_members(thread_id).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_name_defaults_to_first_line_of_parent_message() {
+        assert_eq!(
+            thread_name(None, "  Idée de sortie\nsuite").unwrap(),
+            "Idée de sortie"
+        );
+        assert_eq!(thread_name(None, "").unwrap(), "Thread");
+
+        let long = "a".repeat(MAX_THREAD_NAME_CHARS + 10);
+        assert_eq!(
+            thread_name(None, &long).unwrap().chars().count(),
+            MAX_THREAD_NAME_CHARS
+        );
+    }
+
+    #[test]
+    fn thread_name_rejects_blank_or_long_names() {
+        assert_eq!(thread_name(Some(" Sortie "), "").unwrap(), "Sortie");
+        assert!(thread_name(Some("   "), "contenu").is_err());
+        assert!(thread_name(Some(&"a".repeat(MAX_THREAD_NAME_CHARS + 1)), "").is_err());
+    }
+
+    #[test]
+    fn auto_archive_accepts_only_known_durations() {
+        assert!(validate_auto_archive(60).is_ok());
+        assert!(validate_auto_archive(10080).is_ok());
+        assert!(validate_auto_archive(0).is_err());
+        assert!(validate_auto_archive(120).is_err());
+    }
+}
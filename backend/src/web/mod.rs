@@ -4,5 +4,6 @@ pub mod ws;
 pub use mw_auth::mw_ctx_resolver;
 pub use mw_auth::mw_require_auth;
 pub use ws::ws_handler;
+pub use ws::HeartbeatConfig;
 pub use ws::WsHub;
 pub use ws::{MetricsSnapshot, WsMetrics};
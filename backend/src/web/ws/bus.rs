@@ -0,0 +1,372 @@
+//! Bus d'événements entre instances du backend
+//! Chaque WsHub livre d'abord à ses connexions locales, puis publie sur le bus pour
+//! que les autres instances livrent aux leurs (les sessions restent locales)
+
+use futures::future::BoxFuture;
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::web::ws::protocol::ServerEvent;
+use crate::web::ws::voice::VoiceState;
+
+/// Taille du buffer du bus en mémoire
+const IN_MEMORY_BUS_CAPACITY: usize = 1024;
+
+/// Canal Postgres LISTEN/NOTIFY
+const PG_CHANNEL: &str = "gateway_events";
+
+/// Limite d'un payload NOTIFY (8000 octets) : au-delà, l'événement passe par une table
+const PG_NOTIFY_MAX_PAYLOAD: usize = 7900;
+
+/// Préfixe d'un NOTIFY qui référence une ligne de `gateway_events`
+const PG_REF_PREFIX: &str = "ref:";
+
+/// Opération du hub à rejouer sur les autres instances
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BusMessage {
+    Channel {
+        server_id: Uuid,
+        channel_id: Uuid,
+        /// Channel privé : seuls ces utilisateurs reçoivent l'événement
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        viewers: Option<HashSet<Uuid>>,
+        event: ServerEvent,
+    },
+    User {
+        user_id: Uuid,
+        event: ServerEvent,
+    },
+    /// Même événement pour plusieurs utilisateurs (membres d'un serveur, viewers d'un channel)
+    Users {
+        user_ids: HashSet<Uuid>,
+        event: ServerEvent,
+    },
+    All {
+        event: ServerEvent,
+    },
+    /// Abonnement (ou désabonnement) des connexions d'un utilisateur à un serveur
+    ServerMembership {
+        user_id: Uuid,
+        server_id: Uuid,
+        subscribed: bool,
+    },
+    ServerRemoved {
+        server_id: Uuid,
+    },
+    /// Événement pour une connexion précise, livré par l'instance qui la porte
+    Connection {
+        conn_id: Uuid,
+        event: ServerEvent,
+    },
+    /// Présence vocale d'un utilisateur (`None` : il a quitté), répliquée sur chaque instance
+    VoiceState {
+        user_id: Uuid,
+        state: Option<VoiceState>,
+    },
+}
+
+/// Message publié, avec l'instance d'origine (ignoré par celle-ci à la réception)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BusEnvelope {
+    pub origin: Uuid,
+    pub message: BusMessage,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BusError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Transport des événements gateway entre instances
+pub trait EventBus: Send + Sync {
+    /// Publie un message pour toutes les instances (y compris l'émettrice)
+    fn publish(&self, envelope: BusEnvelope) -> BoxFuture<'_, Result<(), BusError>>;
+
+    /// Flux des messages publiés par toutes les instances
+    fn subscribe(&self) -> BoxFuture<'_, Result<BoxStream<'static, BusEnvelope>, BusError>>;
+}
+
+/// Bus en mémoire : une seule instance (ou plusieurs hubs dans le même processus)
+#[derive(Clone)]
+pub struct InMemoryEventBus {
+    sender: broadcast::Sender<BusEnvelope>,
+}
+
+impl InMemoryEventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(IN_MEMORY_BUS_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl Default for InMemoryEventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus for InMemoryEventBus {
+    fn publish(&self, envelope: BusEnvelope) -> BoxFuture<'_, Result<(), BusError>> {
+        // Aucun abonné n'est pas une erreur : rien à livrer ailleurs
+        let _ = self.sender.send(envelope);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<BoxStream<'static, BusEnvelope>, BusError>> {
+        let receiver = self.sender.subscribe();
+        Box::pin(async move {
+            let stream = stream::unfold(receiver, |mut receiver| async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(envelope) => return Some((envelope, receiver)),
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!("[Bus] In-memory bus lagged, skipped {}", skipped);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            });
+            Ok(stream.boxed())
+        })
+    }
+}
+
+/// Bus Postgres LISTEN/NOTIFY : partage le pool existant, aucune infrastructure en plus
+#[derive(Clone)]
+pub struct PgEventBus {
+    pool: PgPool,
+}
+
+impl PgEventBus {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn publish_envelope(&self, envelope: BusEnvelope) -> Result<(), BusError> {
+        let payload = serde_json::to_string(&envelope)?;
+
+        let notification = if payload.len() <= PG_NOTIFY_MAX_PAYLOAD {
+            payload
+        } else {
+            // Trop gros pour NOTIFY : stocker l'événement et ne notifier que sa référence
+            let id: i64 =
+                sqlx::query_scalar("INSERT INTO gateway_events (payload) VALUES ($1) RETURNING id")
+                    .bind(&payload)
+                    .fetch_one(&self.pool)
+                    .await?;
+
+            sqlx::query(
+                "DELETE FROM gateway_events WHERE created_at < NOW() - INTERVAL '1 minute'",
+            )
+            .execute(&self.pool)
+            .await?;
+
+            format!("{}{}", PG_REF_PREFIX, id)
+        };
+
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(PG_CHANNEL)
+            .bind(notification)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn decode(pool: &PgPool, notification: &str) -> Result<Option<BusEnvelope>, BusError> {
+        let Some(id) = notification.strip_prefix(PG_REF_PREFIX) else {
+            return Ok(Some(serde_json::from_str(notification)?));
+        };
+
+        let Ok(id) = id.parse::<i64>() else {
+            return Ok(None);
+        };
+        let payload: Option<String> =
+            sqlx::query_scalar("SELECT payload FROM gateway_events WHERE id = $1")
+                .bind(id)
+                .fetch_optional(pool)
+                .await?;
+
+        payload
+            .map(|payload| serde_json::from_str(&payload))
+            .transpose()
+            .map_err(BusError::from)
+    }
+}
+
+impl EventBus for PgEventBus {
+    fn publish(&self, envelope: BusEnvelope) -> BoxFuture<'_, Result<(), BusError>> {
+        Box::pin(self.publish_envelope(envelope))
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<BoxStream<'static, BusEnvelope>, BusError>> {
+        Box::pin(async move {
+            let mut listener = PgListener::connect_with(&self.pool).await?;
+            listener.listen(PG_CHANNEL).await?;
+
+            let pool = self.pool.clone();
+            let stream = stream::unfold((listener, pool), |(mut listener, pool)| async move {
+                loop {
+                    // recv() se reconnecte après une coupure (les NOTIFY manqués sont perdus)
+                    let notification = match listener.recv().await {
+                        Ok(notification) => notification,
+                        Err(e) => {
+                            tracing::warn!("[Bus] Postgres listener error: {}", e);
+                            tokio::time::sleep(Duration::from_secs(1)).await;
+                            continue;
+                        }
+                    };
+
+                    match Self::decode(&pool, notification.payload()).await {
+                        Ok(Some(envelope)) => return Some((envelope, (listener, pool))),
+                        Ok(None) => {}
+                        Err(e) => tracing::warn!("[Bus] Invalid gateway event: {}", e),
+                    }
+                }
+            });
+            Ok(stream.boxed())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use crate::web::ws::hub::WsHub;
+    use crate::web::ws::metrics::WsMetrics;
+    use crate::web::ws::protocol::ServerFrame;
+
+    fn message_delete(channel_id: Uuid) -> ServerEvent {
+        ServerEvent::MessageDelete {
+            id: Uuid::new_v4(),
+            channel_id,
+        }
+    }
+
+    async fn next_frame(queue: &mut crate::web::ws::hub::ConnectionQueue) -> ServerFrame {
+        tokio::time::timeout(Duration::from_secs(5), queue.frames.recv())
+            .await
+            .expect("frame should arrive through the bus")
+            .expect("queue should stay open")
+    }
+
+    /// Deux hubs sur le même bus : chacun livre aux connexions de l'autre
+    async fn assert_cross_hub_fanout(bus: Arc<dyn EventBus>) {
+        let hub_a = WsHub::with_event_bus(WsMetrics::new(), bus.clone());
+        let hub_b = WsHub::with_event_bus(WsMetrics::new(), bus);
+        hub_a.start_event_bus().await.unwrap();
+        hub_b.start_event_bus().await.unwrap();
+
+        let user_id = Uuid::new_v4();
+        let server_id = Uuid::new_v4();
+        let channel_id = Uuid::new_v4();
+
+        let conn_b = Uuid::new_v4();
+        let mut queue_b = hub_b.register(conn_b).await;
+        hub_b.associate_user(conn_b, user_id).await;
+        hub_b.create_session(conn_b, user_id).await;
+
+        // Abonnement décidé sur l'instance A (ex. : join via REST), appliqué sur B
+        hub_a.subscribe_user_to_server(user_id, server_id).await;
+        hub_a
+            .send_to_user(user_id, &message_delete(channel_id))
+            .await;
+        assert_eq!(next_frame(&mut queue_b).await.s, Some(1));
+
+        // Un seul message sur le bus pour plusieurs destinataires
+        hub_a
+            .send_to_users(
+                &HashSet::from([user_id, Uuid::new_v4()]),
+                &message_delete(channel_id),
+            )
+            .await;
+        assert_eq!(next_frame(&mut queue_b).await.s, Some(2));
+
+        hub_a
+            .broadcast_to_channel(server_id, channel_id, &message_delete(channel_id))
+            .await;
+        let frame = next_frame(&mut queue_b).await;
+        assert_eq!(frame.s, Some(3));
+        assert!(matches!(*frame.event, ServerEvent::MessageDelete { .. }));
+
+        // Un événement trop gros pour NOTIFY passe par la table gateway_events
+        let big = ServerEvent::Error {
+            code: "BIG".to_string(),
+            message: "x".repeat(PG_NOTIFY_MAX_PAYLOAD * 2),
+            nonce: None,
+            retry_after: None,
+        };
+        hub_a.send_to_user(user_id, &big).await;
+        assert!(matches!(
+            &*next_frame(&mut queue_b).await.event,
+            ServerEvent::Error { code, .. } if code == "BIG"
+        ));
+
+        // L'instance émettrice ne rejoue pas ses propres messages
+        let conn_a = Uuid::new_v4();
+        let mut queue_a = hub_a.register(conn_a).await;
+        hub_a.associate_user(conn_a, user_id).await;
+        hub_a
+            .send_to_user(user_id, &message_delete(channel_id))
+            .await;
+        next_frame(&mut queue_a).await;
+        next_frame(&mut queue_b).await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(queue_a.frames.try_recv().is_err());
+
+        // Présence vocale rejointe sur B, connue de A qui relaie la signalisation à B
+        let voice_state = VoiceState {
+            server_id,
+            channel_id,
+            conn_id: conn_b,
+        };
+        hub_b.voice_join(user_id, voice_state).await;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while hub_a.voice_state_of(user_id).await != Some(voice_state) {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("voice state should be replicated through the bus");
+
+        hub_a
+            .send_to_connection_anywhere(conn_b, &message_delete(channel_id))
+            .await;
+        assert!(matches!(
+            *next_frame(&mut queue_b).await.event,
+            ServerEvent::MessageDelete { .. }
+        ));
+        assert!(queue_a.frames.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn in_memory_bus_fans_out_between_hubs() {
+        assert_cross_hub_fanout(Arc::new(InMemoryEventBus::new())).await;
+    }
+
+    /// Nécessite une base locale : `TEST_DATABASE_URL=postgres://... cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore = "requires TEST_DATABASE_URL"]
+    async fn postgres_bus_fans_out_between_hubs() {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set");
+
+        let pool = PgPool::connect(&url).await.unwrap();
+        crate::services::bootstrap::apply_postgres_bootstrap(&pool)
+            .await
+            .unwrap();
+
+        assert_cross_hub_fanout(Arc::new(PgEventBus::new(pool))).await;
+    }
+}
@@ -0,0 +1,710 @@
+//! Encodage des trames gateway négocié à la connexion
+//! `?encoding=json|msgpack` (JSON par défaut) et `?compress=zlib-stream`
+
+use axum::extract::ws::Message;
+use flate2::{Compress, Compression, FlushCompress};
+use serde::Deserialize;
+
+use crate::web::ws::protocol::{ClientEvent, ServerFrame};
+
+/// Suffixe d'un flush zlib synchrone : marque la fin d'un message compressé
+pub const ZLIB_SUFFIX: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Format des trames (client → serveur et serveur → client)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    #[default]
+    Json,
+    #[serde(rename = "msgpack")]
+    MsgPack,
+}
+
+/// Compression des trames serveur → client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum GatewayCompression {
+    /// Un seul contexte zlib pour toute la connexion, chaque trame terminée par ZLIB_SUFFIX
+    #[serde(rename = "zlib-stream")]
+    ZlibStream,
+}
+
+/// Paramètres de query de `/ws`
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct GatewayParams {
+    #[serde(default)]
+    pub encoding: Encoding,
+    #[serde(default)]
+    pub compress: Option<GatewayCompression>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CodecError {
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("MessagePack encode error: {0}")]
+    MsgPackEncode(#[from] rmp_serde::encode::Error),
+    #[error("MessagePack decode error: {0}")]
+    MsgPackDecode(#[from] rmp_serde::decode::Error),
+    #[error("Compression error: {0}")]
+    Compress(#[from] flate2::CompressError),
+    #[error("Expected a {0} frame")]
+    UnexpectedFrame(&'static str),
+}
+
+impl Encoding {
+    /// Décode un événement client : texte en JSON, binaire en MessagePack
+    pub fn decode(self, msg: &Message) -> Result<ClientEvent, CodecError> {
+        match (self, msg) {
+            (Encoding::Json, Message::Text(text)) => Ok(ClientEvent::from_json(text)?),
+            (Encoding::MsgPack, Message::Binary(data)) => Ok(ClientEvent::from_msgpack(data)?),
+            (Encoding::Json, _) => Err(CodecError::UnexpectedFrame("text")),
+            (Encoding::MsgPack, _) => Err(CodecError::UnexpectedFrame("binary")),
+        }
+    }
+}
+
+/// Encodeur d'une connexion (garde le contexte zlib entre les trames)
+pub struct GatewayCodec {
+    encoding: Encoding,
+    deflate: Option<Compress>,
+}
+
+impl GatewayCodec {
+    pub fn new(params: GatewayParams) -> Self {
+        Self {
+            encoding: params.encoding,
+            deflate: params
+                .compress
+                .map(|GatewayCompression::ZlibStream| Compress::new(Compression::default(), true)),
+        }
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Encode une trame serveur : texte pour du JSON non compressé, binaire sinon
+    pub fn encode(&mut self, frame: &ServerFrame) -> Result<Message, CodecError> {
+        let payload = match self.encoding {
+            Encoding::Json => {
+                let json = frame.to_json()?;
+                if self.deflate.is_none() {
+                    return Ok(Message::Text(json.into()));
+                }
+                json.into_bytes()
+            }
+            Encoding::MsgPack => frame.to_msgpack()?,
+        };
+
+        let payload = match self.deflate.as_mut() {
+            Some(deflate) => deflate_sync(deflate, &payload)?,
+            None => payload,
+        };
+
+        Ok(Message::Binary(payload.into()))
+    }
+}
+
+/// Compresse `input` dans le flux zlib et vide le contexte (se termine par ZLIB_SUFFIX)
+fn deflate_sync(deflate: &mut Compress, input: &[u8]) -> Result<Vec<u8>, CodecError> {
+    let mut out = Vec::with_capacity(input.len() / 2 + 64);
+    let start = deflate.total_in();
+
+    loop {
+        let consumed = (deflate.total_in() - start) as usize;
+        deflate.compress_vec(&input[consumed..], &mut out, FlushCompress::Sync)?;
+
+        let consumed = (deflate.total_in() - start) as usize;
+        // Le flush est terminé quand tout est consommé et qu'il reste de la place en sortie
+        if consumed == input.len() && out.len() < out.capacity() {
+            debug_assert!(out.ends_with(&ZLIB_SUFFIX));
+            return Ok(out);
+        }
+        out.reserve(out.capacity().max(64));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use flate2::{Decompress, FlushDecompress};
+    use serde::Serialize;
+    use uuid::Uuid;
+
+    use crate::models::{
+        ChannelKind, MemberRole, MessagePreview, MessageReactionPublic, Permissions, Role, Thread,
+    };
+    use crate::web::ws::protocol::{ServerEvent, VoiceSignal};
+
+    /// Décodeur côté client : un contexte zlib pour toute la connexion
+    struct TestClient {
+        encoding: Encoding,
+        inflate: Option<Decompress>,
+    }
+
+    impl TestClient {
+        fn new(params: GatewayParams) -> Self {
+            Self {
+                encoding: params.encoding,
+                inflate: params.compress.map(|_| Decompress::new(true)),
+            }
+        }
+
+        fn decode(&mut self, msg: Message) -> serde_json::Value {
+            let bytes = match (msg, self.inflate.as_mut()) {
+                (Message::Text(text), None) => text.as_bytes().to_vec(),
+                (Message::Binary(data), Some(inflate)) => {
+                    assert!(data.ends_with(&ZLIB_SUFFIX), "missing zlib flush suffix");
+                    inflate_sync(inflate, &data)
+                }
+                (Message::Binary(data), None) => data.to_vec(),
+                (other, _) => panic!("unexpected frame: {:?}", other),
+            };
+
+            match self.encoding {
+                Encoding::Json => serde_json::from_slice(&bytes).unwrap(),
+                Encoding::MsgPack => serde_json::Value::deserialize(
+                    &mut rmp_serde::Deserializer::new(&bytes[..]).with_human_readable(),
+                )
+                .unwrap(),
+            }
+        }
+
+        fn encode(&self, event: &ClientEvent) -> Message {
+            match self.encoding {
+                Encoding::Json => Message::Text(serde_json::to_string(event).unwrap().into()),
+                Encoding::MsgPack => {
+                    let mut buf = Vec::new();
+                    event
+                        .serialize(
+                            &mut rmp_serde::Serializer::new(&mut buf)
+                                .with_struct_map()
+                                .with_human_readable(),
+                        )
+                        .unwrap();
+                    Message::Binary(buf.into())
+                }
+            }
+        }
+    }
+
+    fn inflate_sync(inflate: &mut Decompress, input: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(input.len() * 4 + 64);
+        let start = inflate.total_in();
+        loop {
+            let consumed = (inflate.total_in() - start) as usize;
+            inflate
+                .decompress_vec(&input[consumed..], &mut out, FlushDecompress::Sync)
+                .unwrap();
+            let consumed = (inflate.total_in() - start) as usize;
+            if consumed == input.len() && out.len() < out.capacity() {
+                return out;
+            }
+            out.reserve(out.capacity());
+        }
+    }
+
+    fn all_params() -> Vec<GatewayParams> {
+        let mut params = Vec::new();
+        for encoding in [Encoding::Json, Encoding::MsgPack] {
+            for compress in [None, Some(GatewayCompression::ZlibStream)] {
+                params.push(GatewayParams { encoding, compress });
+            }
+        }
+        params
+    }
+
+    /// Toutes les variantes client (le match garantit qu'une nouvelle variante est ajoutée ici)
+    fn client_events() -> Vec<ClientEvent> {
+        let id = Uuid::new_v4();
+        let nonce = Some("nonce-1".to_string());
+        let events = vec![
+            ClientEvent::Identify {
+                token: "token".to_string(),
+            },
+            ClientEvent::Resume {
+                token: "token".to_string(),
+                session_id: id,
+                seq: 42,
+            },
+            ClientEvent::SendMessage {
+                channel_id: id,
+                content: "héllo".to_string(),
+                nonce: nonce.clone(),
+                reply_to: Some(id),
+            },
+            ClientEvent::EditMessage {
+                message_id: id,
+                content: "edit".to_string(),
+                nonce: None,
+            },
+            ClientEvent::DeleteMessage {
+                message_id: id,
+                nonce: nonce.clone(),
+            },
+            ClientEvent::AddReaction {
+                message_id: id,
+                emoji: "👍".to_string(),
+                nonce: nonce.clone(),
+            },
+            ClientEvent::RemoveReaction {
+                message_id: id,
+                emoji: "👍".to_string(),
+                nonce: None,
+            },
+            ClientEvent::SendDirectMessage {
+                dm_id: id,
+                content: "dm".to_string(),
+                nonce,
+                reply_to: None,
+            },
+            ClientEvent::TypingStart { channel_id: id },
+            ClientEvent::TypingStop { channel_id: id },
+            ClientEvent::DirectMessageTypingStart { dm_id: id },
+            ClientEvent::DirectMessageTypingStop { dm_id: id },
+            ClientEvent::Heartbeat { seq: Some(7) },
+            ClientEvent::Subscribe { channel_id: id },
+            ClientEvent::Unsubscribe { channel_id: id },
+            ClientEvent::SubscribeServer { server_id: id },
+            ClientEvent::UnsubscribeServer { server_id: id },
+            ClientEvent::PresenceUpdate {
+                status: "dnd".to_string(),
+            },
+            ClientEvent::VoiceJoin { channel_id: id },
+            ClientEvent::VoiceLeave,
+            ClientEvent::VoiceSignal {
+                channel_id: id,
+                target_user_id: id,
+                signal: VoiceSignal::IceCandidate {
+                    candidate: serde_json::json!({ "candidate": "candidate:1", "sdpMLineIndex": 0 }),
+                },
+            },
+        ];
+
+        for event in &events {
+            match event {
+                ClientEvent::Identify { .. }
+                | ClientEvent::Resume { .. }
+                | ClientEvent::SendMessage { .. }
+                | ClientEvent::EditMessage { .. }
+                | ClientEvent::DeleteMessage { .. }
+                | ClientEvent::AddReaction { .. }
+                | ClientEvent::RemoveReaction { .. }
+                | ClientEvent::SendDirectMessage { .. }
+                | ClientEvent::TypingStart { .. }
+                | ClientEvent::TypingStop { .. }
+                | ClientEvent::DirectMessageTypingStart { .. }
+                | ClientEvent::DirectMessageTypingStop { .. }
+                | ClientEvent::Heartbeat { .. }
+                | ClientEvent::Subscribe { .. }
+                | ClientEvent::Unsubscribe { .. }
+                | ClientEvent::SubscribeServer { .. }
+                | ClientEvent::UnsubscribeServer { .. }
+                | ClientEvent::PresenceUpdate { .. }
+                | ClientEvent::VoiceJoin { .. }
+                | ClientEvent::VoiceLeave
+                | ClientEvent::VoiceSignal { .. } => {}
+            }
+        }
+        events
+    }
+
+    /// Toutes les variantes serveur (le match garantit qu'une nouvelle variante est ajoutée ici)
+    fn server_events() -> Vec<ServerEvent> {
+        let id = Uuid::new_v4();
+        let other_id = Uuid::new_v4();
+        let now = Utc::now();
+        let reactions = vec![MessageReactionPublic {
+            user_id: other_id,
+            emoji: "🎉".to_string(),
+            created_at: now,
+        }];
+        let role = Role {
+            id: other_id,
+            server_id: id,
+            name: "Modérateur".to_string(),
+            color: 0x3498DB,
+            position: 1,
+            permissions: Permissions::KICK_MEMBERS | Permissions::MANAGE_MESSAGES,
+            is_default: false,
+            created_at: now,
+            updated_at: now,
+        };
+        let thread = Thread {
+            id: other_id,
+            server_id: id,
+            parent_id: id,
+            parent_message_id: other_id,
+            owner_id: Some(id),
+            name: "Sortie de samedi".to_string(),
+            archived: false,
+            archived_at: None,
+            auto_archive_minutes: 1440,
+            last_activity_at: now,
+            rate_limit_per_user: 0,
+            created_at: now,
+        };
+        let events = vec![
+            ServerEvent::Hello {
+                heartbeat_interval: 30_000,
+            },
+            ServerEvent::Ready {
+                user_id: id,
+                username: "alice".to_string(),
+                session_id: other_id,
+            },
+            ServerEvent::Resumed {
+                session_id: id,
+                seq: 12,
+            },
+            ServerEvent::InvalidSession,
+            ServerEvent::Error {
+                code: "MESSAGE_ERROR".to_string(),
+                message: "boom".to_string(),
+                nonce: Some("n".to_string()),
+                retry_after: Some(3),
+            },
+            ServerEvent::Ack {
+                op: "SEND_MESSAGE".to_string(),
+                nonce: None,
+                id: Some(id),
+            },
+            ServerEvent::MessageCreate {
+                id,
+                channel_id: other_id,
+                server_id: other_id,
+                author_id: id,
+                username: "alice".to_string(),
+                nickname: Some("Alice du serveur".to_string()),
+                avatar_url: None,
+                content: "salut".to_string(),
+                created_at: now,
+                edited_at: None,
+                reactions: reactions.clone(),
+                nonce: Some("n".to_string()),
+                crossposted_from: Some(other_id),
+                reply_to: Some(MessagePreview {
+                    id: other_id,
+                    deleted: false,
+                    author_id: Some(other_id),
+                    username: Some("bob".to_string()),
+                    nickname: None,
+                    content: Some("cité".to_string()),
+                }),
+                mentions: vec![other_id],
+                mention_roles: vec![id],
+                mention_everyone: true,
+            },
+            ServerEvent::MessageUpdate {
+                id,
+                channel_id: other_id,
+                content: "edit".to_string(),
+                edited_at: now,
+            },
+            ServerEvent::MessageDelete {
+                id,
+                channel_id: other_id,
+            },
+            ServerEvent::MessageDeleteBulk {
+                ids: vec![id, other_id],
+                channel_id: other_id,
+            },
+            ServerEvent::MessageReactionUpdate {
+                id,
+                channel_id: other_id,
+                reactions: reactions.clone(),
+            },
+            ServerEvent::ChannelPinsUpdate {
+                channel_id: other_id,
+                message_id: id,
+                pinned_at: Some(now),
+            },
+            ServerEvent::DirectMessageCreate {
+                id,
+                dm_id: other_id,
+                author_id: id,
+                username: "bob".to_string(),
+                content: "dm".to_string(),
+                created_at: now,
+                edited_at: Some(now),
+                reactions: reactions.clone(),
+                nonce: None,
+                reply_to: None,
+            },
+            ServerEvent::DirectMessageUpdate {
+                id,
+                dm_id: other_id,
+                content: "edit".to_string(),
+                edited_at: now,
+            },
+            ServerEvent::DirectMessageDelete {
+                id,
+                dm_id: other_id,
+            },
+            ServerEvent::DirectMessageReactionUpdate {
+                id,
+                dm_id: other_id,
+                reactions,
+            },
+            ServerEvent::DirectMessagePinsUpdate {
+                dm_id: other_id,
+                message_id: id,
+                pinned_at: None,
+            },
+            ServerEvent::ChannelCreate {
+                id,
+                server_id: other_id,
+                name: "général".to_string(),
+                kind: ChannelKind::Category,
+                parent_id: None,
+                position: 0,
+                rate_limit_per_user: 0,
+                created_at: now,
+                updated_at: now,
+            },
+            ServerEvent::ChannelUpdate {
+                id,
+                server_id: other_id,
+                name: "annonces".to_string(),
+                kind: ChannelKind::Text,
+                parent_id: Some(id),
+                position: 3,
+                rate_limit_per_user: 10,
+                created_at: now,
+                updated_at: now,
+            },
+            ServerEvent::ChannelDelete {
+                id,
+                server_id: other_id,
+            },
+            ServerEvent::ThreadCreate {
+                thread: thread.clone(),
+            },
+            ServerEvent::ThreadUpdate { thread },
+            ServerEvent::ServerUpdate {
+                id,
+                name: "serveur".to_string(),
+                owner_id: other_id,
+                updated_at: now,
+            },
+            ServerEvent::ServerDelete { id },
+            ServerEvent::MemberAdd {
+                server_id: id,
+                user_id: other_id,
+                role: MemberRole::Member,
+                joined_at: now,
+                username: "carol".to_string(),
+                avatar_url: None,
+            },
+            ServerEvent::MemberUpdate {
+                server_id: id,
+                user_id: other_id,
+                role: MemberRole::Admin,
+                roles: vec![id],
+                communication_disabled_until: Some(now),
+                nickname: Some("Ali".to_string()),
+                server_avatar_url: Some("/files/avatar.png".to_string()),
+            },
+            ServerEvent::MemberRemove {
+                server_id: id,
+                user_id: other_id,
+            },
+            ServerEvent::BanAdd {
+                server_id: id,
+                user_id: other_id,
+                banned_by: id,
+                reason: Some("spam".to_string()),
+                expires_at: Some(now),
+                banned_at: now,
+            },
+            ServerEvent::BanRemove {
+                server_id: id,
+                user_id: other_id,
+            },
+            ServerEvent::RoleCreate { role: role.clone() },
+            ServerEvent::RoleUpdate { role },
+            ServerEvent::RoleDelete {
+                server_id: id,
+                role_id: other_id,
+            },
+            ServerEvent::TypingStart {
+                channel_id: id,
+                user_id: other_id,
+                username: "alice".to_string(),
+                nickname: None,
+            },
+            ServerEvent::TypingStop {
+                channel_id: id,
+                user_id: other_id,
+            },
+            ServerEvent::DirectMessageTypingStart {
+                dm_id: id,
+                user_id: other_id,
+                username: "bob".to_string(),
+            },
+            ServerEvent::DirectMessageTypingStop {
+                dm_id: id,
+                user_id: other_id,
+            },
+            ServerEvent::HeartbeatAck { seq: None },
+            ServerEvent::Subscribed { channel_id: id },
+            ServerEvent::Unsubscribed { channel_id: id },
+            ServerEvent::ServerSubscribed { server_id: id },
+            ServerEvent::ServerUnsubscribed { server_id: id },
+            ServerEvent::PresenceUpdate {
+                user_id: id,
+                status: "online".to_string(),
+            },
+            ServerEvent::VoiceStateUpdate {
+                server_id: id,
+                user_id: other_id,
+                channel_id: None,
+            },
+            ServerEvent::VoiceReady {
+                channel_id: id,
+                participants: vec![id, other_id],
+            },
+            ServerEvent::VoiceSignal {
+                channel_id: id,
+                from_user_id: other_id,
+                signal: VoiceSignal::Offer {
+                    sdp: "v=0".to_string(),
+                },
+            },
+        ];
+
+        for event in &events {
+            match event {
+                ServerEvent::Hello { .. }
+                | ServerEvent::Ready { .. }
+                | ServerEvent::Resumed { .. }
+                | ServerEvent::InvalidSession
+                | ServerEvent::Error { .. }
+                | ServerEvent::Ack { .. }
+                | ServerEvent::MessageCreate { .. }
+                | ServerEvent::MessageUpdate { .. }
+                | ServerEvent::MessageDelete { .. }
+                | ServerEvent::MessageDeleteBulk { .. }
+                | ServerEvent::MessageReactionUpdate { .. }
+                | ServerEvent::ChannelPinsUpdate { .. }
+                | ServerEvent::DirectMessageCreate { .. }
+                | ServerEvent::DirectMessageUpdate { .. }
+                | ServerEvent::DirectMessageDelete { .. }
+                | ServerEvent::DirectMessageReactionUpdate { .. }
+                | ServerEvent::DirectMessagePinsUpdate { .. }
+                | ServerEvent::ChannelCreate { .. }
+                | ServerEvent::ChannelUpdate { .. }
+                | ServerEvent::ChannelDelete { .. }
+                | ServerEvent::ThreadCreate { .. }
+                | ServerEvent::ThreadUpdate { .. }
+                | ServerEvent::ServerUpdate { .. }
+                | ServerEvent::ServerDelete { .. }
+                | ServerEvent::MemberAdd { .. }
+                | ServerEvent::MemberUpdate { .. }
+                | ServerEvent::MemberRemove { .. }
+                | ServerEvent::BanAdd { .. }
+                | ServerEvent::BanRemove { .. }
+                | ServerEvent::RoleCreate { .. }
+                | ServerEvent::RoleUpdate { .. }
+                | ServerEvent::RoleDelete { .. }
+                | ServerEvent::TypingStart { .. }
+                | ServerEvent::TypingStop { .. }
+                | ServerEvent::DirectMessageTypingStart { .. }
+                | ServerEvent::DirectMessageTypingStop { .. }
+                | ServerEvent::HeartbeatAck { .. }
+                | ServerEvent::Subscribed { .. }
+                | ServerEvent::Unsubscribed { .. }
+                | ServerEvent::ServerSubscribed { .. }
+                | ServerEvent::ServerUnsubscribed { .. }
+                | ServerEvent::PresenceUpdate { .. }
+                | ServerEvent::VoiceStateUpdate { .. }
+                | ServerEvent::VoiceReady { .. }
+                | ServerEvent::VoiceSignal { .. } => {}
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn parses_gateway_params_from_query() {
+        let params: GatewayParams = serde_json::from_value(serde_json::json!({
+            "encoding": "msgpack",
+            "compress": "zlib-stream",
+        }))
+        .unwrap();
+        assert_eq!(params.encoding, Encoding::MsgPack);
+        assert_eq!(params.compress, Some(GatewayCompression::ZlibStream));
+
+        let default: GatewayParams = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert_eq!(default.encoding, Encoding::Json);
+        assert_eq!(default.compress, None);
+
+        assert!(
+            serde_json::from_value::<GatewayParams>(serde_json::json!({ "encoding": "xml" }))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn round_trips_every_server_event() {
+        for params in all_params() {
+            // Un codec et un client par connexion : le contexte zlib couvre toutes les trames
+            let mut codec = GatewayCodec::new(params);
+            let mut client = TestClient::new(params);
+
+            for (seq, event) in server_events().into_iter().enumerate() {
+                let expected = serde_json::to_value(&event).unwrap();
+                let mut frame = ServerFrame::new(event);
+                frame.s = Some(seq as u64);
+
+                let msg = codec.encode(&frame).unwrap();
+                if params.encoding == Encoding::Json && params.compress.is_none() {
+                    assert!(matches!(msg, Message::Text(_)));
+                } else {
+                    assert!(matches!(msg, Message::Binary(_)));
+                }
+
+                let mut decoded = client.decode(msg);
+                assert_eq!(decoded["s"], serde_json::json!(seq), "{:?}", params);
+                decoded.as_object_mut().unwrap().remove("s");
+                assert_eq!(decoded, expected, "{:?}", params);
+
+                // La trame décodée redevient un ServerEvent identique
+                let reparsed: ServerEvent = serde_json::from_value(decoded).unwrap();
+                assert_eq!(serde_json::to_value(&reparsed).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_every_client_event() {
+        for params in all_params() {
+            let client = TestClient::new(params);
+
+            for event in client_events() {
+                let decoded = params.encoding.decode(&client.encode(&event)).unwrap();
+                assert_eq!(
+                    serde_json::to_value(&decoded).unwrap(),
+                    serde_json::to_value(&event).unwrap(),
+                    "{:?}",
+                    params
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_frames_of_the_wrong_type() {
+        let heartbeat = ClientEvent::Heartbeat { seq: None };
+        let json = TestClient::new(GatewayParams::default()).encode(&heartbeat);
+        let msgpack = TestClient::new(GatewayParams {
+            encoding: Encoding::MsgPack,
+            compress: None,
+        })
+        .encode(&heartbeat);
+
+        assert!(Encoding::MsgPack.decode(&json).is_err());
+        assert!(Encoding::Json.decode(&msgpack).is_err());
+    }
+}
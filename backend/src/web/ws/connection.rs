@@ -1,37 +1,70 @@
 //! Gestion d'une connexion WebSocket individuelle
 //! Read loop, write loop, heartbeat, backpressure
 
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
 use futures_util::{SinkExt, StreamExt};
-use std::time::{Duration, Instant};
-use tokio::sync::{broadcast, mpsc};
-use tokio::time::interval;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::time::{interval, sleep_until, Instant};
 use uuid::Uuid;
 
+use crate::web::ws::codec::{GatewayCodec, GatewayParams};
 use crate::web::ws::hub::WsHub;
-use crate::web::ws::protocol::{ClientEvent, ServerEvent};
+use crate::web::ws::protocol::{close_code, ClientEvent, ServerEvent, ServerFrame};
 
 /// Configuration d'une connexion
-const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
 const MAX_MESSAGE_SIZE: usize = 1024 * 64; // 64KB max
 
+/// Configuration du heartbeat applicatif
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatConfig {
+    /// Intervalle annoncé au client dans HELLO
+    pub interval: Duration,
+    /// Nombre d'intervalles tolérés sans HEARTBEAT avant fermeture
+    pub grace_multiplier: u32,
+}
+
+impl HeartbeatConfig {
+    /// Délai maximum sans HEARTBEAT avant de considérer la connexion morte
+    pub fn timeout(&self) -> Duration {
+        self.interval * self.grace_multiplier.max(1)
+    }
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            grace_multiplier: 2,
+        }
+    }
+}
+
 /// État d'une connexion WebSocket
 pub struct WsConnection {
     conn_id: Uuid,
     hub: WsHub,
     user_id: Option<Uuid>,
-    #[allow(dead_code)] // Réservé pour future implémentation de timeout
+    heartbeat: HeartbeatConfig,
+    params: GatewayParams,
     last_heartbeat: Instant,
     #[allow(dead_code)] // Réservé pour future validation
     authenticated: bool,
 }
 
 impl WsConnection {
-    pub fn new(conn_id: Uuid, hub: WsHub) -> Self {
+    pub fn new(
+        conn_id: Uuid,
+        hub: WsHub,
+        heartbeat: HeartbeatConfig,
+        params: GatewayParams,
+    ) -> Self {
         Self {
             conn_id,
             hub,
             user_id: None,
+            heartbeat,
+            params,
             last_heartbeat: Instant::now(),
             authenticated: false,
         }
@@ -44,10 +77,10 @@ impl WsConnection {
         let (tx, rx) = mpsc::channel(100);
 
         // Channel pour envoyer des messages depuis read_task vers write_task
-        let (error_tx, mut error_rx) = mpsc::channel::<String>(10);
+        let (error_tx, mut error_rx) = mpsc::channel::<ServerEvent>(10);
 
         // Enregistrer la connexion dans le Hub
-        let mut hub_rx = self.hub.register(self.conn_id).await;
+        let mut queue = self.hub.register(self.conn_id).await;
 
         // Split read/write
         let (mut sender, mut receiver) = socket.split();
@@ -56,30 +89,63 @@ impl WsConnection {
         let hub = self.hub.clone();
         let user_id = self.user_id;
 
+        // Dernier HEARTBEAT reçu (mis à jour par read_task, surveillé par write_task)
+        let (heartbeat_tx, heartbeat_rx) = watch::channel(self.last_heartbeat);
+        let heartbeat_timeout = self.heartbeat.timeout();
+
+        // Encodage négocié à l'upgrade (JSON par défaut)
+        let mut codec = GatewayCodec::new(self.params);
+        let encoding = codec.encoding();
+
         // Spawn write loop (envoi des messages du Hub)
+        let ping_interval = self.heartbeat.interval;
         let mut write_task = tokio::spawn(async move {
-            let mut interval = interval(HEARTBEAT_INTERVAL);
+            let mut interval = interval(ping_interval);
             loop {
+                let heartbeat_deadline = *heartbeat_rx.borrow() + heartbeat_timeout;
                 tokio::select! {
                     // Message du Hub à envoyer
-                    result = hub_rx.recv() => {
+                    result = queue.frames.recv() => {
                         match result {
-                            Ok(msg) => {
-                                if sender.send(Message::Text(msg.into())).await.is_err() {
+                            Some(frame) => {
+                                let msg = match codec.encode(&frame) {
+                                    Ok(msg) => msg,
+                                    Err(e) => {
+                                        tracing::error!("[WS] Failed to encode frame for {}: {}", conn_id, e);
+                                        continue;
+                                    }
+                                };
+                                if sender.send(msg).await.is_err() {
                                     break; // Connexion fermée
                                 }
                             }
-                            Err(broadcast::error::RecvError::Closed) => break,
-                            Err(broadcast::error::RecvError::Lagged(skipped)) => {
-                                tracing::warn!("[WS] Connection {} lagged, skipped {} messages", conn_id, skipped);
-                            }
+                            None => break,
                         }
                     }
+                    // Évincé par le Hub : file d'envoi saturée (ou connexion remplacée par un RESUME)
+                    evicted = &mut queue.evicted => {
+                        if evicted.is_ok() {
+                            let _ = sender
+                                .send(Message::Close(Some(CloseFrame {
+                                    code: close_code::SLOW_CONSUMER,
+                                    reason: "Slow consumer, reconnect and resume".into(),
+                                })))
+                                .await;
+                        }
+                        break;
+                    }
                     // Message d'erreur depuis read_task
                     error_msg = error_rx.recv() => {
                         match error_msg {
-                            Some(msg) => {
-                                if sender.send(Message::Text(msg.into())).await.is_err() {
+                            Some(event) => {
+                                let msg = match codec.encode(&ServerFrame::new(event)) {
+                                    Ok(msg) => msg,
+                                    Err(e) => {
+                                        tracing::error!("[WS] Failed to encode error for {}: {}", conn_id, e);
+                                        continue;
+                                    }
+                                };
+                                if sender.send(msg).await.is_err() {
                                     break;
                                 }
                             }
@@ -92,6 +158,25 @@ impl WsConnection {
                             break;
                         }
                     }
+                    // Aucun HEARTBEAT depuis trop longtemps : connexion zombie
+                    _ = sleep_until(heartbeat_deadline) => {
+                        if *heartbeat_rx.borrow() + heartbeat_timeout > Instant::now() {
+                            continue; // HEARTBEAT reçu entre-temps
+                        }
+
+                        tracing::warn!(
+                            "[WS] Connection {} missed heartbeats for {:?}, closing",
+                            conn_id,
+                            heartbeat_timeout
+                        );
+                        let _ = sender
+                            .send(Message::Close(Some(CloseFrame {
+                                code: close_code::HEARTBEAT_TIMEOUT,
+                                reason: "Heartbeat timeout".into(),
+                            })))
+                            .await;
+                        break;
+                    }
                 }
             }
         });
@@ -102,45 +187,49 @@ impl WsConnection {
         let mut read_task = tokio::spawn(async move {
             while let Some(msg) = receiver.next().await {
                 match msg {
-                    Ok(Message::Text(text)) => {
-                        tracing::debug!(
-                            "[WS] Received text message from {}: {}",
-                            conn_id_clone,
-                            &text[..text.len().min(100)]
-                        );
-                        if text.len() > MAX_MESSAGE_SIZE {
+                    Ok(msg @ (Message::Text(_) | Message::Binary(_))) => {
+                        let size = match &msg {
+                            Message::Text(text) => text.len(),
+                            Message::Binary(data) => data.len(),
+                            _ => 0,
+                        };
+                        if size > MAX_MESSAGE_SIZE {
                             tracing::warn!("[WS] Message too large from {}", conn_id_clone);
                             continue;
                         }
 
-                        // Parser l'événement client
-                        match ClientEvent::from_json(&text) {
+                        // Parser l'événement client selon l'encodage négocié
+                        match encoding.decode(&msg) {
                             Ok(event) => {
                                 tracing::debug!(
                                     "[WS] Parsed event from {}: {:?}",
                                     conn_id_clone,
                                     event
                                 );
+                                if matches!(event, ClientEvent::Heartbeat { .. }) {
+                                    heartbeat_tx.send_replace(Instant::now());
+                                }
                                 // Envoyer au handler principal via channel
                                 if tx.send((conn_id_clone, event)).await.is_err() {
                                     break; // Handler fermé
                                 }
                             }
                             Err(e) => {
-                                tracing::warn!("[WS] Invalid JSON from {}: {}", conn_id_clone, e);
+                                tracing::warn!(
+                                    "[WS] Invalid payload from {}: {}",
+                                    conn_id_clone,
+                                    e
+                                );
                                 let error = ServerEvent::Error {
-                                    code: "INVALID_JSON".to_string(),
-                                    message: format!("Invalid JSON: {}", e),
+                                    code: "INVALID_PAYLOAD".to_string(),
+                                    message: format!("Invalid payload: {}", e),
+                                    nonce: None,
+                                    retry_after: None,
                                 };
-                                if let Ok(json) = error.to_json() {
-                                    let _ = error_tx_clone.send(json).await;
-                                }
+                                let _ = error_tx_clone.send(error).await;
                             }
                         }
                     }
-                    Ok(Message::Binary(_)) => {
-                        tracing::warn!("[WS] Binary messages not supported");
-                    }
                     Ok(Message::Ping(_)) => {
                         // Ping géré automatiquement par axum
                         // Pas besoin de répondre manuellement
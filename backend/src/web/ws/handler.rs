@@ -1,38 +1,50 @@
 //! Handler WebSocket Axum : upgrade HTTP → WS + gestion des événements
 
-use axum::extract::{ws::WebSocketUpgrade, State};
+use axum::extract::{ws::WebSocketUpgrade, Query, State};
 use axum::response::Response;
 use uuid::Uuid;
 
+use crate::error::Error;
+use crate::models::CreateDMMessagePayload;
 use crate::services::verify_token;
+use crate::web::ws::codec::GatewayParams;
 use crate::web::ws::connection::WsConnection;
 use crate::web::ws::hub::WsHub;
 use crate::web::ws::protocol::{ClientEvent, ServerEvent};
 use crate::AppState;
 
 /// Handler principal pour l'upgrade WebSocket
-pub async fn ws_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> Response {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+/// `?encoding=json|msgpack&compress=zlib-stream` choisit le format des trames
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<GatewayParams>,
+    State(state): State<AppState>,
+) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(socket, state, params))
 }
 
 /// Gère une connexion WebSocket après upgrade
-async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
+async fn handle_socket(
+    socket: axum::extract::ws::WebSocket,
+    state: AppState,
+    params: GatewayParams,
+) {
     let conn_id = Uuid::new_v4();
-    tracing::info!("[WS] New connection: {}", conn_id);
+    tracing::info!("[WS] New connection: {} ({:?})", conn_id, params);
 
     // Enregistrer la connexion dans les métriques
     state.ws_metrics.on_connection().await;
 
     // Créer la connexion
     let hub = state.ws_hub.clone();
-    let connection = WsConnection::new(conn_id, hub.clone());
+    let connection = WsConnection::new(conn_id, hub.clone(), state.ws_heartbeat, params);
 
     // Démarrer la connexion et obtenir le channel d'événements
     let mut event_rx = connection.handle(socket).await;
 
     // Envoyer HELLO immédiatement
     let hello = ServerEvent::Hello {
-        heartbeat_interval: 30000, // 30s
+        heartbeat_interval: state.ws_heartbeat.interval.as_millis() as u64,
     };
     hub.send_to_connection(conn_id, &hello).await;
 
@@ -47,6 +59,11 @@ async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
 
         match event {
             ClientEvent::Identify { token } => {
+                if authenticated {
+                    send_error(&hub, conn_id, "ALREADY_AUTHENTICATED", "Already identified").await;
+                    continue;
+                }
+
                 // Vérifier le token
                 match verify_token(&token, &state.jwt_secret) {
                     Ok(claims) => {
@@ -57,6 +74,20 @@ async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
                                 user_id = Some(claims.sub);
                                 authenticated = true;
                                 hub.associate_user(conn_id, claims.sub).await;
+                                let session_id = hub.create_session(conn_id, claims.sub).await;
+
+                                // Recevoir les événements de tous les serveurs de l'utilisateur
+                                if let Err(e) = crate::services::realtime::subscribe_user_servers(
+                                    &state, conn_id, claims.sub,
+                                )
+                                .await
+                                {
+                                    tracing::error!(
+                                        "[WS] Server auto-subscribe failed for user {}: {}",
+                                        claims.sub,
+                                        e
+                                    );
+                                }
 
                                 // Marquer l'utilisateur comme en ligne
                                 crate::services::realtime::handle_user_online(&state, claims.sub)
@@ -66,6 +97,7 @@ async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
                                 let ready = ServerEvent::Ready {
                                     user_id: claims.sub,
                                     username: user.username.clone(),
+                                    session_id,
                                 };
                                 send_to_connection(&hub, conn_id, &ready).await;
 
@@ -90,9 +122,57 @@ async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
                     }
                 }
             }
+            ClientEvent::Resume {
+                token,
+                session_id,
+                seq,
+            } => {
+                if authenticated {
+                    send_error(&hub, conn_id, "ALREADY_AUTHENTICATED", "Already identified").await;
+                    continue;
+                }
+
+                // Seul le propriétaire de la session peut la reprendre
+                let resumed = match verify_token(&token, &state.jwt_secret) {
+                    Ok(claims) => {
+                        hub.resume_session(conn_id, session_id, seq, claims.sub)
+                            .await
+                    }
+                    Err(_) => None,
+                };
+
+                // Rejoue les événements manqués puis confirme la reprise
+                match resumed {
+                    Some(resumed) => {
+                        user_id = Some(resumed.user_id);
+                        authenticated = true;
+
+                        crate::services::realtime::handle_user_online(&state, resumed.user_id)
+                            .await;
+
+                        let event = ServerEvent::Resumed {
+                            session_id,
+                            seq: resumed.seq,
+                        };
+                        send_to_connection(&hub, conn_id, &event).await;
+
+                        tracing::info!(
+                            "[WS] Connection {} resumed session {} for user {}",
+                            conn_id,
+                            session_id,
+                            resumed.user_id
+                        );
+                    }
+                    None => {
+                        send_to_connection(&hub, conn_id, &ServerEvent::InvalidSession).await;
+                    }
+                }
+            }
             ClientEvent::SendMessage {
                 channel_id,
                 content,
+                nonce,
+                reply_to,
             } => {
                 if !authenticated {
                     send_error(&hub, conn_id, "NOT_AUTHENTICATED", "Must identify first").await;
@@ -102,13 +182,107 @@ async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
                 let uid = user_id.expect("User ID should be set after authentication check");
 
                 // Déléguer au service realtime
-                if let Err(e) =
-                    crate::services::realtime::handle_send_message(&state, uid, channel_id, content)
+                let result = crate::services::realtime::handle_send_message(
+                    &state,
+                    uid,
+                    channel_id,
+                    content,
+                    nonce.clone(),
+                    reply_to,
+                )
+                .await
+                .map(|message| Some(message.id));
+                send_op_result(&hub, conn_id, "SEND_MESSAGE", nonce, result).await;
+            }
+            ClientEvent::EditMessage {
+                message_id,
+                content,
+                nonce,
+            } => {
+                if !authenticated {
+                    send_error(&hub, conn_id, "NOT_AUTHENTICATED", "Must identify first").await;
+                    continue;
+                }
+
+                let uid = user_id.expect("User ID should be set after authentication check");
+                let result = crate::services::realtime::handle_edit_message(
+                    &state, uid, message_id, content,
+                )
+                .await
+                .map(|message| Some(message.id));
+                send_op_result(&hub, conn_id, "EDIT_MESSAGE", nonce, result).await;
+            }
+            ClientEvent::DeleteMessage { message_id, nonce } => {
+                if !authenticated {
+                    send_error(&hub, conn_id, "NOT_AUTHENTICATED", "Must identify first").await;
+                    continue;
+                }
+
+                let uid = user_id.expect("User ID should be set after authentication check");
+                let result =
+                    crate::services::realtime::handle_delete_message(&state, uid, message_id)
                         .await
-                {
-                    tracing::error!("[WS] Error sending message: {}", e);
-                    send_error(&hub, conn_id, "MESSAGE_ERROR", &e.to_string()).await;
+                        .map(|_| Some(message_id));
+                send_op_result(&hub, conn_id, "DELETE_MESSAGE", nonce, result).await;
+            }
+            ClientEvent::AddReaction {
+                message_id,
+                emoji,
+                nonce,
+            } => {
+                if !authenticated {
+                    send_error(&hub, conn_id, "NOT_AUTHENTICATED", "Must identify first").await;
+                    continue;
                 }
+
+                let uid = user_id.expect("User ID should be set after authentication check");
+                let result =
+                    crate::services::realtime::handle_add_reaction(&state, uid, message_id, emoji)
+                        .await
+                        .map(|message| Some(message.id));
+                send_op_result(&hub, conn_id, "ADD_REACTION", nonce, result).await;
+            }
+            ClientEvent::RemoveReaction {
+                message_id,
+                emoji,
+                nonce,
+            } => {
+                if !authenticated {
+                    send_error(&hub, conn_id, "NOT_AUTHENTICATED", "Must identify first").await;
+                    continue;
+                }
+
+                let uid = user_id.expect("User ID should be set after authentication check");
+                let result = crate::services::realtime::handle_remove_reaction(
+                    &state, uid, message_id, emoji,
+                )
+                .await
+                .map(|message| Some(message.id));
+                send_op_result(&hub, conn_id, "REMOVE_REACTION", nonce, result).await;
+            }
+            ClientEvent::SendDirectMessage {
+                dm_id,
+                content,
+                nonce,
+                reply_to,
+            } => {
+                if !authenticated {
+                    send_error(&hub, conn_id, "NOT_AUTHENTICATED", "Must identify first").await;
+                    continue;
+                }
+
+                let uid = user_id.expect("User ID should be set after authentication check");
+                let payload = CreateDMMessagePayload {
+                    content,
+                    nonce: nonce.clone(),
+                    reply_to,
+                };
+                let result = crate::services::realtime::handle_send_direct_message(
+                    &state, uid, dm_id, payload,
+                )
+                .await
+                .map(|message| Some(message.id));
+                send_op_result(&hub, conn_id, "SEND_DIRECT_MESSAGE", nonce, result).await;
             }
             ClientEvent::TypingStart { channel_id } => {
                 if !authenticated {
@@ -146,6 +320,42 @@ async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
                     send_error(&hub, conn_id, "TYPING_FORBIDDEN", &e.to_string()).await;
                 }
             }
+            ClientEvent::DirectMessageTypingStart { dm_id } => {
+                if !authenticated {
+                    continue;
+                }
+
+                let uid = user_id.expect("User ID should be set after authentication check");
+                if let Err(e) =
+                    crate::services::realtime::handle_dm_typing_start(&state, uid, dm_id).await
+                {
+                    tracing::warn!(
+                        "[WS] DM TypingStart denied for user {} on dm {}: {}",
+                        uid,
+                        dm_id,
+                        e
+                    );
+                    send_error(&hub, conn_id, "TYPING_FORBIDDEN", &e.to_string()).await;
+                }
+            }
+            ClientEvent::DirectMessageTypingStop { dm_id } => {
+                if !authenticated {
+                    continue;
+                }
+
+                let uid = user_id.expect("User ID should be set after authentication check");
+                if let Err(e) =
+                    crate::services::realtime::handle_dm_typing_stop(&state, uid, dm_id).await
+                {
+                    tracing::warn!(
+                        "[WS] DM TypingStop denied for user {} on dm {}: {}",
+                        uid,
+                        dm_id,
+                        e
+                    );
+                    send_error(&hub, conn_id, "TYPING_FORBIDDEN", &e.to_string()).await;
+                }
+            }
             ClientEvent::Heartbeat { seq } => {
                 let ack = ServerEvent::HeartbeatAck { seq };
                 send_to_connection(&hub, conn_id, &ack).await;
@@ -157,20 +367,18 @@ async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
                 }
 
                 let uid = user_id.expect("User ID should be set after authentication check");
-                let can_subscribe = match state.channel_repo.find_by_id(channel_id).await {
-                    Ok(Some(channel)) => {
-                        match state.server_repo.find_member(channel.server_id, uid).await {
-                            Ok(Some(_)) => true,
-                            Ok(None) => false,
-                            Err(e) => {
-                                tracing::error!("[WS] Subscribe membership check failed: {}", e);
-                                false
-                            }
-                        }
-                    }
-                    Ok(None) => false,
+                let can_subscribe = match crate::services::channels::get_channel(
+                    &state.server_repo,
+                    &state.channel_repo,
+                    channel_id,
+                    uid,
+                )
+                .await
+                {
+                    Ok(_) => true,
+                    Err(Error::ChannelNotFound | Error::ChannelForbidden) => false,
                     Err(e) => {
-                        tracing::error!("[WS] Subscribe channel check failed: {}", e);
+                        tracing::error!("[WS] Subscribe permission check failed: {}", e);
                         false
                     }
                 };
@@ -195,6 +403,42 @@ async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
                 let unsubscribed = ServerEvent::Unsubscribed { channel_id };
                 send_to_connection(&hub, conn_id, &unsubscribed).await;
             }
+            ClientEvent::SubscribeServer { server_id } => {
+                if !authenticated {
+                    send_error(&hub, conn_id, "NOT_AUTHENTICATED", "Must identify first").await;
+                    continue;
+                }
+
+                let uid = user_id.expect("User ID should be set after authentication check");
+                if let Err(e) = crate::services::realtime::handle_subscribe_server(
+                    &state, conn_id, uid, server_id,
+                )
+                .await
+                {
+                    tracing::warn!(
+                        "[WS] SubscribeServer denied for user {} on server {}: {}",
+                        uid,
+                        server_id,
+                        e
+                    );
+                    send_error(
+                        &hub,
+                        conn_id,
+                        "SUBSCRIBE_FORBIDDEN",
+                        "Server access forbidden",
+                    )
+                    .await;
+                    continue;
+                }
+
+                let subscribed = ServerEvent::ServerSubscribed { server_id };
+                send_to_connection(&hub, conn_id, &subscribed).await;
+            }
+            ClientEvent::UnsubscribeServer { server_id } => {
+                hub.unsubscribe_server(conn_id, server_id).await;
+                let unsubscribed = ServerEvent::ServerUnsubscribed { server_id };
+                send_to_connection(&hub, conn_id, &unsubscribed).await;
+            }
             ClientEvent::PresenceUpdate { status } => {
                 if !authenticated {
                     send_error(&hub, conn_id, "NOT_AUTHENTICATED", "Must identify first").await;
@@ -204,19 +448,83 @@ async fn handle_socket(socket: axum::extract::ws::WebSocket, state: AppState) {
                 let uid = user_id.expect("User ID should be set after authentication check");
                 crate::services::realtime::handle_presence_update(&state, uid, status).await;
             }
+            ClientEvent::VoiceJoin { channel_id } => {
+                if !authenticated {
+                    send_error(&hub, conn_id, "NOT_AUTHENTICATED", "Must identify first").await;
+                    continue;
+                }
+
+                let uid = user_id.expect("User ID should be set after authentication check");
+                if let Err(e) =
+                    crate::services::realtime::handle_voice_join(&state, conn_id, uid, channel_id)
+                        .await
+                {
+                    tracing::warn!(
+                        "[WS] VoiceJoin denied for user {} on channel {}: {}",
+                        uid,
+                        channel_id,
+                        e
+                    );
+                    send_error(&hub, conn_id, "VOICE_FORBIDDEN", &e.to_string()).await;
+                }
+            }
+            ClientEvent::VoiceLeave => {
+                if !authenticated {
+                    continue;
+                }
+
+                let uid = user_id.expect("User ID should be set after authentication check");
+                crate::services::realtime::handle_voice_leave(&state, uid).await;
+            }
+            ClientEvent::VoiceSignal {
+                channel_id,
+                target_user_id,
+                signal,
+            } => {
+                if !authenticated {
+                    send_error(&hub, conn_id, "NOT_AUTHENTICATED", "Must identify first").await;
+                    continue;
+                }
+
+                let uid = user_id.expect("User ID should be set after authentication check");
+                if let Err(e) = crate::services::realtime::handle_voice_signal(
+                    &state,
+                    uid,
+                    channel_id,
+                    target_user_id,
+                    signal,
+                )
+                .await
+                {
+                    tracing::warn!(
+                        "[WS] VoiceSignal rejected for user {} on channel {}: {}",
+                        uid,
+                        channel_id,
+                        e
+                    );
+                    send_error(&hub, conn_id, "VOICE_SIGNAL_REJECTED", &e.to_string()).await;
+                }
+            }
         }
     }
 
-    // Marquer l'utilisateur comme hors ligne avant de nettoyer
-    if let Some(uid) = user_id {
-        crate::services::realtime::handle_user_offline(&state, uid).await;
+    // Fin de connexion (fermeture client, erreur ou timeout heartbeat)
+    if user_id.is_some() {
+        crate::services::realtime::handle_voice_disconnect(&state, conn_id).await;
     }
 
     // Enregistrer la déconnexion dans les métriques
     state.ws_metrics.on_disconnection().await;
 
-    // Nettoyer la connexion
+    // Nettoyer la connexion (la session reste disponible pour un RESUME)
     hub.unregister(conn_id, user_id).await;
+
+    // Hors ligne seulement si aucune autre connexion (autre appareil ou session reprise) n'est ouverte
+    if let Some(uid) = user_id {
+        if !hub.is_user_connected(uid).await {
+            crate::services::realtime::handle_user_offline(&state, uid).await;
+        }
+    }
 }
 
 /// Envoie un événement à une connexion spécifique
@@ -229,6 +537,39 @@ async fn send_error(hub: &WsHub, conn_id: Uuid, code: &str, message: &str) {
     let error = ServerEvent::Error {
         code: code.to_string(),
         message: message.to_string(),
+        nonce: None,
+        retry_after: None,
     };
     hub.send_to_connection(conn_id, &error).await;
 }
+
+/// Répond à une opération client : ACK en cas de succès, ERROR sinon (même nonce)
+async fn send_op_result(
+    hub: &WsHub,
+    conn_id: Uuid,
+    op: &str,
+    nonce: Option<String>,
+    result: crate::Result<Option<Uuid>>,
+) {
+    let event = match result {
+        Ok(id) => ServerEvent::Ack {
+            op: op.to_string(),
+            nonce,
+            id,
+        },
+        Err(e) => {
+            tracing::warn!("[WS] {} failed on connection {}: {}", op, conn_id, e);
+            let (code, retry_after) = match e {
+                crate::Error::SlowmodeActive { retry_after } => ("SLOWMODE", Some(retry_after)),
+                _ => ("MESSAGE_ERROR", None),
+            };
+            ServerEvent::Error {
+                code: code.to_string(),
+                message: e.to_string(),
+                nonce,
+                retry_after,
+            }
+        }
+    };
+    hub.send_to_connection(conn_id, &event).await;
+}
@@ -1,60 +1,229 @@
 //! Hub WebSocket : gestion centralisée des connexions et broadcasts
 //! Pattern singleton partagé via Arc<Mutex<>> dans AppState
+//! Les broadcasts sont livrés localement puis publiés sur l'EventBus pour les autres instances
 
+use futures::StreamExt;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::{broadcast, Mutex};
+use std::time::Instant;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot, Mutex};
 use uuid::Uuid;
 
-use crate::web::ws::protocol::ServerEvent;
+use crate::web::ws::bus::{BusEnvelope, BusError, BusMessage, EventBus, InMemoryEventBus};
+use crate::web::ws::metrics::WsMetrics;
+use crate::web::ws::protocol::{ServerEvent, ServerFrame};
+use crate::web::ws::session::{SessionId, WsSession, REPLAY_BUFFER_SIZE};
+use crate::web::ws::voice::{VoiceState, VoiceStates};
 
 /// ID unique d'une connexion WebSocket
 pub type ConnectionId = Uuid;
 
+/// Taille de la file d'envoi d'une connexion (doit pouvoir contenir un replay complet)
+const CONNECTION_BUFFER_SIZE: usize = 256;
+
+/// Trames en attente au-delà desquelles le client est jugé trop lent et déconnecté.
+/// Inférieur au buffer de replay : le client peut reprendre sa session sans trou.
+const SLOW_CONSUMER_THRESHOLD: usize = 100;
+
+const _: () = assert!(
+    SLOW_CONSUMER_THRESHOLD < REPLAY_BUFFER_SIZE && REPLAY_BUFFER_SIZE <= CONNECTION_BUFFER_SIZE
+);
+
+/// Côté hub d'une connexion : file d'envoi bornée + signal d'éviction
+struct ConnectionHandle {
+    queue: mpsc::Sender<ServerFrame>,
+    evict: oneshot::Sender<()>,
+}
+
+/// Côté connexion : trames à écrire sur le socket
+/// `evicted` reçoit `Ok(())` si le client est trop lent, `Err` si le hub a oublié la connexion
+pub struct ConnectionQueue {
+    pub frames: mpsc::Receiver<ServerFrame>,
+    pub evicted: oneshot::Receiver<()>,
+}
+
+/// Session reprise avec succès via RESUME
+pub struct ResumedSession {
+    pub user_id: Uuid,
+    pub seq: u64,
+}
+
 /// Hub central pour gérer toutes les connexions WebSocket
 #[derive(Clone)]
 pub struct WsHub {
-    /// Connexions actives : ConnectionId -> Sender pour envoyer des messages
-    connections: Arc<Mutex<HashMap<ConnectionId, broadcast::Sender<String>>>>,
+    /// Connexions actives : ConnectionId -> file d'envoi
+    connections: Arc<Mutex<HashMap<ConnectionId, ConnectionHandle>>>,
 
     /// Subscriptions : ChannelId -> Set de ConnectionId
     subscriptions: Arc<Mutex<HashMap<Uuid, HashSet<ConnectionId>>>>,
 
+    /// Subscriptions serveur : ServerId -> Set de ConnectionId (tous les channels du serveur)
+    server_subscriptions: Arc<Mutex<HashMap<Uuid, HashSet<ConnectionId>>>>,
+
     /// Connexions par utilisateur : UserId -> Set de ConnectionId (multi-device)
     user_connections: Arc<Mutex<HashMap<Uuid, HashSet<ConnectionId>>>>,
+
+    /// Sessions gateway : ConnectionId (attachée ou détachée) -> Session
+    sessions: Arc<Mutex<HashMap<ConnectionId, WsSession>>>,
+
+    /// Présences vocales de toutes les instances (répliquées via le bus)
+    voice_states: Arc<Mutex<VoiceStates>>,
+
+    /// Compteurs de trames perdues et de clients trop lents
+    metrics: WsMetrics,
+
+    /// Identifiant de cette instance (ignore ses propres messages sur le bus)
+    instance_id: Uuid,
+
+    /// Fan-out vers les autres instances
+    bus: Arc<dyn EventBus>,
 }
 
 impl WsHub {
     pub fn new() -> Self {
+        Self::with_metrics(WsMetrics::new())
+    }
+
+    pub fn with_metrics(metrics: WsMetrics) -> Self {
+        Self::with_event_bus(metrics, Arc::new(InMemoryEventBus::new()))
+    }
+
+    pub fn with_event_bus(metrics: WsMetrics, bus: Arc<dyn EventBus>) -> Self {
         Self {
             connections: Arc::new(Mutex::new(HashMap::new())),
             subscriptions: Arc::new(Mutex::new(HashMap::new())),
+            server_subscriptions: Arc::new(Mutex::new(HashMap::new())),
             user_connections: Arc::new(Mutex::new(HashMap::new())),
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            voice_states: Arc::new(Mutex::new(VoiceStates::default())),
+            metrics,
+            instance_id: Uuid::new_v4(),
+            bus,
+        }
+    }
+
+    /// Écoute le bus et applique localement les messages des autres instances
+    pub async fn start_event_bus(&self) -> Result<(), BusError> {
+        let mut stream = self.bus.subscribe().await?;
+        let hub = self.clone();
+
+        tokio::spawn(async move {
+            while let Some(envelope) = stream.next().await {
+                if envelope.origin != hub.instance_id {
+                    hub.apply_bus_message(envelope.message).await;
+                }
+            }
+            tracing::warn!("[Hub] Event bus stream ended");
+        });
+
+        Ok(())
+    }
+
+    async fn apply_bus_message(&self, message: BusMessage) {
+        match message {
+            BusMessage::Channel {
+                server_id,
+                channel_id,
+                viewers,
+                event,
+            } => {
+                self.deliver_to_channel(server_id, channel_id, viewers.as_ref(), &event)
+                    .await;
+            }
+            BusMessage::User { user_id, event } => self.deliver_to_user(user_id, &event).await,
+            BusMessage::Users { user_ids, event } => self.deliver_to_users(&user_ids, &event).await,
+            BusMessage::All { event } => self.deliver_to_all(&event).await,
+            BusMessage::ServerMembership {
+                user_id,
+                server_id,
+                subscribed,
+            } => {
+                self.apply_server_membership(user_id, server_id, subscribed)
+                    .await
+            }
+            BusMessage::ServerRemoved { server_id } => {
+                self.server_subscriptions.lock().await.remove(&server_id);
+            }
+            BusMessage::Connection { conn_id, event } => {
+                self.send_to_connection(conn_id, &event).await
+            }
+            BusMessage::VoiceState { user_id, state } => {
+                let mut voice_states = self.voice_states.lock().await;
+                match state {
+                    Some(state) => voice_states.join(user_id, state),
+                    None => voice_states.leave(user_id),
+                };
+            }
+        }
+    }
+
+    /// Publie sur le bus ; un échec n'affecte que les autres instances
+    async fn publish(&self, message: BusMessage) {
+        let envelope = BusEnvelope {
+            origin: self.instance_id,
+            message,
+        };
+        if let Err(err) = self.bus.publish(envelope).await {
+            tracing::warn!("[Hub] Failed to publish to event bus: {}", err);
         }
     }
 
     /// Enregistre une nouvelle connexion
-    pub async fn register(&self, conn_id: ConnectionId) -> broadcast::Receiver<String> {
-        let (tx, rx) = broadcast::channel(100); // Buffer de 100 messages max
+    pub async fn register(&self, conn_id: ConnectionId) -> ConnectionQueue {
+        let (queue, frames) = mpsc::channel(CONNECTION_BUFFER_SIZE);
+        let (evict, evicted) = oneshot::channel();
 
         let mut connections = self.connections.lock().await;
-        connections.insert(conn_id, tx);
+        connections.insert(conn_id, ConnectionHandle { queue, evict });
 
-        rx
+        ConnectionQueue { frames, evicted }
     }
 
     /// Supprime une connexion
+    /// Si elle porte une session, ses subscriptions sont conservées (détachée) jusqu'à
+    /// expiration de la période de grâce pour permettre un RESUME
     pub async fn unregister(&self, conn_id: ConnectionId, user_id: Option<Uuid>) {
-        // Retirer de toutes les subscriptions
         let mut subscriptions = self.subscriptions.lock().await;
-        for (_, conn_set) in subscriptions.iter_mut() {
-            conn_set.remove(&conn_id);
+        let mut server_subs = self.server_subscriptions.lock().await;
+        let mut user_conns = self.user_connections.lock().await;
+        let mut connections = self.connections.lock().await;
+        let mut sessions = self.sessions.lock().await;
+
+        connections.remove(&conn_id);
+
+        if let Some(session) = sessions.get_mut(&conn_id) {
+            session.detach();
+            return;
+        }
+
+        Self::forget_connection(
+            &mut subscriptions,
+            &mut server_subs,
+            &mut user_conns,
+            conn_id,
+            user_id,
+        );
+    }
+
+    /// Retire une connexion de toutes les subscriptions et de user_connections
+    fn forget_connection(
+        subscriptions: &mut HashMap<Uuid, HashSet<ConnectionId>>,
+        server_subs: &mut HashMap<Uuid, HashSet<ConnectionId>>,
+        user_conns: &mut HashMap<Uuid, HashSet<ConnectionId>>,
+        conn_id: ConnectionId,
+        user_id: Option<Uuid>,
+    ) {
+        // Retirer de toutes les subscriptions (channels et serveurs)
+        for subs in [subscriptions, server_subs] {
+            for (_, conn_set) in subs.iter_mut() {
+                conn_set.remove(&conn_id);
+            }
+            subs.retain(|_, conn_set| !conn_set.is_empty());
         }
-        subscriptions.retain(|_, conn_set| !conn_set.is_empty());
 
         // Retirer de user_connections si user_id fourni
         if let Some(uid) = user_id {
-            let mut user_conns = self.user_connections.lock().await;
             if let Some(conn_set) = user_conns.get_mut(&uid) {
                 conn_set.remove(&conn_id);
                 if conn_set.is_empty() {
@@ -62,10 +231,206 @@ impl WsHub {
                 }
             }
         }
+    }
+
+    /// Remplace une connexion par une autre dans les subscriptions et user_connections
+    fn rebind_connection(
+        subscriptions: &mut HashMap<Uuid, HashSet<ConnectionId>>,
+        server_subs: &mut HashMap<Uuid, HashSet<ConnectionId>>,
+        user_conns: &mut HashMap<Uuid, HashSet<ConnectionId>>,
+        old_conn_id: ConnectionId,
+        new_conn_id: ConnectionId,
+        user_id: Uuid,
+    ) {
+        for conn_set in subscriptions.values_mut().chain(server_subs.values_mut()) {
+            if conn_set.remove(&old_conn_id) {
+                conn_set.insert(new_conn_id);
+            }
+        }
+
+        let conn_set = user_conns.entry(user_id).or_default();
+        conn_set.remove(&old_conn_id);
+        conn_set.insert(new_conn_id);
+    }
+
+    /// Ouvre une session pour une connexion authentifiée, retourne son ID (pour READY)
+    pub async fn create_session(&self, conn_id: ConnectionId, user_id: Uuid) -> SessionId {
+        let session = WsSession::new(user_id);
+        let session_id = session.session_id();
+
+        self.sessions.lock().await.insert(conn_id, session);
+
+        session_id
+    }
+
+    /// Reprend une session détachée de `user_id` sur une nouvelle connexion et rejoue
+    /// les événements postérieurs à `seq`. None si la session est inconnue, encore
+    /// attachée, expirée, appartient à un autre utilisateur ou si les événements
+    /// demandés ne sont plus dans le buffer.
+    pub async fn resume_session(
+        &self,
+        conn_id: ConnectionId,
+        session_id: SessionId,
+        seq: u64,
+        user_id: Uuid,
+    ) -> Option<ResumedSession> {
+        let mut subscriptions = self.subscriptions.lock().await;
+        let mut server_subs = self.server_subscriptions.lock().await;
+        let mut user_conns = self.user_connections.lock().await;
+        let connections = self.connections.lock().await;
+        let mut sessions = self.sessions.lock().await;
+
+        let old_conn_id = sessions
+            .iter()
+            .find(|(_, session)| session.session_id() == session_id)
+            .map(|(id, _)| *id)?;
+
+        let session = sessions.get(&old_conn_id)?;
+        if session.user_id() != user_id
+            || !session.is_detached()
+            || session.is_expired(Instant::now())
+        {
+            return None;
+        }
+        let replay = session.replay_after(seq)?;
+
+        let mut session = sessions.remove(&old_conn_id)?;
+        session.attach();
+        let current_seq = session.seq();
+
+        Self::rebind_connection(
+            &mut subscriptions,
+            &mut server_subs,
+            &mut user_conns,
+            old_conn_id,
+            conn_id,
+            user_id,
+        );
+
+        if let Some(handle) = connections.get(&conn_id) {
+            for frame in replay {
+                let _ = handle.queue.try_send(frame);
+            }
+        }
+        sessions.insert(conn_id, session);
+
+        Some(ResumedSession {
+            user_id,
+            seq: current_seq,
+        })
+    }
+
+    /// Supprime les sessions détachées depuis plus longtemps que la période de grâce
+    pub async fn reap_expired_sessions(&self) -> usize {
+        let mut subscriptions = self.subscriptions.lock().await;
+        let mut server_subs = self.server_subscriptions.lock().await;
+        let mut user_conns = self.user_connections.lock().await;
+        let mut sessions = self.sessions.lock().await;
+
+        let now = Instant::now();
+        let expired: Vec<(ConnectionId, Uuid)> = sessions
+            .iter()
+            .filter(|(_, session)| session.is_expired(now))
+            .map(|(conn_id, session)| (*conn_id, session.user_id()))
+            .collect();
+
+        for (conn_id, user_id) in &expired {
+            sessions.remove(conn_id);
+            Self::forget_connection(
+                &mut subscriptions,
+                &mut server_subs,
+                &mut user_conns,
+                *conn_id,
+                Some(*user_id),
+            );
+        }
+
+        expired.len()
+    }
+
+    /// Envoie un événement à une liste de connexions
+    /// Les événements de dispatch sont numérotés et bufferisés par session,
+    /// y compris pour les sessions détachées (rejoués au RESUME).
+    /// Un client dont la file dépasse SLOW_CONSUMER_THRESHOLD est évincé plutôt que
+    /// de recevoir un flux avec des trous.
+    async fn deliver(
+        &self,
+        conn_ids: HashSet<ConnectionId>,
+        event: &ServerEvent,
+    ) -> (usize, usize) {
+        let frame = ServerFrame::new(event.clone());
+        let is_dispatch = event.is_dispatch();
 
-        // Retirer la connexion
         let mut connections = self.connections.lock().await;
-        connections.remove(&conn_id);
+        let mut sessions = self.sessions.lock().await;
+        let mut sent = 0;
+        let mut errors = 0;
+        let mut detached = 0;
+        let mut slow_consumers = Vec::new();
+
+        for conn_id in conn_ids {
+            let frame = match sessions.get_mut(&conn_id) {
+                Some(session) if is_dispatch => session.sequence(frame.clone()),
+                _ => frame.clone(),
+            };
+
+            let Some(handle) = connections.get(&conn_id) else {
+                // Session détachée : la trame n'existe que dans le buffer de replay
+                if is_dispatch && sessions.contains_key(&conn_id) {
+                    detached += 1;
+                }
+                continue;
+            };
+
+            let pending = handle.queue.max_capacity() - handle.queue.capacity();
+            if pending >= SLOW_CONSUMER_THRESHOLD {
+                slow_consumers.push(conn_id);
+                continue;
+            }
+
+            match handle.queue.try_send(frame) {
+                Ok(()) => sent += 1,
+                Err(TrySendError::Full(_)) => slow_consumers.push(conn_id),
+                Err(TrySendError::Closed(_)) => {
+                    // Receiver fermé, connexion morte (nettoyée à la fin de sa boucle)
+                    errors += 1;
+                }
+            }
+        }
+
+        // Plus aucune trame pour ces clients : session détachée, ils reprendront via RESUME
+        // Perdues : la trame refusée et celles encore en file, abandonnées à la fermeture
+        let mut dropped = 0;
+        for conn_id in &slow_consumers {
+            if let Some(session) = sessions.get_mut(conn_id) {
+                session.detach();
+            }
+            if let Some(handle) = connections.remove(conn_id) {
+                tracing::warn!(
+                    "[Hub] Connection {} lagged past {} pending frames, evicting",
+                    conn_id,
+                    SLOW_CONSUMER_THRESHOLD
+                );
+                dropped += 1 + handle.queue.max_capacity() - handle.queue.capacity();
+                let _ = handle.evict.send(());
+            }
+        }
+        drop(sessions);
+        drop(connections);
+
+        if !slow_consumers.is_empty() {
+            self.metrics.on_frames_dropped(dropped as u64).await;
+            for _ in &slow_consumers {
+                self.metrics.on_slow_consumer_disconnect().await;
+            }
+        }
+        if detached > 0 {
+            self.metrics
+                .on_detached_frames_buffered(detached as u64)
+                .await;
+        }
+
+        (sent, errors)
     }
 
     /// Associe une connexion à un utilisateur
@@ -97,39 +462,152 @@ impl WsHub {
         }
     }
 
-    /// Broadcast un événement à tous les abonnés d'un channel
-    pub async fn broadcast_to_channel(&self, channel_id: Uuid, event: &ServerEvent) {
-        let event_json = match event.to_json() {
-            Ok(json) => json,
-            Err(e) => {
-                eprintln!("[Hub] Failed to serialize event: {}", e);
-                return;
+    /// Subscribe une connexion à tous les channels d'un serveur
+    pub async fn subscribe_server(&self, conn_id: ConnectionId, server_id: Uuid) {
+        let mut server_subs = self.server_subscriptions.lock().await;
+        server_subs.entry(server_id).or_default().insert(conn_id);
+    }
+
+    /// Unsubscribe une connexion d'un serveur
+    pub async fn unsubscribe_server(&self, conn_id: ConnectionId, server_id: Uuid) {
+        let mut server_subs = self.server_subscriptions.lock().await;
+        if let Some(conn_set) = server_subs.get_mut(&server_id) {
+            conn_set.remove(&conn_id);
+            if conn_set.is_empty() {
+                server_subs.remove(&server_id);
             }
+        }
+    }
+
+    /// Subscribe toutes les connexions d'un utilisateur à un serveur (nouveau membre)
+    pub async fn subscribe_user_to_server(&self, user_id: Uuid, server_id: Uuid) {
+        self.apply_server_membership(user_id, server_id, true).await;
+        self.publish(BusMessage::ServerMembership {
+            user_id,
+            server_id,
+            subscribed: true,
+        })
+        .await;
+    }
+
+    /// Retire toutes les connexions d'un utilisateur d'un serveur (départ, kick, ban)
+    pub async fn unsubscribe_user_from_server(&self, user_id: Uuid, server_id: Uuid) {
+        self.apply_server_membership(user_id, server_id, false)
+            .await;
+        self.publish(BusMessage::ServerMembership {
+            user_id,
+            server_id,
+            subscribed: false,
+        })
+        .await;
+    }
+
+    async fn apply_server_membership(&self, user_id: Uuid, server_id: Uuid, subscribed: bool) {
+        let mut server_subs = self.server_subscriptions.lock().await;
+        let user_conns = self.user_connections.lock().await;
+
+        let Some(conn_ids) = user_conns.get(&user_id) else {
+            return;
         };
 
+        if subscribed {
+            server_subs
+                .entry(server_id)
+                .or_default()
+                .extend(conn_ids.iter().copied());
+        } else if let Some(conn_set) = server_subs.get_mut(&server_id) {
+            conn_set.retain(|conn_id| !conn_ids.contains(conn_id));
+            if conn_set.is_empty() {
+                server_subs.remove(&server_id);
+            }
+        }
+    }
+
+    /// Supprime toutes les subscriptions d'un serveur (serveur supprimé)
+    pub async fn remove_server(&self, server_id: Uuid) {
+        self.server_subscriptions.lock().await.remove(&server_id);
+        self.publish(BusMessage::ServerRemoved { server_id }).await;
+    }
+
+    /// Abonnés du channel et de son serveur, restreints aux connexions de `viewers` si fourni
+    async fn channel_recipients(
+        &self,
+        server_id: Uuid,
+        channel_id: Uuid,
+        viewers: Option<&HashSet<Uuid>>,
+    ) -> HashSet<ConnectionId> {
         let subscriptions = self.subscriptions.lock().await;
-        let conn_ids = match subscriptions.get(&channel_id) {
-            Some(ids) => ids.clone(),
-            None => return, // Aucun abonné
-        };
-        drop(subscriptions);
+        let server_subs = self.server_subscriptions.lock().await;
 
-        let connections = self.connections.lock().await;
-        let mut sent = 0;
-        let mut errors = 0;
+        let mut recipients: HashSet<ConnectionId> = subscriptions
+            .get(&channel_id)
+            .into_iter()
+            .chain(server_subs.get(&server_id))
+            .flatten()
+            .copied()
+            .collect();
+
+        if let Some(viewers) = viewers {
+            let user_connections = self.user_connections.lock().await;
+            let allowed: HashSet<ConnectionId> = viewers
+                .iter()
+                .filter_map(|user_id| user_connections.get(user_id))
+                .flatten()
+                .copied()
+                .collect();
+            recipients.retain(|conn_id| allowed.contains(conn_id));
+        }
 
-        for conn_id in conn_ids {
-            if let Some(tx) = connections.get(&conn_id) {
-                match tx.send(event_json.clone()) {
-                    Ok(_) => sent += 1,
-                    Err(_) => {
-                        // Receiver fermé, connexion morte (sera nettoyée au prochain heartbeat)
-                        errors += 1;
-                    }
-                }
-            }
+        recipients
+    }
+
+    /// Broadcast un événement aux abonnés d'un channel et de son serveur (toutes instances)
+    pub async fn broadcast_to_channel(
+        &self,
+        server_id: Uuid,
+        channel_id: Uuid,
+        event: &ServerEvent,
+    ) {
+        self.broadcast_to_channel_viewers(server_id, channel_id, None, event)
+            .await;
+    }
+
+    /// Comme `broadcast_to_channel`, limité aux utilisateurs qui voient le channel
+    /// (`None` : tous les membres du serveur le voient)
+    pub async fn broadcast_to_channel_viewers(
+        &self,
+        server_id: Uuid,
+        channel_id: Uuid,
+        viewers: Option<&HashSet<Uuid>>,
+        event: &ServerEvent,
+    ) {
+        self.deliver_to_channel(server_id, channel_id, viewers, event)
+            .await;
+        self.publish(BusMessage::Channel {
+            server_id,
+            channel_id,
+            viewers: viewers.cloned(),
+            event: event.clone(),
+        })
+        .await;
+    }
+
+    async fn deliver_to_channel(
+        &self,
+        server_id: Uuid,
+        channel_id: Uuid,
+        viewers: Option<&HashSet<Uuid>>,
+        event: &ServerEvent,
+    ) {
+        let conn_ids = self
+            .channel_recipients(server_id, channel_id, viewers)
+            .await;
+        if conn_ids.is_empty() {
+            return; // Aucun abonné
         }
 
+        let (sent, errors) = self.deliver(conn_ids, event).await;
+
         if sent > 0 {
             tracing::debug!(
                 "[Hub] Broadcast to channel {}: {} sent, {} errors",
@@ -143,49 +621,109 @@ impl WsHub {
     /// Broadcast avec tracking des métriques
     pub async fn broadcast_to_channel_with_metrics(
         &self,
+        server_id: Uuid,
         channel_id: Uuid,
+        viewers: Option<&HashSet<Uuid>>,
         event: &ServerEvent,
         metrics: Option<&crate::web::ws::metrics::WsMetrics>,
     ) {
-        self.broadcast_to_channel(channel_id, event).await;
+        self.broadcast_to_channel_viewers(server_id, channel_id, viewers, event)
+            .await;
 
         // Compter les messages envoyés dans les métriques
         if let Some(m) = metrics {
-            let subscriptions = self.subscriptions.lock().await;
-            if let Some(conn_ids) = subscriptions.get(&channel_id) {
-                for _ in conn_ids.iter() {
-                    m.on_message_sent().await;
-                }
+            let recipients = self
+                .channel_recipients(server_id, channel_id, viewers)
+                .await;
+            for _ in recipients.iter() {
+                m.on_message_sent().await;
             }
         }
     }
 
     /// Envoie un événement à une connexion spécifique
     pub async fn send_to_connection(&self, conn_id: ConnectionId, event: &ServerEvent) {
-        let event_json = match event.to_json() {
-            Ok(json) => json,
-            Err(e) => {
-                eprintln!("[Hub] Failed to serialize event: {}", e);
-                return;
-            }
+        self.deliver(HashSet::from([conn_id]), event).await;
+    }
+
+    /// Comme `send_to_connection`, pour une connexion qui peut être portée par une autre instance
+    pub async fn send_to_connection_anywhere(&self, conn_id: ConnectionId, event: &ServerEvent) {
+        if self.connections.lock().await.contains_key(&conn_id) {
+            self.send_to_connection(conn_id, event).await;
+        } else {
+            self.publish(BusMessage::Connection {
+                conn_id,
+                event: event.clone(),
+            })
+            .await;
+        }
+    }
+
+    /// Place l'utilisateur dans un channel vocal (toutes instances)
+    /// Retourne l'état qu'il quitte et les participants du channel rejoint
+    pub async fn voice_join(
+        &self,
+        user_id: Uuid,
+        state: VoiceState,
+    ) -> (Option<VoiceState>, Vec<Uuid>) {
+        let (previous, participants) = {
+            let mut voice_states = self.voice_states.lock().await;
+            let previous = voice_states.join(user_id, state);
+            (previous, voice_states.participants(state.channel_id))
         };
+        self.publish_voice_state(user_id, Some(state)).await;
+        (previous, participants)
+    }
 
-        let connections = self.connections.lock().await;
-        if let Some(tx) = connections.get(&conn_id) {
-            let _ = tx.send(event_json);
+    pub async fn voice_leave(&self, user_id: Uuid) -> Option<VoiceState> {
+        let previous = self.voice_states.lock().await.leave(user_id)?;
+        self.publish_voice_state(user_id, None).await;
+        Some(previous)
+    }
+
+    /// Libère la présence vocale portée par une connexion de cette instance
+    pub async fn voice_leave_connection(
+        &self,
+        conn_id: ConnectionId,
+    ) -> Option<(Uuid, VoiceState)> {
+        let (user_id, previous) = self.voice_states.lock().await.leave_connection(conn_id)?;
+        self.publish_voice_state(user_id, None).await;
+        Some((user_id, previous))
+    }
+
+    /// Vide un channel vocal, retourne les utilisateurs déconnectés
+    pub async fn voice_clear_channel(&self, channel_id: Uuid) -> Vec<(Uuid, VoiceState)> {
+        let removed = self.voice_states.lock().await.clear_channel(channel_id);
+        for (user_id, _) in &removed {
+            self.publish_voice_state(*user_id, None).await;
         }
+        removed
+    }
+
+    pub async fn voice_participants(&self, channel_id: Uuid) -> Vec<Uuid> {
+        self.voice_states.lock().await.participants(channel_id)
+    }
+
+    pub async fn voice_state_of(&self, user_id: Uuid) -> Option<VoiceState> {
+        self.voice_states.lock().await.state_of(user_id)
     }
 
-    /// Envoie un événement à un utilisateur spécifique (toutes ses connexions)
+    async fn publish_voice_state(&self, user_id: Uuid, state: Option<VoiceState>) {
+        self.publish(BusMessage::VoiceState { user_id, state })
+            .await;
+    }
+
+    /// Envoie un événement à un utilisateur spécifique (toutes ses connexions, toutes instances)
     pub async fn send_to_user(&self, user_id: Uuid, event: &ServerEvent) {
-        let event_json = match event.to_json() {
-            Ok(json) => json,
-            Err(e) => {
-                eprintln!("[Hub] Failed to serialize event: {}", e);
-                return;
-            }
-        };
+        self.deliver_to_user(user_id, event).await;
+        self.publish(BusMessage::User {
+            user_id,
+            event: event.clone(),
+        })
+        .await;
+    }
 
+    async fn deliver_to_user(&self, user_id: Uuid, event: &ServerEvent) {
         let user_conns = self.user_connections.lock().await;
         let conn_ids = match user_conns.get(&user_id) {
             Some(ids) => ids.clone(),
@@ -193,35 +731,69 @@ impl WsHub {
         };
         drop(user_conns);
 
-        let connections = self.connections.lock().await;
-        for conn_id in conn_ids {
-            if let Some(tx) = connections.get(&conn_id) {
-                let _ = tx.send(event_json.clone());
-            }
+        self.deliver(conn_ids, event).await;
+    }
+
+    /// Envoie un événement à plusieurs utilisateurs, publié une seule fois sur le bus
+    pub async fn send_to_users(&self, user_ids: &HashSet<Uuid>, event: &ServerEvent) {
+        if user_ids.is_empty() {
+            return;
         }
+
+        self.deliver_to_users(user_ids, event).await;
+        self.publish(BusMessage::Users {
+            user_ids: user_ids.clone(),
+            event: event.clone(),
+        })
+        .await;
     }
 
-    /// Broadcast un événement à toutes les connexions actives
-    pub async fn broadcast_all(&self, event: &ServerEvent) {
-        let event_json = match event.to_json() {
-            Ok(json) => json,
-            Err(e) => {
-                eprintln!("[Hub] Failed to serialize event: {}", e);
-                return;
-            }
-        };
+    async fn deliver_to_users(&self, user_ids: &HashSet<Uuid>, event: &ServerEvent) {
+        let user_conns = self.user_connections.lock().await;
+        let conn_ids: HashSet<ConnectionId> = user_ids
+            .iter()
+            .filter_map(|user_id| user_conns.get(user_id))
+            .flatten()
+            .copied()
+            .collect();
+        drop(user_conns);
 
-        let connections = self.connections.lock().await;
-        for tx in connections.values() {
-            let _ = tx.send(event_json.clone());
+        if !conn_ids.is_empty() {
+            self.deliver(conn_ids, event).await;
         }
     }
 
+    /// Broadcast un événement à toutes les connexions actives (toutes instances)
+    pub async fn broadcast_all(&self, event: &ServerEvent) {
+        self.deliver_to_all(event).await;
+        self.publish(BusMessage::All {
+            event: event.clone(),
+        })
+        .await;
+    }
+
+    async fn deliver_to_all(&self, event: &ServerEvent) {
+        let conn_ids: HashSet<ConnectionId> =
+            self.connections.lock().await.keys().copied().collect();
+
+        self.deliver(conn_ids, event).await;
+    }
+
     /// Nombre de connexions actives
     pub async fn connection_count(&self) -> usize {
         self.connections.lock().await.len()
     }
 
+    /// Vrai si l'utilisateur a au moins une connexion ouverte sur cette instance
+    pub async fn is_user_connected(&self, user_id: Uuid) -> bool {
+        let user_conns = self.user_connections.lock().await;
+        let connections = self.connections.lock().await;
+
+        user_conns
+            .get(&user_id)
+            .is_some_and(|conn_ids| conn_ids.iter().any(|id| connections.contains_key(id)))
+    }
+
     /// Nombre d'abonnés à un channel
     pub async fn channel_subscriber_count(&self, channel_id: Uuid) -> usize {
         self.subscriptions
@@ -238,3 +810,145 @@ impl Default for WsHub {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn evicts_slow_consumer_and_lets_it_resume_without_gap() {
+        let metrics = WsMetrics::new();
+        let hub = WsHub::with_metrics(metrics.clone());
+        let user_id = Uuid::new_v4();
+        let channel_id = Uuid::new_v4();
+
+        let conn_id = Uuid::new_v4();
+        let mut queue = hub.register(conn_id).await;
+        let session_id = hub.create_session(conn_id, user_id).await;
+        hub.subscribe(conn_id, channel_id).await;
+
+        // Le client ne lit rien : la file atteint le seuil puis le client est évincé
+        for _ in 0..=SLOW_CONSUMER_THRESHOLD {
+            let event = ServerEvent::MessageDelete {
+                id: Uuid::new_v4(),
+                channel_id,
+            };
+            hub.broadcast_to_channel(Uuid::new_v4(), channel_id, &event)
+                .await;
+        }
+
+        assert!(queue.evicted.try_recv().is_ok());
+        let snapshot = metrics.get_metrics().await;
+        assert_eq!(snapshot.frames_dropped, SLOW_CONSUMER_THRESHOLD as u64 + 1);
+        assert_eq!(snapshot.slow_consumer_disconnects, 1);
+        assert_eq!(snapshot.detached_frames_buffered, 0);
+
+        // Session détachée : les événements suivants ne vont que dans le buffer de replay
+        let event = ServerEvent::MessageDelete {
+            id: Uuid::new_v4(),
+            channel_id,
+        };
+        hub.broadcast_to_channel(Uuid::new_v4(), channel_id, &event)
+            .await;
+        assert_eq!(metrics.get_metrics().await.detached_frames_buffered, 1);
+
+        // Le client avait lu 10 trames avant de décrocher
+        let mut last_seq = 0;
+        for _ in 0..10 {
+            last_seq = queue.frames.recv().await.unwrap().s.unwrap();
+        }
+        hub.unregister(conn_id, Some(user_id)).await;
+
+        let new_conn_id = Uuid::new_v4();
+        let mut new_queue = hub.register(new_conn_id).await;
+        let resumed = hub
+            .resume_session(new_conn_id, session_id, last_seq, user_id)
+            .await
+            .expect("session should be resumable after eviction");
+
+        assert_eq!(resumed.seq, SLOW_CONSUMER_THRESHOLD as u64 + 2);
+        let mut expected = last_seq + 1;
+        while let Ok(frame) = new_queue.frames.try_recv() {
+            assert_eq!(frame.s, Some(expected));
+            expected += 1;
+        }
+        assert_eq!(expected, resumed.seq + 1);
+    }
+
+    #[tokio::test]
+    async fn resume_requires_session_owner_and_detached_session() {
+        let hub = WsHub::new();
+        let user_id = Uuid::new_v4();
+
+        let conn_id = Uuid::new_v4();
+        let _queue = hub.register(conn_id).await;
+        let session_id = hub.create_session(conn_id, user_id).await;
+
+        // Session encore attachée : pas de détournement possible
+        let other_conn = Uuid::new_v4();
+        let _other_queue = hub.register(other_conn).await;
+        assert!(hub
+            .resume_session(other_conn, session_id, 0, user_id)
+            .await
+            .is_none());
+
+        hub.unregister(conn_id, Some(user_id)).await;
+        assert!(hub
+            .resume_session(other_conn, session_id, 0, Uuid::new_v4())
+            .await
+            .is_none());
+        assert!(hub
+            .resume_session(other_conn, session_id, 0, user_id)
+            .await
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn user_stays_connected_while_another_connection_is_open() {
+        let hub = WsHub::new();
+        let user_id = Uuid::new_v4();
+
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        for conn_id in [first, second] {
+            let _ = hub.register(conn_id).await;
+            hub.associate_user(conn_id, user_id).await;
+            hub.create_session(conn_id, user_id).await;
+        }
+
+        hub.unregister(first, Some(user_id)).await;
+        assert!(hub.is_user_connected(user_id).await);
+
+        // Session détachée : la connexion n'est plus comptée
+        hub.unregister(second, Some(user_id)).await;
+        assert!(!hub.is_user_connected(user_id).await);
+    }
+
+    #[tokio::test]
+    async fn private_channel_broadcast_reaches_only_viewers() {
+        let hub = WsHub::new();
+        let server_id = Uuid::new_v4();
+        let channel_id = Uuid::new_v4();
+        let viewer = Uuid::new_v4();
+        let outsider = Uuid::new_v4();
+
+        let mut queues = Vec::new();
+        for user_id in [viewer, outsider] {
+            let conn_id = Uuid::new_v4();
+            let queue = hub.register(conn_id).await;
+            hub.associate_user(conn_id, user_id).await;
+            hub.subscribe_server(conn_id, server_id).await;
+            queues.push(queue);
+        }
+
+        let event = ServerEvent::MessageDelete {
+            id: Uuid::new_v4(),
+            channel_id,
+        };
+        let viewers = HashSet::from([viewer]);
+        hub.broadcast_to_channel_viewers(server_id, channel_id, Some(&viewers), &event)
+            .await;
+
+        assert!(queues[0].frames.try_recv().is_ok());
+        assert!(queues[1].frames.try_recv().is_err());
+    }
+}
@@ -24,6 +24,15 @@ struct MetricsInner {
     /// Nombre total de messages envoyés
     messages_sent: u64,
 
+    /// Trames non délivrées car la file d'envoi du client était saturée
+    frames_dropped: u64,
+
+    /// Connexions fermées parce que le client ne lisait pas assez vite
+    slow_consumer_disconnects: u64,
+
+    /// Trames numérotées pour une session détachée (gardées pour le replay, non envoyées)
+    detached_frames_buffered: u64,
+
     /// Timestamp du dernier message
     last_message_at: Option<Instant>,
 
@@ -77,6 +86,24 @@ impl WsMetrics {
         inner.messages_sent += 1;
     }
 
+    /// Enregistre des trames perdues (file d'envoi saturée)
+    pub async fn on_frames_dropped(&self, count: u64) {
+        let mut inner = self.inner.lock().await;
+        inner.frames_dropped += count;
+    }
+
+    /// Enregistre la déconnexion d'un client trop lent
+    pub async fn on_slow_consumer_disconnect(&self) {
+        let mut inner = self.inner.lock().await;
+        inner.slow_consumer_disconnects += 1;
+    }
+
+    /// Enregistre des trames bufferisées pour des sessions détachées
+    pub async fn on_detached_frames_buffered(&self, count: u64) {
+        let mut inner = self.inner.lock().await;
+        inner.detached_frames_buffered += count;
+    }
+
     /// Récupère les métriques actuelles
     pub async fn get_metrics(&self) -> MetricsSnapshot {
         let inner = self.inner.lock().await;
@@ -85,6 +112,9 @@ impl WsMetrics {
             active_connections: inner.active_connections,
             messages_received: inner.messages_received,
             messages_sent: inner.messages_sent,
+            frames_dropped: inner.frames_dropped,
+            slow_consumer_disconnects: inner.slow_consumer_disconnects,
+            detached_frames_buffered: inner.detached_frames_buffered,
             messages_per_second: inner.messages_per_second,
             last_message_at: inner.last_message_at.map(|_| {
                 // Timestamp Unix en millisecondes
@@ -105,6 +135,9 @@ pub struct MetricsSnapshot {
     pub active_connections: usize,
     pub messages_received: u64,
     pub messages_sent: u64,
+    pub frames_dropped: u64,
+    pub slow_consumer_disconnects: u64,
+    pub detached_frames_buffered: u64,
     pub messages_per_second: f64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_message_at: Option<u64>, // Timestamp Unix en millisecondes
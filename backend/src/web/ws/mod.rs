@@ -1,12 +1,17 @@
 //! Module WebSocket : transport temps réel
 //! Séparé de la logique métier (services/realtime)
 
+pub mod bus;
+pub mod codec;
 pub mod connection;
 pub mod handler;
 pub mod hub;
 pub mod metrics;
 pub mod protocol;
+pub mod session;
+pub mod voice;
 
+pub use connection::HeartbeatConfig;
 pub use handler::ws_handler;
 pub use hub::WsHub;
 pub use metrics::{MetricsSnapshot, WsMetrics};
@@ -3,9 +3,22 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::models::MessageReactionPublic;
+use crate::models::{ChannelKind, MemberRole, MessagePreview, MessageReactionPublic, Role, Thread};
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Codes de fermeture WebSocket applicatifs (plage 4000-4999)
+pub mod close_code {
+    /// Aucun HEARTBEAT reçu dans le délai imparti
+    pub const HEARTBEAT_TIMEOUT: u16 = 4009;
+    /// File d'envoi saturée : le client doit se reconnecter puis envoyer RESUME
+    pub const SLOW_CONSUMER: u16 = 4010;
+}
 
 /// Événements envoyés par le client
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,9 +28,73 @@ pub enum ClientEvent {
     #[serde(rename = "IDENTIFY")]
     Identify { token: String },
 
+    /// Reprise d'une session après déconnexion (replay des événements manqués depuis `seq`)
+    /// Le token doit appartenir à l'utilisateur de la session
+    #[serde(rename = "RESUME")]
+    Resume {
+        token: String,
+        session_id: Uuid,
+        seq: u64,
+    },
+
     /// Envoi d'un message dans un channel
     #[serde(rename = "SEND_MESSAGE")]
-    SendMessage { channel_id: Uuid, content: String },
+    SendMessage {
+        channel_id: Uuid,
+        content: String,
+        #[serde(default)]
+        nonce: Option<String>,
+        /// Message du même channel auquel on répond
+        #[serde(default)]
+        reply_to: Option<Uuid>,
+    },
+
+    /// Modification d'un message de channel (auteur uniquement)
+    #[serde(rename = "EDIT_MESSAGE")]
+    EditMessage {
+        message_id: Uuid,
+        content: String,
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Suppression d'un message de channel
+    #[serde(rename = "DELETE_MESSAGE")]
+    DeleteMessage {
+        message_id: Uuid,
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Ajout d'une réaction sur un message de channel
+    #[serde(rename = "ADD_REACTION")]
+    AddReaction {
+        message_id: Uuid,
+        emoji: String,
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Retrait d'une réaction sur un message de channel
+    #[serde(rename = "REMOVE_REACTION")]
+    RemoveReaction {
+        message_id: Uuid,
+        emoji: String,
+        #[serde(default)]
+        nonce: Option<String>,
+    },
+
+    /// Envoi d'un message privé dans une conversation
+    #[serde(rename = "SEND_DIRECT_MESSAGE")]
+    SendDirectMessage {
+        dm_id: Uuid,
+        content: String,
+        #[serde(default)]
+        nonce: Option<String>,
+        /// Message de la même conversation auquel on répond
+        #[serde(default)]
+        reply_to: Option<Uuid>,
+    },
 
     /// Début de frappe dans un channel
     #[serde(rename = "TYPING_START")]
@@ -27,6 +104,14 @@ pub enum ClientEvent {
     #[serde(rename = "TYPING_STOP")]
     TypingStop { channel_id: Uuid },
 
+    /// Début de frappe dans une conversation privée
+    #[serde(rename = "DIRECT_MESSAGE_TYPING_START")]
+    DirectMessageTypingStart { dm_id: Uuid },
+
+    /// Fin de frappe dans une conversation privée
+    #[serde(rename = "DIRECT_MESSAGE_TYPING_STOP")]
+    DirectMessageTypingStop { dm_id: Uuid },
+
     /// Heartbeat (pong applicatif)
     #[serde(rename = "HEARTBEAT")]
     Heartbeat { seq: Option<u64> },
@@ -39,9 +124,42 @@ pub enum ClientEvent {
     #[serde(rename = "UNSUBSCRIBE")]
     Unsubscribe { channel_id: Uuid },
 
+    /// Subscription à tous les channels d'un serveur
+    #[serde(rename = "SUBSCRIBE_SERVER")]
+    SubscribeServer { server_id: Uuid },
+
+    /// Unsubscription d'un serveur
+    #[serde(rename = "UNSUBSCRIBE_SERVER")]
+    UnsubscribeServer { server_id: Uuid },
+
     /// Mise à jour de présence
     #[serde(rename = "PRESENCE_UPDATE")]
     PresenceUpdate { status: String },
+
+    /// Connexion à un channel vocal (quitte le précédent)
+    #[serde(rename = "VOICE_JOIN")]
+    VoiceJoin { channel_id: Uuid },
+
+    /// Déconnexion du channel vocal courant
+    #[serde(rename = "VOICE_LEAVE")]
+    VoiceLeave,
+
+    /// Signalisation WebRTC à relayer vers un participant du channel
+    #[serde(rename = "VOICE_SIGNAL")]
+    VoiceSignal {
+        channel_id: Uuid,
+        target_user_id: Uuid,
+        signal: VoiceSignal,
+    },
+}
+
+/// Trame de signalisation WebRTC, relayée telle quelle entre participants
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VoiceSignal {
+    Offer { sdp: String },
+    Answer { sdp: String },
+    IceCandidate { candidate: serde_json::Value },
 }
 
 /// Événements envoyés par le serveur
@@ -56,11 +174,40 @@ pub enum ServerEvent {
 
     /// Identification réussie
     #[serde(rename = "READY")]
-    Ready { user_id: Uuid, username: String },
+    Ready {
+        user_id: Uuid,
+        username: String,
+        session_id: Uuid,
+    },
+
+    /// Session reprise, les événements manqués ont été renvoyés
+    #[serde(rename = "RESUMED")]
+    Resumed { session_id: Uuid, seq: u64 },
+
+    /// Session introuvable ou expirée : le client doit renvoyer IDENTIFY
+    #[serde(rename = "INVALID_SESSION")]
+    InvalidSession,
 
-    /// Erreur (auth, validation, etc.)
+    /// Erreur (auth, validation, etc.), avec le nonce de l'opération en échec
     #[serde(rename = "ERROR")]
-    Error { code: String, message: String },
+    Error {
+        code: String,
+        message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        nonce: Option<String>,
+        /// Slowmode : secondes à attendre avant de renvoyer un message
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry_after: Option<u64>,
+    },
+
+    /// Opération client réussie, corrélée par le nonce fourni
+    #[serde(rename = "ACK")]
+    Ack {
+        op: String,
+        nonce: Option<String>,
+        /// ID de la ressource créée ou modifiée
+        id: Option<Uuid>,
+    },
 
     /// Nouveau message reçu
     #[serde(rename = "MESSAGE_CREATE")]
@@ -70,10 +217,34 @@ pub enum ServerEvent {
         server_id: Uuid,
         author_id: Uuid,
         username: String,
+        /// Pseudo de serveur de l'auteur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        nickname: Option<String>,
+        /// Avatar de serveur de l'auteur, sinon son avatar global
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        avatar_url: Option<String>,
         content: String,
         created_at: DateTime<Utc>,
         edited_at: Option<DateTime<Utc>>,
         reactions: Vec<MessageReactionPublic>,
+        /// Nonce fourni par l'auteur, pour réconcilier son affichage optimiste
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        nonce: Option<String>,
+        /// Channel d'annonces suivi dont le message est recopié
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        crossposted_from: Option<Uuid>,
+        /// Aperçu du message auquel celui-ci répond
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reply_to: Option<MessagePreview>,
+        /// Membres mentionnés (`<@user_id>`)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        mentions: Vec<Uuid>,
+        /// Rôles mentionnés (`<@&role_id>`)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        mention_roles: Vec<Uuid>,
+        /// `@everyone` ou `@here` : tout le channel est notifié
+        #[serde(default, skip_serializing_if = "is_false")]
+        mention_everyone: bool,
     },
 
     /// Message modifié
@@ -89,6 +260,10 @@ pub enum ServerEvent {
     #[serde(rename = "MESSAGE_DELETE")]
     MessageDelete { id: Uuid, channel_id: Uuid },
 
+    /// Messages supprimés en une fois (modération)
+    #[serde(rename = "MESSAGE_DELETE_BULK")]
+    MessageDeleteBulk { ids: Vec<Uuid>, channel_id: Uuid },
+
     /// Réactions du message mises à jour
     #[serde(rename = "MESSAGE_REACTION_UPDATE")]
     MessageReactionUpdate {
@@ -97,6 +272,14 @@ pub enum ServerEvent {
         reactions: Vec<MessageReactionPublic>,
     },
 
+    /// Message épinglé, ou désépinglé (`pinned_at` null)
+    #[serde(rename = "CHANNEL_PINS_UPDATE")]
+    ChannelPinsUpdate {
+        channel_id: Uuid,
+        message_id: Uuid,
+        pinned_at: Option<DateTime<Utc>>,
+    },
+
     /// Nouveau message privé reçu
     #[serde(rename = "DIRECT_MESSAGE_CREATE")]
     DirectMessageCreate {
@@ -108,6 +291,12 @@ pub enum ServerEvent {
         created_at: DateTime<Utc>,
         edited_at: Option<DateTime<Utc>>,
         reactions: Vec<MessageReactionPublic>,
+        /// Nonce fourni par l'auteur, pour réconcilier son affichage optimiste
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        nonce: Option<String>,
+        /// Aperçu du message auquel celui-ci répond
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reply_to: Option<MessagePreview>,
     },
 
     /// Message privé modifié
@@ -131,18 +320,171 @@ pub enum ServerEvent {
         reactions: Vec<MessageReactionPublic>,
     },
 
+    /// Message privé épinglé, ou désépinglé (`pinned_at` null)
+    #[serde(rename = "DIRECT_MESSAGE_PINS_UPDATE")]
+    DirectMessagePinsUpdate {
+        dm_id: Uuid,
+        message_id: Uuid,
+        pinned_at: Option<DateTime<Utc>>,
+    },
+
+    /// Channel créé dans un serveur
+    #[serde(rename = "CHANNEL_CREATE")]
+    ChannelCreate {
+        id: Uuid,
+        server_id: Uuid,
+        name: String,
+        kind: ChannelKind,
+        parent_id: Option<Uuid>,
+        position: i32,
+        rate_limit_per_user: i32,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+    },
+
+    /// Channel renommé ou déplacé (position, catégorie)
+    #[serde(rename = "CHANNEL_UPDATE")]
+    ChannelUpdate {
+        id: Uuid,
+        server_id: Uuid,
+        name: String,
+        kind: ChannelKind,
+        parent_id: Option<Uuid>,
+        position: i32,
+        rate_limit_per_user: i32,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+    },
+
+    /// Channel supprimé
+    #[serde(rename = "CHANNEL_DELETE")]
+    ChannelDelete { id: Uuid, server_id: Uuid },
+
+    /// Thread ouvert depuis un message
+    #[serde(rename = "THREAD_CREATE")]
+    ThreadCreate { thread: Thread },
+
+    /// Thread renommé, archivé ou désarchivé
+    #[serde(rename = "THREAD_UPDATE")]
+    ThreadUpdate { thread: Thread },
+
+    /// Serveur modifié (nom, propriétaire)
+    #[serde(rename = "SERVER_UPDATE")]
+    ServerUpdate {
+        id: Uuid,
+        name: String,
+        owner_id: Uuid,
+        updated_at: DateTime<Utc>,
+    },
+
+    /// Serveur supprimé
+    #[serde(rename = "SERVER_DELETE")]
+    ServerDelete { id: Uuid },
+
+    /// Nouveau membre dans un serveur
+    #[serde(rename = "MEMBER_ADD")]
+    MemberAdd {
+        server_id: Uuid,
+        user_id: Uuid,
+        role: MemberRole,
+        joined_at: DateTime<Utc>,
+        username: String,
+        avatar_url: Option<String>,
+    },
+
+    /// Rang, rôles attribués, timeout ou profil de serveur d'un membre modifiés
+    #[serde(rename = "MEMBER_UPDATE")]
+    MemberUpdate {
+        server_id: Uuid,
+        user_id: Uuid,
+        role: MemberRole,
+        roles: Vec<Uuid>,
+        communication_disabled_until: Option<DateTime<Utc>>,
+        nickname: Option<String>,
+        server_avatar_url: Option<String>,
+    },
+
+    /// Membre parti, exclu ou banni
+    #[serde(rename = "MEMBER_REMOVE")]
+    MemberRemove { server_id: Uuid, user_id: Uuid },
+
+    /// Utilisateur banni d'un serveur
+    #[serde(rename = "BAN_ADD")]
+    BanAdd {
+        server_id: Uuid,
+        user_id: Uuid,
+        banned_by: Uuid,
+        reason: Option<String>,
+        expires_at: Option<DateTime<Utc>>,
+        banned_at: DateTime<Utc>,
+    },
+
+    /// Ban levé
+    #[serde(rename = "BAN_REMOVE")]
+    BanRemove { server_id: Uuid, user_id: Uuid },
+
+    /// Rôle créé dans un serveur
+    #[serde(rename = "ROLE_CREATE")]
+    RoleCreate { role: Role },
+
+    /// Rôle modifié (nom, couleur, position, permissions)
+    #[serde(rename = "ROLE_UPDATE")]
+    RoleUpdate { role: Role },
+
+    /// Rôle supprimé (retiré de tous les membres)
+    #[serde(rename = "ROLE_DELETE")]
+    RoleDelete { server_id: Uuid, role_id: Uuid },
+
     /// Quelqu'un commence à taper
     #[serde(rename = "TYPING_START")]
     TypingStart {
         channel_id: Uuid,
         user_id: Uuid,
         username: String,
+        /// Pseudo de serveur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        nickname: Option<String>,
     },
 
     /// Quelqu'un arrête de taper
     #[serde(rename = "TYPING_STOP")]
     TypingStop { channel_id: Uuid, user_id: Uuid },
 
+    /// L'autre participant d'une conversation privée commence à taper
+    #[serde(rename = "DIRECT_MESSAGE_TYPING_START")]
+    DirectMessageTypingStart {
+        dm_id: Uuid,
+        user_id: Uuid,
+        username: String,
+    },
+
+    /// L'autre participant d'une conversation privée arrête de taper
+    #[serde(rename = "DIRECT_MESSAGE_TYPING_STOP")]
+    DirectMessageTypingStop { dm_id: Uuid, user_id: Uuid },
+
+    /// Un membre rejoint (channel_id) ou quitte (null) un channel vocal
+    #[serde(rename = "VOICE_STATE_UPDATE")]
+    VoiceStateUpdate {
+        server_id: Uuid,
+        user_id: Uuid,
+        channel_id: Option<Uuid>,
+    },
+
+    /// Connexion vocale établie, avec les participants présents (soi compris)
+    #[serde(rename = "VOICE_READY")]
+    VoiceReady {
+        channel_id: Uuid,
+        participants: Vec<Uuid>,
+    },
+
+    /// Signalisation WebRTC relayée depuis un autre participant
+    #[serde(rename = "VOICE_SIGNAL")]
+    VoiceSignal {
+        channel_id: Uuid,
+        from_user_id: Uuid,
+        signal: VoiceSignal,
+    },
+
     /// Heartbeat ACK
     #[serde(rename = "HEARTBEAT_ACK")]
     HeartbeatAck { seq: Option<u64> },
@@ -155,6 +497,14 @@ pub enum ServerEvent {
     #[serde(rename = "UNSUBSCRIBED")]
     Unsubscribed { channel_id: Uuid },
 
+    /// Subscription serveur confirmée
+    #[serde(rename = "SERVER_SUBSCRIBED")]
+    ServerSubscribed { server_id: Uuid },
+
+    /// Unsubscription serveur confirmée
+    #[serde(rename = "SERVER_UNSUBSCRIBED")]
+    ServerUnsubscribed { server_id: Uuid },
+
     /// Mise à jour de présence utilisateur
     #[serde(rename = "PRESENCE_UPDATE")]
     PresenceUpdate {
@@ -168,6 +518,11 @@ impl ClientEvent {
     pub fn from_json(data: &str) -> Result<Self, serde_json::Error> {
         serde_json::from_str(data)
     }
+
+    /// Parse depuis MessagePack (mêmes clés que le JSON, UUID et dates en chaînes)
+    pub fn from_msgpack(data: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+        Self::deserialize(&mut rmp_serde::Deserializer::new(data).with_human_readable())
+    }
 }
 
 impl ServerEvent {
@@ -175,4 +530,69 @@ impl ServerEvent {
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(self)
     }
+
+    /// Événement de dispatch (numéroté et rejouable), par opposition aux réponses de contrôle
+    pub fn is_dispatch(&self) -> bool {
+        !matches!(
+            self,
+            Self::Hello { .. }
+                | Self::Ready { .. }
+                | Self::Resumed { .. }
+                | Self::InvalidSession
+                | Self::Error { .. }
+                | Self::Ack { .. }
+                | Self::HeartbeatAck { .. }
+                | Self::Subscribed { .. }
+                | Self::Unsubscribed { .. }
+                | Self::ServerSubscribed { .. }
+                | Self::ServerUnsubscribed { .. }
+        )
+    }
+}
+
+/// Trame envoyée sur le socket : événement + numéro de séquence `s` (dispatch uniquement)
+#[derive(Debug, Clone)]
+pub struct ServerFrame {
+    pub event: Arc<ServerEvent>,
+    pub s: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct ServerFrameRef<'a> {
+    #[serde(flatten)]
+    event: &'a ServerEvent,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    s: Option<u64>,
+}
+
+impl ServerFrame {
+    pub fn new(event: ServerEvent) -> Self {
+        Self {
+            event: Arc::new(event),
+            s: None,
+        }
+    }
+
+    /// Convertit en JSON pour envoi : `{"op": ..., "d": ..., "s": ...}`
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&self.borrowed())
+    }
+
+    /// Convertit en MessagePack, même structure que le JSON
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        let mut buf = Vec::new();
+        self.borrowed().serialize(
+            &mut rmp_serde::Serializer::new(&mut buf)
+                .with_struct_map()
+                .with_human_readable(),
+        )?;
+        Ok(buf)
+    }
+
+    fn borrowed(&self) -> ServerFrameRef<'_> {
+        ServerFrameRef {
+            event: &self.event,
+            s: self.s,
+        }
+    }
 }
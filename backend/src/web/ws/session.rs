@@ -0,0 +1,158 @@
+//! Sessions gateway : numéros de séquence et buffer de replay pour RESUME
+//! Une session survit à la connexion pendant une période de grâce
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::web::ws::protocol::ServerFrame;
+
+/// ID unique d'une session gateway (renvoyé dans READY)
+pub type SessionId = Uuid;
+
+/// Nombre maximum d'événements conservés pour le replay
+pub const REPLAY_BUFFER_SIZE: usize = 128;
+
+/// Durée pendant laquelle une session détachée peut être reprise
+pub const SESSION_RESUME_GRACE: Duration = Duration::from_secs(60);
+
+/// État d'une session : séquence courante + derniers événements envoyés
+pub struct WsSession {
+    session_id: SessionId,
+    user_id: Uuid,
+    seq: u64,
+    buffer: VecDeque<ServerFrame>,
+    detached_at: Option<Instant>,
+}
+
+impl WsSession {
+    pub fn new(user_id: Uuid) -> Self {
+        Self {
+            session_id: Uuid::new_v4(),
+            user_id,
+            seq: 0,
+            buffer: VecDeque::with_capacity(REPLAY_BUFFER_SIZE),
+            detached_at: None,
+        }
+    }
+
+    pub fn session_id(&self) -> SessionId {
+        self.session_id
+    }
+
+    pub fn user_id(&self) -> Uuid {
+        self.user_id
+    }
+
+    /// Dernier numéro de séquence attribué
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Attribue le prochain numéro de séquence et garde la trame pour le replay
+    pub fn sequence(&mut self, mut frame: ServerFrame) -> ServerFrame {
+        self.seq += 1;
+        frame.s = Some(self.seq);
+
+        if self.buffer.len() == REPLAY_BUFFER_SIZE {
+            self.buffer.pop_front();
+        }
+        self.buffer.push_back(frame.clone());
+
+        frame
+    }
+
+    /// Trames manquées depuis `seq`, ou None si elles ne sont plus dans le buffer
+    pub fn replay_after(&self, seq: u64) -> Option<Vec<ServerFrame>> {
+        if seq > self.seq || seq + (self.buffer.len() as u64) < self.seq {
+            return None;
+        }
+
+        Some(
+            self.buffer
+                .iter()
+                .filter(|frame| frame.s.is_some_and(|s| s > seq))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    pub fn detach(&mut self) {
+        if self.detached_at.is_none() {
+            self.detached_at = Some(Instant::now());
+        }
+    }
+
+    pub fn is_detached(&self) -> bool {
+        self.detached_at.is_some()
+    }
+
+    pub fn attach(&mut self) {
+        self.detached_at = None;
+    }
+
+    /// Vrai si la session est détachée depuis plus longtemps que la période de grâce
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.detached_at
+            .is_some_and(|detached_at| now.duration_since(detached_at) >= SESSION_RESUME_GRACE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::ws::protocol::ServerEvent;
+
+    fn frame(channel_id: Uuid) -> ServerFrame {
+        ServerFrame::new(ServerEvent::MessageDelete {
+            id: Uuid::new_v4(),
+            channel_id,
+        })
+    }
+
+    #[test]
+    fn sequences_frames_monotonically() {
+        let mut session = WsSession::new(Uuid::new_v4());
+        let channel_id = Uuid::new_v4();
+
+        let first = session.sequence(frame(channel_id));
+        let second = session.sequence(frame(channel_id));
+
+        assert_eq!(first.s, Some(1));
+        assert_eq!(second.s, Some(2));
+        assert_eq!(session.seq(), 2);
+    }
+
+    #[test]
+    fn replays_only_frames_after_the_acknowledged_seq() {
+        let mut session = WsSession::new(Uuid::new_v4());
+        let channel_id = Uuid::new_v4();
+        for _ in 0..5 {
+            session.sequence(frame(channel_id));
+        }
+
+        let replay = session
+            .replay_after(3)
+            .expect("frames should still be buffered");
+        let seqs: Vec<_> = replay.iter().filter_map(|frame| frame.s).collect();
+
+        assert_eq!(seqs, vec![4, 5]);
+        assert!(session.replay_after(5).unwrap().is_empty());
+        assert!(session.replay_after(6).is_none());
+    }
+
+    #[test]
+    fn refuses_replay_once_the_buffer_has_rotated() {
+        let mut session = WsSession::new(Uuid::new_v4());
+        let channel_id = Uuid::new_v4();
+        for _ in 0..REPLAY_BUFFER_SIZE + 10 {
+            session.sequence(frame(channel_id));
+        }
+
+        assert!(session.replay_after(5).is_none());
+        assert_eq!(
+            session.replay_after(10).map(|frames| frames.len()),
+            Some(REPLAY_BUFFER_SIZE)
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! États vocaux : qui est connecté à quel channel vocal, via quelle connexion
+//! Chaque instance en garde une copie, tenue à jour par l'EventBus (voir WsHub::voice_join).
+//! Une instance qui démarre part d'un état vide : les présences déjà établies ne lui
+//! sont connues qu'au prochain changement.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::web::ws::hub::ConnectionId;
+
+/// Présence d'un utilisateur dans un channel vocal, via une connexion précise
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoiceState {
+    pub server_id: Uuid,
+    pub channel_id: Uuid,
+    pub conn_id: ConnectionId,
+}
+
+/// Un utilisateur est dans au plus un channel vocal à la fois
+#[derive(Debug, Default)]
+pub struct VoiceStates {
+    by_user: HashMap<Uuid, VoiceState>,
+}
+
+impl VoiceStates {
+    /// Place l'utilisateur dans un channel, retourne l'état qu'il quitte
+    pub fn join(&mut self, user_id: Uuid, state: VoiceState) -> Option<VoiceState> {
+        self.by_user.insert(user_id, state)
+    }
+
+    pub fn leave(&mut self, user_id: Uuid) -> Option<VoiceState> {
+        self.by_user.remove(&user_id)
+    }
+
+    /// Retire l'utilisateur seulement si sa présence vocale passe par cette connexion
+    pub fn leave_connection(&mut self, conn_id: ConnectionId) -> Option<(Uuid, VoiceState)> {
+        let user_id = self
+            .by_user
+            .iter()
+            .find(|(_, state)| state.conn_id == conn_id)
+            .map(|(user_id, _)| *user_id)?;
+        self.by_user.remove(&user_id).map(|state| (user_id, state))
+    }
+
+    /// Vide un channel, retourne les utilisateurs déconnectés
+    pub fn clear_channel(&mut self, channel_id: Uuid) -> Vec<(Uuid, VoiceState)> {
+        let user_ids: Vec<Uuid> = self
+            .by_user
+            .iter()
+            .filter(|(_, state)| state.channel_id == channel_id)
+            .map(|(user_id, _)| *user_id)
+            .collect();
+        user_ids
+            .into_iter()
+            .filter_map(|user_id| self.by_user.remove(&user_id).map(|state| (user_id, state)))
+            .collect()
+    }
+
+    pub fn participants(&self, channel_id: Uuid) -> Vec<Uuid> {
+        let mut participants: Vec<Uuid> = self
+            .by_user
+            .iter()
+            .filter(|(_, state)| state.channel_id == channel_id)
+            .map(|(user_id, _)| *user_id)
+            .collect();
+        participants.sort();
+        participants
+    }
+
+    pub fn state_of(&self, user_id: Uuid) -> Option<VoiceState> {
+        self.by_user.get(&user_id).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice_state(channel_id: Uuid, conn_id: ConnectionId) -> VoiceState {
+        VoiceState {
+            server_id: Uuid::nil(),
+            channel_id,
+            conn_id,
+        }
+    }
+
+    #[test]
+    fn joining_another_channel_moves_the_user() {
+        let mut states = VoiceStates::default();
+        let (user, first, second) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let conn = Uuid::new_v4();
+
+        assert_eq!(states.join(user, voice_state(first, conn)), None);
+        let previous = states.join(user, voice_state(second, conn));
+
+        assert_eq!(previous.map(|state| state.channel_id), Some(first));
+        assert!(states.participants(first).is_empty());
+        assert_eq!(states.participants(second), vec![user]);
+    }
+
+    #[test]
+    fn only_the_voice_connection_releases_the_state() {
+        let mut states = VoiceStates::default();
+        let (user, channel) = (Uuid::new_v4(), Uuid::new_v4());
+        let (voice_conn, other_conn) = (Uuid::new_v4(), Uuid::new_v4());
+        states.join(user, voice_state(channel, voice_conn));
+
+        assert_eq!(states.leave_connection(other_conn), None);
+        assert_eq!(states.participants(channel), vec![user]);
+
+        let removed = states.leave_connection(voice_conn);
+        assert_eq!(removed.map(|(id, _)| id), Some(user));
+        assert!(states.state_of(user).is_none());
+    }
+}